@@ -0,0 +1,147 @@
+//! A record of every change to the task lists in audit.log next to state.json: when,
+//! from where (the window, a capture prompt, the tray, a shortcut or the scheduler),
+//! what it was and which tasks it touched, for tracing a change nobody remembers making
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::done::DateRange;
+use crate::events::{self, TaskChange};
+use crate::search::SearchList;
+use crate::{ensure_tasks_dir, get_tasks_dir, TaskState};
+
+/// Where a change came from
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The main window
+    Ui,
+    /// The quick-add window
+    Capture,
+    /// The tray menu or icon, including drops onto it
+    Tray,
+    /// A global shortcut
+    Hotkey,
+    /// Snoozed tasks waking, recurring tasks and auto-archive
+    Scheduler,
+}
+
+/// How many tasks the lists held
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ListSizes {
+    pub current: usize,
+    pub shelf: usize,
+    pub recurring: usize,
+}
+
+impl ListSizes {
+    fn of(tasks: &TaskState) -> Self {
+        Self {
+            current: tasks.current.len(),
+            shelf: tasks.shelf.len(),
+            recurring: tasks.recurring.len(),
+        }
+    }
+}
+
+/// A line of audit.log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub at: NaiveDateTime,
+    pub source: Source,
+    /// What was done, as the undo menu names it, e.g. "Snooze"
+    pub action: String,
+    pub before: ListSizes,
+    pub after: ListSizes,
+    /// The tasks it touched, e.g. `added "Call Sam" to current`
+    pub changes: Vec<String>,
+}
+
+fn audit_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("audit.log"))
+}
+
+fn list_name(list: SearchList) -> &'static str {
+    match list {
+        SearchList::Current => "current",
+        _ => "the shelf",
+    }
+}
+
+fn describe(change: &TaskChange, task: &str) -> String {
+    match change {
+        TaskChange::Added { list, text } => format!("added \"{}\" to {}", text, list_name(*list)),
+        TaskChange::Edited { from, to } => format!("edited \"{}\" to \"{}\"", from, to),
+        TaskChange::NoteAdded { text } => format!("added note \"{}\" to \"{}\"", text, task),
+        TaskChange::NoteCompleted { text } => format!("checked note \"{}\" of \"{}\"", text, task),
+        TaskChange::NoteReopened { text } => format!("unchecked note \"{}\" of \"{}\"", text, task),
+        TaskChange::NoteRemoved { text } => format!("removed note \"{}\" from \"{}\"", text, task),
+        TaskChange::Moved { to, .. } => format!("moved \"{}\" to {}", task, list_name(*to)),
+        TaskChange::Completed => format!("completed \"{}\"", task),
+        TaskChange::Removed => format!("removed \"{}\"", task),
+    }
+}
+
+fn write(entry: &AuditEntry) -> Result<(), String> {
+    ensure_tasks_dir()?;
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_file()?)
+        .map_err(|e| e.to_string())?;
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Log `action`, from `source`, which took the lists from `before` to `after`. `extra`
+/// adds changes the lists don't show, such as archive files
+pub fn record(source: Source, action: &str, before: &TaskState, after: &TaskState, extra: Vec<String>) {
+    let mut changes: Vec<String> = events::diff(before, after)
+        .iter()
+        .map(|(id, change)| {
+            let task = after
+                .current
+                .iter()
+                .chain(&after.shelf)
+                .chain(before.current.iter().chain(&before.shelf))
+                .find(|t| t.id == Some(*id))
+                .map(|t| t.text.as_str())
+                .unwrap_or_default();
+            describe(change, task)
+        })
+        .collect();
+    if before.recurring != after.recurring {
+        changes.push("changed the recurring tasks".to_string());
+    }
+    changes.extend(extra);
+    let entry = AuditEntry {
+        at: Local::now().naive_local(),
+        source,
+        action: action.to_string(),
+        before: ListSizes::of(before),
+        after: ListSizes::of(after),
+        changes,
+    };
+    if let Err(e) = write(&entry) {
+        eprintln!("Warning: Failed to write audit.log: {}", e);
+    }
+}
+
+/// Changes made on the days in `range`, oldest first; unreadable lines are skipped
+#[tauri::command]
+pub fn get_audit_log(range: DateRange) -> Result<Vec<AuditEntry>, String> {
+    let path = audit_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .filter(|entry| range.contains(entry.at.date()))
+        .collect())
+}
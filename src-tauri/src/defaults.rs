@@ -0,0 +1,74 @@
+//! Defaults that differ by platform. Windows and Linux have no Cmd key and keep their
+//! tray in a different corner than the macOS menu bar, and each OS handles
+//! notifications its own way
+
+use std::collections::HashMap;
+
+use crate::hotkeys::HotkeyAction;
+use crate::pomodoro::PomodoroSounds;
+use crate::window::WindowAnchor;
+
+/// Hyper (all four modifiers) + =
+#[cfg(target_os = "macos")]
+pub const TOGGLE_HOTKEY: &str = "Cmd+Ctrl+Alt+Shift+=";
+/// The Windows and Super keys are mostly claimed by the OS and desktop, so leave them out
+#[cfg(not(target_os = "macos"))]
+pub const TOGGLE_HOTKEY: &str = "Ctrl+Alt+Shift+=";
+
+#[cfg(target_os = "macos")]
+const QUICK_ADD_HOTKEY: &str = "Cmd+Ctrl+Alt+Shift+N";
+#[cfg(not(target_os = "macos"))]
+const QUICK_ADD_HOTKEY: &str = "Ctrl+Alt+Shift+N";
+
+pub fn hotkeys() -> HashMap<HotkeyAction, String> {
+    HashMap::from([
+        (HotkeyAction::Toggle, TOGGLE_HOTKEY.to_string()),
+        (HotkeyAction::QuickAdd, QUICK_ADD_HOTKEY.to_string()),
+    ])
+}
+
+/// Near the tray: the menu bar is at the top on macOS and the taskbar at the bottom on
+/// Windows. Linux panels vary, so it gets the top, where most desktops put theirs
+pub fn window_anchor() -> WindowAnchor {
+    if cfg!(target_os = "windows") {
+        WindowAnchor::BottomRight
+    } else {
+        WindowAnchor::TopRight
+    }
+}
+
+/// Linux can't report idle time, so holding reminders for the user's return is off there
+pub fn idle_minutes() -> u32 {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        5
+    } else {
+        0
+    }
+}
+
+/// Windows keeps every notification in the Action Center until it's dismissed, so
+/// overdue repeats start further apart there
+pub fn overdue_renotify_minutes() -> Vec<u32> {
+    if cfg!(target_os = "windows") {
+        vec![240, 1440]
+    } else {
+        vec![60, 240, 1440]
+    }
+}
+
+/// Sounds each platform ships: macOS system sounds, Windows notification sounds and
+/// freedesktop sound theme names on Linux
+pub fn pomodoro_sounds() -> PomodoroSounds {
+    let (work, short_break, long_break) = if cfg!(target_os = "macos") {
+        ("Glass", "Pop", "Hero")
+    } else if cfg!(target_os = "windows") {
+        ("Reminder", "Default", "Mail")
+    } else {
+        ("alarm-clock-elapsed", "complete", "complete")
+    };
+    PomodoroSounds {
+        work: work.to_string(),
+        short_break: short_break.to_string(),
+        long_break: long_break.to_string(),
+    }
+}
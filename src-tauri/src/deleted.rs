@@ -0,0 +1,120 @@
+//! Tasks deleted from the window, kept in recently_deleted.json next to state.json for
+//! 30 days so they can be recovered after undo no longer reaches them
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::audit;
+use crate::search::SearchList;
+use crate::{
+    ensure_tasks_dir, events, get_tasks_dir, insert_task, notify_tasks_changed, save_tasks, undo, AppState, Task,
+    TaskState,
+};
+
+/// How long a deleted task can be recovered
+const KEEP_DAYS: i64 = 30;
+
+/// A task taken out of the lists without being completed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeletedTask {
+    pub task: Task,
+    /// The list it was in
+    pub list: SearchList,
+    pub deleted_at: NaiveDateTime,
+}
+
+fn deleted_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("recently_deleted.json"))
+}
+
+/// Deleted tasks that can still be recovered at `now`, oldest first
+fn load(now: NaiveDateTime) -> Result<Vec<DeletedTask>, String> {
+    let path = deleted_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let mut deleted: Vec<DeletedTask> = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    deleted.retain(|d| now - d.deleted_at < TimeDelta::days(KEEP_DAYS));
+    Ok(deleted)
+}
+
+fn save(deleted: &[DeletedTask]) -> Result<(), String> {
+    ensure_tasks_dir()?;
+    let content = serde_json::to_string_pretty(deleted).map_err(|e| e.to_string())?;
+    fs::write(deleted_file()?, content).map_err(|e| e.to_string())
+}
+
+/// The tasks in `old` that `new` drops without their having been completed. Called
+/// before `new` is saved, while completions are still told apart
+pub fn dropped(old: &TaskState, new: &TaskState) -> Vec<DeletedTask> {
+    let ids: Vec<u64> = new.current.iter().chain(&new.shelf).filter_map(|t| t.id).collect();
+    let now = Local::now().naive_local();
+    let lists = [(SearchList::Current, &old.current), (SearchList::Shelf, &old.shelf)];
+    lists
+        .into_iter()
+        .flat_map(|(list, tasks)| tasks.iter().map(move |task| (list, task)))
+        .filter(|(_, task)| task.id.is_some_and(|id| !ids.contains(&id) && !events::is_completed(id)))
+        .map(|(list, task)| DeletedTask {
+            task: task.clone(),
+            list,
+            deleted_at: now,
+        })
+        .collect()
+}
+
+/// Keep `dropped` for 30 days
+pub fn keep(dropped: Vec<DeletedTask>) {
+    if dropped.is_empty() {
+        return;
+    }
+    let kept = load(Local::now().naive_local()).and_then(|mut deleted| {
+        deleted.extend(dropped);
+        save(&deleted)
+    });
+    if let Err(e) = kept {
+        eprintln!("Warning: Failed to update recently_deleted.json: {}", e);
+    }
+}
+
+/// Tasks deleted in the last 30 days, newest first
+#[tauri::command]
+pub fn list_recently_deleted() -> Result<Vec<DeletedTask>, String> {
+    let mut deleted = load(Local::now().naive_local())?;
+    deleted.reverse();
+    Ok(deleted)
+}
+
+/// Put the deleted task with id `id` back in the list it was deleted from (current
+/// overflows to the shelf as usual) and return it
+#[tauri::command]
+pub fn recover_deleted(id: u64, app: AppHandle) -> Result<Task, String> {
+    let mut deleted = load(Local::now().naive_local())?;
+    let index = deleted
+        .iter()
+        .rposition(|d| d.task.id == Some(id))
+        .ok_or_else(|| format!("No recently deleted task with id {}", id))?;
+    let recovered = {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if tasks.current.iter().chain(&tasks.shelf).any(|t| t.id == Some(id)) {
+            return Err("That task is back in the lists already".to_string());
+        }
+        let DeletedTask { task, list, .. } = deleted.remove(index);
+        match list {
+            SearchList::Shelf => tasks.shelf.push(task.clone()),
+            _ => insert_task(&mut tasks, task.clone()),
+        }
+        save_tasks(&mut tasks)?;
+        undo::record(&app, audit::Source::Ui, "Recover Deleted", &tasks);
+        task
+    };
+    if let Err(e) = save(&deleted) {
+        eprintln!("Warning: Failed to update recently_deleted.json: {}", e);
+    }
+    notify_tasks_changed(&app);
+    Ok(recovered)
+}
@@ -0,0 +1,1212 @@
+use chrono::{Months, NaiveDate, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{get_done_file, get_done_records_file, get_tasks_dir, locale, Note, Priority, Task};
+
+/// How the done log is written
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DoneFormat {
+    /// "## DATE" headings with a bullet per task
+    #[default]
+    Markdown,
+    /// "* DATE" headlines with a "**" headline per task and checkbox notes
+    Org,
+    /// One row per task: date, time, task, then a column per note
+    Csv,
+}
+
+impl DoneFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DoneFormat::Markdown => "md",
+            DoneFormat::Org => "org",
+            DoneFormat::Csv => "csv",
+        }
+    }
+
+    /// The format a file is in, going by its extension
+    pub fn of_file(name: &str) -> Option<Self> {
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        [DoneFormat::Markdown, DoneFormat::Org, DoneFormat::Csv]
+            .into_iter()
+            .find(|f| name.strip_suffix(f.extension()).is_some_and(|rest| rest.ends_with('.')))
+    }
+
+    /// The ISO date a heading starts with; anything after it, like the date in the
+    /// user's own format, is only for reading
+    fn heading_date(self, line: &str) -> Option<&str> {
+        let prefix = match self {
+            DoneFormat::Markdown => "## ",
+            DoneFormat::Org => "* ",
+            DoneFormat::Csv => return None,
+        };
+        line.strip_prefix(prefix)
+            .and_then(|rest| rest.split_whitespace().next())
+            .filter(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+    }
+}
+
+/// Where the done log lives and how it's written
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct DoneLogConfig {
+    /// File to write to, `~/` allowed; defaults to done.<extension> in ~/.tasks
+    pub path: Option<String>,
+    pub format: DoneFormat,
+}
+
+impl DoneLogConfig {
+    fn file(&self) -> Result<PathBuf, String> {
+        match self.path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            Some(path) => expand_path(path),
+            None => Ok(get_tasks_dir()?.join(format!("done.{}", self.format.extension()))),
+        }
+    }
+
+    /// Check that the path can hold a done log
+    pub fn validate(&self) -> Result<(), String> {
+        let file = self.file()?;
+        if !file.is_absolute() {
+            return Err(format!("Done log path must be absolute or start with ~/: {}", file.display()));
+        }
+        if file.is_dir() {
+            return Err(format!("Done log path is a folder: {}", file.display()));
+        }
+        if !file.parent().is_some_and(|dir| dir.is_dir()) {
+            return Err(format!("Folder for the done log doesn't exist: {}", file.display()));
+        }
+        if file == get_done_records_file()? {
+            return Err("The done log can't replace done.jsonl".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// `path` with a leading `~/` resolved to the home directory
+pub fn expand_path(path: &str) -> Result<PathBuf, String> {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|h| h.join(rest))
+            .ok_or_else(|| "Could not determine home directory".to_string()),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+static LOG: Mutex<DoneLogConfig> = Mutex::new(DoneLogConfig {
+    path: None,
+    format: DoneFormat::Markdown,
+});
+
+/// Use `config` for every read and write of the done log from now on
+pub fn configure(config: &DoneLogConfig) {
+    *LOG.lock().unwrap_or_else(|e| e.into_inner()) = config.clone();
+}
+
+/// Format of the live done log
+pub fn log_format() -> DoneFormat {
+    LOG.lock().unwrap_or_else(|e| e.into_inner()).format
+}
+
+/// Path of the live done log
+pub fn log_file() -> Result<PathBuf, String> {
+    LOG.lock().unwrap_or_else(|e| e.into_inner()).file()
+}
+
+/// File name for an archive of the live done log labelled `label`
+pub fn archive_name(label: &str) -> String {
+    format!("done_{}.{}", label, log_format().extension())
+}
+
+/// Archives sit next to the live done log
+pub fn archive_dir() -> Result<PathBuf, String> {
+    let file = log_file()?;
+    file.parent()
+        .map(|dir| dir.to_path_buf())
+        .ok_or_else(|| format!("No folder for {}", file.display()))
+}
+
+/// A line of done.jsonl: the same completion as in done.md, without the markdown
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DoneRecord {
+    /// Id the task had in the lists, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    pub completed_at: NaiveDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDateTime>,
+    /// `#words` in the text, lowercased and without the `#`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// When the task was added, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDateTime>,
+    /// Seconds the task was timed with `start_timer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_spent: Option<u64>,
+}
+
+impl DoneRecord {
+    pub fn new(task: &Task, completed_at: NaiveDateTime) -> Self {
+        Self {
+            id: task.id,
+            text: task.text.clone(),
+            notes: task.notes.clone(),
+            completed_at,
+            due: task.due,
+            tags: tags(&task.text),
+            project: task.project.clone(),
+            priority: task.priority,
+            created: task.created,
+            time_spent: task.time_spent,
+        }
+    }
+
+    /// The completion as `history` lists it, for records no done log still has
+    pub fn into_match(self) -> DoneMatch {
+        DoneMatch {
+            source: "done.jsonl".to_string(),
+            date: self.completed_at.format("%Y-%m-%d").to_string(),
+            time: Some(self.completed_at.format("%H:%M").to_string()),
+            task: Task {
+                text: self.text,
+                notes: self.notes,
+                due: self.due,
+                project: self.project,
+                priority: self.priority,
+                created: self.created,
+                time_spent: self.time_spent,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// What to do with the project of a completed task that has one
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectLogs {
+    /// Log it like any other task
+    #[default]
+    Off,
+    /// Also log it to `done-<project>.<ext>` next to the done log
+    Separate,
+    /// Log it as "(project) text"
+    Prefix,
+}
+
+/// How a task with `project` reads in the done log under `ProjectLogs::Prefix`
+pub fn prefixed(project: &str, text: &str) -> String {
+    format!("({}) {}", project, text)
+}
+
+/// Texts a completion of `task` may be logged under: as is, or after its project prefix
+pub fn logged_texts(task: &Task) -> Vec<String> {
+    let mut texts = vec![task.text.clone()];
+    if let Some(project) = &task.project {
+        texts.push(prefixed(project, &task.text));
+    }
+    texts
+}
+
+/// Project name made safe for a file name: lowercase letters, digits and dashes
+fn slug(project: &str) -> String {
+    let slug: String = project
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.trim_matches('-').to_string()
+}
+
+/// The `#tags` in `text`, lowercased, in order and without repeats
+pub fn tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let Some(tag) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag = tag.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '-' && c != '_').to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// A completed task read back from done.md
+#[derive(Debug, Serialize, Clone)]
+pub struct DoneEntry {
+    /// Line in done.md where the entry starts, as of reading it
+    pub line: usize,
+    pub date: String,
+    /// Time of day as written in done.md, e.g. "14:32"; older entries have none
+    pub time: Option<String>,
+    pub task: Task,
+    /// Number of lines the entry spans, including its notes
+    #[serde(skip)]
+    len: usize,
+}
+
+impl DoneEntry {
+    /// The completion date, unless it's malformed
+    pub fn day(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.date, "%Y-%m-%d").ok()
+    }
+}
+
+/// Inclusive range of days; an open end is unbounded
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+/// A completed task found by `search`, and the file it was found in
+#[derive(Debug, Serialize, Clone)]
+pub struct DoneMatch {
+    /// The done log's file name, an archive name, or "done.jsonl" for completions no other log still has
+    pub source: String,
+    pub date: String,
+    pub time: Option<String>,
+    pub task: Task,
+}
+
+/// A `done_*.md` file left behind by archiving done.md
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveInfo {
+    pub name: String,
+    /// Dates of the oldest and newest entries, when it has any
+    pub first: Option<NaiveDate>,
+    pub last: Option<NaiveDate>,
+    pub entries: usize,
+    /// Size on disk
+    pub bytes: u64,
+    /// Gzipped by `prune_archives`
+    pub compressed: bool,
+}
+
+/// Limits on how many archives are kept, applied by `prune_archives`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ArchiveRetention {
+    /// Archives whose newest entry is older than this many months are pruned
+    pub keep_months: Option<u32>,
+    /// Past this many megabytes in total, the oldest archives are deleted
+    pub max_total_mb: Option<u64>,
+    /// Gzip archives that are too old instead of deleting them
+    pub compress: bool,
+}
+
+/// Outcome of `dedupe`
+#[derive(Debug, Serialize, Clone)]
+pub struct DedupeReport {
+    /// Entries brought in from the merged file
+    pub merged: usize,
+    /// Duplicates dropped
+    pub removed: usize,
+    /// Entries in the done log afterwards
+    pub total: usize,
+}
+
+/// What `prune_archives` did, or would do on a dry run, to one archive
+#[derive(Debug, Serialize, Clone)]
+pub struct PruneAction {
+    pub name: String,
+    /// "compress" or "delete"
+    pub action: &'static str,
+    pub bytes: u64,
+}
+
+/// First line of a done.md written with `## DATE` headings; files without it
+/// use the older "- DATE: text" bullets and keep that format until archived
+pub const FORMAT_MARKER: &str = "<!-- task_log done.md v2 -->";
+
+const CSV_HEADER: &str = "date,time,task,notes";
+
+fn is_headed(content: &str, format: DoneFormat) -> bool {
+    match format {
+        DoneFormat::Markdown => content.lines().next() == Some(FORMAT_MARKER),
+        DoneFormat::Org => true,
+        DoneFormat::Csv => false,
+    }
+}
+
+/// Parse the entries (and their notes) out of a done log in `format`
+pub fn parse_entries(content: &str, format: DoneFormat) -> Vec<DoneEntry> {
+    match format {
+        DoneFormat::Csv => parse_csv(content),
+        _ => parse_outline(content, format),
+    }
+}
+
+/// Markdown, as bullets under "## DATE" headings or, in older files,
+/// "- DATE: text" bullets; or org headlines under "* DATE"
+fn parse_outline(content: &str, format: DoneFormat) -> Vec<DoneEntry> {
+    let headed = is_headed(content, format);
+    let (bullet_prefix, done_prefix, open_prefix) = match format {
+        DoneFormat::Org => ("** ", "   - [X] ", "   - [ ] "),
+        _ => ("- ", "  ✓ ", "  ○ "),
+    };
+    let mut entries: Vec<DoneEntry> = Vec::new();
+    let mut day: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let bullet = if headed {
+            if let Some(date) = format.heading_date(line) {
+                day = Some(date);
+                continue;
+            }
+            day.zip(line.strip_prefix(bullet_prefix))
+        } else {
+            line.strip_prefix("- ")
+                .and_then(|rest| rest.split_once(": "))
+                .filter(|(date, _)| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+        };
+        if let Some((date, text)) = bullet {
+            let (time, text) = split_time(text);
+            entries.push(DoneEntry {
+                line: i,
+                date: date.to_string(),
+                time: time.map(str::to_string),
+                task: Task {
+                    text: text.to_string(),
+                    ..Default::default()
+                },
+                len: 1,
+            });
+            continue;
+        }
+
+        // Notes only belong to the entry directly above them
+        let Some(last) = entries.last_mut().filter(|e| e.line + e.len == i) else {
+            continue;
+        };
+        let note = if let Some(text) = line.strip_prefix(done_prefix) {
+            Note { text: text.to_string(), completed: true }
+        } else if let Some(text) = line.strip_prefix(open_prefix) {
+            Note { text: text.to_string(), completed: false }
+        } else {
+            continue;
+        };
+        last.task.notes.push(note);
+        last.len += 1;
+    }
+
+    entries
+}
+
+/// Rows of "date,time,task,notes..." with notes written as "✓ text" or "○ text"
+fn parse_csv(content: &str) -> Vec<DoneEntry> {
+    let mut entries = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let fields = split_csv(line);
+        let [date, time, text, notes @ ..] = fields.as_slice() else {
+            continue;
+        };
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            continue;
+        }
+        let notes = notes
+            .iter()
+            .filter_map(|n| {
+                if let Some(text) = n.strip_prefix("✓ ") {
+                    Some(Note { text: text.to_string(), completed: true })
+                } else {
+                    n.strip_prefix("○ ").map(|text| Note { text: text.to_string(), completed: false })
+                }
+            })
+            .collect();
+        entries.push(DoneEntry {
+            line: i,
+            date: date.clone(),
+            time: Some(time.clone()).filter(|t| !t.is_empty()),
+            task: Task {
+                text: text.clone(),
+                notes,
+                ..Default::default()
+            },
+            len: 1,
+        });
+    }
+    entries
+}
+
+/// Fields of one CSV line, with double quotes around fields and "" for a quote inside
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split a "[14:32] text" bullet into its time and text; a bracketed prefix
+/// without a digit is part of the text
+fn split_time(text: &str) -> (Option<&str>, &str) {
+    text.strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(time, _)| time.contains(|c: char| c.is_ascii_digit()))
+        .map_or((None, text), |(time, rest)| (Some(time), rest))
+}
+
+/// The text to append to a done log in `format` (currently `content`) for `task`
+/// completed on `date` at `time`, starting a new day heading on the first
+/// completion of the day. Older markdown files without headings only get the date
+pub fn format_entry(content: &str, date: &str, time: Option<&str>, task: &Task, format: DoneFormat) -> String {
+    let mut entry = String::new();
+
+    if format == DoneFormat::Csv {
+        if content.trim().is_empty() {
+            entry.push_str(CSV_HEADER);
+            entry.push('\n');
+        }
+        let mut fields = vec![date.to_string(), time.unwrap_or_default().to_string(), csv_field(&task.text)];
+        for note in &task.notes {
+            let status = if note.completed { "✓" } else { "○" };
+            fields.push(csv_field(&format!("{} {}", status, note.text)));
+        }
+        entry.push_str(&fields.join(","));
+        entry.push('\n');
+        return entry;
+    }
+
+    let (heading, bullet_prefix) = match format {
+        DoneFormat::Org => ("*", "**"),
+        _ => ("##", "-"),
+    };
+    let bullet = match time {
+        Some(time) => format!("{} [{}] {}\n", bullet_prefix, time, task.text),
+        None => format!("{} {}\n", bullet_prefix, task.text),
+    };
+    // Headings keep the ISO date the log is read by, and add the date as the user writes it
+    let title = match NaiveDate::parse_from_str(date, "%Y-%m-%d").map(locale::date) {
+        Ok(shown) if shown != date => format!("{} ({})", date, shown),
+        _ => date.to_string(),
+    };
+
+    if content.trim().is_empty() {
+        if format == DoneFormat::Markdown {
+            entry.push_str(&format!("{}\n\n", FORMAT_MARKER));
+        }
+        entry.push_str(&format!("{} {}\n", heading, title));
+        entry.push_str(&bullet);
+    } else if is_headed(content, format) {
+        if content.lines().rev().find_map(|l| format.heading_date(l)) != Some(date) {
+            entry.push_str(&format!("\n{} {}\n", heading, title));
+        }
+        entry.push_str(&bullet);
+    } else {
+        entry.push_str(&format!("- {}: {}\n", date, task.text));
+    }
+
+    for note in &task.notes {
+        let line = match (format, note.completed) {
+            (DoneFormat::Org, true) => format!("   - [X] {}\n", note.text),
+            (DoneFormat::Org, false) => format!("   - [ ] {}\n", note.text),
+            (_, true) => format!("  ✓ {}\n", note.text),
+            (_, false) => format!("  ○ {}\n", note.text),
+        };
+        entry.push_str(&line);
+    }
+    entry
+}
+
+pub fn read_done() -> Result<String, String> {
+    let path = get_done_file()?;
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+/// Append what `entry` makes of the file's current content, holding an exclusive
+/// lock from the read to the write so another instance, a CLI or a sync tool that
+/// also locks can't add an entry in between or interleave with this one
+fn append_locked(path: &Path, entry: impl FnOnce(&str) -> String) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    file.lock().map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    // A single append, so even writers that ignore the lock can't split the entry
+    file.write_all(entry(&content).as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Replace the file's content with what `rewrite` makes of it, if anything, under
+/// the same lock as `append_locked`. Rewritten in place, since replacing the file
+/// would leave other writers holding a lock on the old one
+fn rewrite_locked(path: &Path, rewrite: impl FnOnce(&str) -> Result<Option<String>, String>) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    file.lock().map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+    let mut content = String::new();
+    file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    let Some(content) = rewrite(&content)? else {
+        return Ok(());
+    };
+    file.set_len(0).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    file.write_all(content.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Log `task`, completed on `date` at `time`, to the done log
+pub fn append_entry(date: &str, time: Option<&str>, task: &Task) -> Result<(), String> {
+    let format = log_format();
+    append_locked(&log_file()?, |content| format_entry(content, date, time, task, format))
+}
+
+/// Every entry in the done log, oldest first
+pub fn entries() -> Result<Vec<DoneEntry>, String> {
+    Ok(parse_entries(&read_done()?, log_format()))
+}
+
+/// The most recently completed entries, newest first
+pub fn recent_entries(limit: usize) -> Result<Vec<DoneEntry>, String> {
+    let entries = parse_entries(&read_done()?, log_format());
+    Ok(entries.into_iter().rev().take(limit).collect())
+}
+
+/// Entries completed on `date` (YYYY-MM-DD), oldest first
+pub fn entries_on(date: &str) -> Result<Vec<DoneEntry>, String> {
+    Ok(parse_entries(&read_done()?, log_format()).into_iter().filter(|e| e.date == date).collect())
+}
+
+/// How many tasks were completed on `date` (YYYY-MM-DD)
+pub fn completed_on(date: &str) -> Result<usize, String> {
+    Ok(entries_on(date)?.len())
+}
+
+/// Date of the oldest entry still in done.md
+pub fn oldest_date() -> Result<Option<NaiveDate>, String> {
+    Ok(parse_entries(&read_done()?, log_format()).iter().filter_map(DoneEntry::day).min())
+}
+
+fn is_archive_name(name: &str) -> bool {
+    name.starts_with("done_") && DoneFormat::of_file(name).is_some() && !name.contains(['/', '\\'])
+}
+
+/// Whether `name` is a project log archived by `archive_project_logs`, whose entries
+/// the main archive from the same moment also has
+pub fn is_project_archive(name: &str) -> bool {
+    let stem = name.trim_end_matches(".gz").rsplit_once('.').map_or(name, |(stem, _)| stem);
+    // done_<date>_<time>_<project>
+    stem.strip_prefix("done_").is_some_and(|rest| rest.split('_').count() > 2)
+}
+
+/// Parse an archive in whichever format its extension says
+fn parse_archive(name: &str, content: &str) -> Vec<DoneEntry> {
+    parse_entries(content, DoneFormat::of_file(name).unwrap_or_default())
+}
+
+/// Name and path of every archive next to the done log
+pub fn archive_paths() -> Result<Vec<(String, PathBuf)>, String> {
+    let dir = archive_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut archives = Vec::new();
+    for item in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = item.map_err(|e| e.to_string())?.path();
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()).filter(|n| is_archive_name(n)) {
+            archives.push((name.to_string(), path.clone()));
+        }
+    }
+    Ok(archives)
+}
+
+/// Name and content of every archive; unreadable ones are skipped
+fn read_archives() -> Result<Vec<(String, String)>, String> {
+    let mut archives = Vec::new();
+    for (name, path) in archive_paths()? {
+        match read_archive_file(&path) {
+            Ok(content) => archives.push((name, content)),
+            Err(e) => eprintln!("Warning: Failed to read archive {}: {}", name, e),
+        }
+    }
+    Ok(archives)
+}
+
+/// Content of an archive, unzipping it if it was compressed
+pub fn read_archive_file(path: &Path) -> Result<String, String> {
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let file = fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut content = String::new();
+        GzDecoder::new(file).read_to_string(&mut content).map_err(|e| e.to_string())?;
+        Ok(content)
+    } else {
+        fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+}
+
+/// The archives next to the done log, newest first
+pub fn list_archives() -> Result<Vec<ArchiveInfo>, String> {
+    let dir = archive_dir()?;
+    let mut archives: Vec<ArchiveInfo> = read_archives()?
+        .into_iter()
+        .map(|(name, content)| {
+            let entries = parse_archive(&name, &content);
+            let dates: Vec<NaiveDate> = entries.iter().filter_map(|e| e.day()).collect();
+            ArchiveInfo {
+                first: dates.iter().min().copied(),
+                last: dates.iter().max().copied(),
+                entries: entries.len(),
+                bytes: fs::metadata(dir.join(&name)).map(|m| m.len()).unwrap_or(0),
+                compressed: name.ends_with(".gz"),
+                name,
+            }
+        })
+        .collect();
+
+    archives.sort_by(|a, b| b.last.cmp(&a.last).then_with(|| b.name.cmp(&a.name)));
+    Ok(archives)
+}
+
+/// The entries in the archive called `name`, oldest first
+pub fn read_archive(name: &str) -> Result<Vec<DoneEntry>, String> {
+    if !is_archive_name(name) {
+        return Err(format!("Not an archive: {}", name));
+    }
+    let path = archive_dir()?.join(name);
+    let content = read_archive_file(&path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(parse_archive(name, &content))
+}
+
+/// Apply `retention` to the archives, oldest first: those past `keep_months` are
+/// compressed or deleted, then the oldest are deleted until the total fits
+/// `max_total_mb`. With `dry_run` nothing changes; either way the actions are returned
+pub fn prune_archives(
+    retention: &ArchiveRetention,
+    today: NaiveDate,
+    dry_run: bool,
+) -> Result<Vec<PruneAction>, String> {
+    let mut archives = list_archives()?;
+    archives.reverse();
+    let cutoff = retention
+        .keep_months
+        .and_then(|months| today.checked_sub_months(Months::new(months)));
+
+    let mut actions = Vec::new();
+    let mut total: u64 = archives.iter().map(|a| a.bytes).sum();
+    let mut kept = Vec::new();
+    for archive in archives {
+        let expired = cutoff.is_some_and(|cutoff| archive.last.is_none_or(|last| last < cutoff));
+        if !expired || (retention.compress && archive.compressed) {
+            kept.push(archive);
+            continue;
+        }
+        if retention.compress {
+            // Roughly what gzip leaves of plain text, until it's actually done
+            let compressed = archive.bytes / 4;
+            total -= archive.bytes - compressed;
+            actions.push(PruneAction { name: archive.name.clone(), action: "compress", bytes: archive.bytes });
+            kept.push(ArchiveInfo { bytes: compressed, ..archive });
+        } else {
+            total -= archive.bytes;
+            actions.push(PruneAction { name: archive.name, action: "delete", bytes: archive.bytes });
+        }
+    }
+
+    if let Some(limit) = retention.max_total_mb.map(|mb| mb * 1024 * 1024) {
+        for archive in kept {
+            if total <= limit {
+                break;
+            }
+            total -= archive.bytes;
+            actions.retain(|a| a.name != archive.name);
+            actions.push(PruneAction { name: archive.name, action: "delete", bytes: archive.bytes });
+        }
+    }
+
+    if dry_run {
+        return Ok(actions);
+    }
+    let dir = archive_dir()?;
+    for action in &actions {
+        let path = dir.join(&action.name);
+        if action.action == "compress" {
+            compress_file(&path)?;
+        } else {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", action.name, e))?;
+        }
+    }
+    Ok(actions)
+}
+
+/// Replace `path` with a gzipped `<path>.gz`
+fn compress_file(path: &Path) -> Result<(), String> {
+    let content = fs::read(path).map_err(|e| e.to_string())?;
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+    let mut encoder = GzEncoder::new(fs::File::create(&gz_path).map_err(|e| e.to_string())?, Compression::default());
+    encoder.write_all(&content).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())?;
+    fs::remove_file(path).map_err(|e| e.to_string())
+}
+
+/// Move entries from months before `today`'s out of the done log into one
+/// `done_YYYY-MM.<ext>` per month, adding to any that already exist; returns their names
+pub fn rotate_months(today: NaiveDate) -> Result<Vec<String>, String> {
+    let path = log_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut written = Vec::new();
+    rewrite_locked(&path, |content| rotate_content(content, today, &mut written))?;
+    Ok(written)
+}
+
+/// `rotate_months` on the done log's `content`: writes the monthly archives and
+/// returns what stays in the done log
+fn rotate_content(content: &str, today: NaiveDate, written: &mut Vec<String>) -> Result<Option<String>, String> {
+    let format = log_format();
+    let this_month = today.format("%Y-%m").to_string();
+    // Entries with a date that doesn't parse stay where they are
+    let month = |e: &DoneEntry| e.day().map(|day| day.format("%Y-%m").to_string());
+    let (earlier, current): (Vec<DoneEntry>, Vec<DoneEntry>) =
+        parse_entries(content, format).into_iter().partition(|e| month(e).is_some_and(|m| m < this_month));
+    if earlier.is_empty() {
+        return Ok(None);
+    }
+
+    let mut by_month: BTreeMap<String, Vec<DoneEntry>> = BTreeMap::new();
+    for entry in earlier {
+        by_month.entry(month(&entry).unwrap_or_default()).or_default().push(entry);
+    }
+
+    let dir = archive_dir()?;
+    for (month, entries) in by_month {
+        let name = archive_name(&month);
+        let path = dir.join(&name);
+        let mut content = if path.exists() {
+            fs::read_to_string(&path).map_err(|e| e.to_string())?
+        } else {
+            String::new()
+        };
+        for entry in entries {
+            let text = format_entry(&content, &entry.date, entry.time.as_deref(), &entry.task, format);
+            content.push_str(&text);
+        }
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+        written.push(name);
+    }
+
+    let mut remaining = String::new();
+    for entry in current {
+        let text = format_entry(&remaining, &entry.date, entry.time.as_deref(), &entry.task, format);
+        remaining.push_str(&text);
+    }
+    Ok(Some(remaining))
+}
+
+/// Text compared for duplicates: case, and spacing within and around it, don't matter
+pub fn normalized(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Drop entries of the done log that repeat an earlier one from the same day
+/// (ignoring case and spacing), keeping the notes of both, after first merging in
+/// the entries of the done log at `merge_from`, if given. Rewrites the file, so
+/// anything in it besides entries is lost
+pub fn dedupe(merge_from: Option<&Path>) -> Result<DedupeReport, String> {
+    let format = log_format();
+    let other = match merge_from {
+        Some(path) => {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            let content = read_archive_file(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            parse_entries(&content, DoneFormat::of_file(name).unwrap_or(format))
+        }
+        None => Vec::new(),
+    };
+
+    let path = log_file()?;
+    if !path.exists() {
+        fs::write(&path, "").map_err(|e| e.to_string())?;
+    }
+    let mut report = None;
+    rewrite_locked(&path, |content| {
+        let (rewritten, result) = dedupe_content(content, other, format);
+        report = Some(result);
+        Ok(rewritten)
+    })?;
+    report.ok_or_else(|| "Failed to dedupe the done log".to_string())
+}
+
+/// `dedupe` on the done log's `content` with `other` merged in; returns the new
+/// content if anything changed
+fn dedupe_content(content: &str, other: Vec<DoneEntry>, format: DoneFormat) -> (Option<String>, DedupeReport) {
+    let mut entries = parse_entries(content, format);
+    let merged = other.len();
+    if merged > 0 {
+        entries.extend(other);
+        entries.sort_by(|a, b| a.date.cmp(&b.date));
+    }
+
+    let before = entries.len();
+    let mut kept: Vec<DoneEntry> = Vec::new();
+    for entry in entries {
+        let key = normalized(&entry.task.text);
+        match kept.iter_mut().find(|k| k.date == entry.date && normalized(&k.task.text) == key) {
+            Some(first) => {
+                for note in entry.task.notes {
+                    if !first.task.notes.iter().any(|n| n.text == note.text) {
+                        first.task.notes.push(note);
+                    }
+                }
+            }
+            None => kept.push(entry),
+        }
+    }
+
+    let removed = before - kept.len();
+    let report = DedupeReport { merged, removed, total: kept.len() };
+    if removed == 0 && merged == 0 {
+        return (None, report);
+    }
+    let mut content = String::new();
+    for entry in &kept {
+        let text = format_entry(&content, &entry.date, entry.time.as_deref(), &entry.task, format);
+        content.push_str(&text);
+    }
+    (Some(content), report)
+}
+
+/// Every completion in `range` from the done log, every archive (but project archives,
+/// which repeat the main ones) and done.jsonl, oldest first; a task logged in more
+/// than one of them on the same day counts once
+pub fn history(range: DateRange) -> Result<Vec<DoneMatch>, String> {
+    let live = log_file()?.file_name().and_then(|n| n.to_str()).unwrap_or("done.md").to_string();
+    let mut sources = vec![(live, read_done()?, log_format())];
+    let archives = read_archives()?.into_iter().filter(|(name, _)| !is_project_archive(name));
+    sources.extend(archives.map(|(name, content)| {
+        let format = DoneFormat::of_file(&name).unwrap_or_default();
+        (name, content, format)
+    }));
+
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    for (source, content, format) in sources {
+        for entry in parse_entries(&content, format) {
+            seen.insert((entry.date.clone(), entry.task.text.clone()));
+            if entry.day().is_some_and(|d| range.contains(d)) {
+                found.push(DoneMatch {
+                    source: source.clone(),
+                    date: entry.date,
+                    time: entry.time,
+                    task: entry.task,
+                });
+            }
+        }
+    }
+
+    // The structured log also covers archives that have since been deleted
+    for record in read_records()? {
+        if !range.contains(record.completed_at.date()) {
+            continue;
+        }
+        let m = record.into_match();
+        if !logged_texts(&m.task).into_iter().any(|text| seen.contains(&(m.date.clone(), text))) {
+            found.push(m);
+        }
+    }
+
+    found.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(found)
+}
+
+/// `content` without `entry`, and without its day heading if that's left empty
+fn without_entry(content: &str, entry: &DoneEntry, format: DoneFormat) -> String {
+    let mut lines: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .filter(|(i, _)| *i < entry.line || *i >= entry.line + entry.len)
+        .map(|(_, l)| l)
+        .collect();
+
+    // Drop the day's heading, and the blank line above it, once its last entry is gone
+    if is_headed(content, format) {
+        let is_heading = |l: &&str| format.heading_date(l).is_some();
+        let heading = lines[..entry.line].iter().rposition(is_heading);
+        let emptied = heading.filter(|&h| lines.get(h + 1).is_none_or(|l| l.trim().is_empty() || is_heading(l)));
+        if let Some(h) = emptied {
+            lines.remove(h);
+            if h > 0 && lines[h - 1].trim().is_empty() {
+                lines.remove(h - 1);
+            }
+        }
+    }
+
+    let mut remaining: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+    if remaining.trim().is_empty() || remaining.trim() == FORMAT_MARKER || remaining.trim() == CSV_HEADER {
+        remaining.clear();
+    }
+    remaining
+}
+
+/// Remove the entry of `text` completed on `date` from the done log and return it. `line` is
+/// where it was read, if it was; otherwise, or when other entries have come and gone since,
+/// the latest match is taken
+pub fn remove_entry(line: Option<usize>, date: &str, text: &str) -> Result<DoneEntry, String> {
+    let format = log_format();
+    let not_found = || "Completed task not found in done log".to_string();
+    let path = get_done_file()?;
+    if !path.exists() {
+        return Err(not_found());
+    }
+
+    let mut removed = None;
+    rewrite_locked(&path, |content| {
+        let mut matching: Vec<DoneEntry> =
+            parse_entries(content, format).into_iter().filter(|e| e.date == date && e.task.text == text).collect();
+        let at_line = matching.iter().position(|e| Some(e.line) == line);
+        let i = at_line.or(matching.len().checked_sub(1)).ok_or_else(not_found)?;
+        let entry = matching.swap_remove(i);
+        let remaining = without_entry(content, &entry, format);
+        removed = Some(entry);
+        Ok(Some(remaining))
+    })?;
+    removed.ok_or_else(not_found)
+}
+
+/// The separate log for `project`, next to the done log
+fn project_log_file(project: &str) -> Result<PathBuf, String> {
+    Ok(archive_dir()?.join(format!("done-{}.{}", slug(project), log_format().extension())))
+}
+
+/// Also log `task` to its project's own done log
+pub fn append_project_entry(project: &str, date: &str, time: Option<&str>, task: &Task) -> Result<(), String> {
+    let format = log_format();
+    append_locked(&project_log_file(project)?, |content| format_entry(content, date, time, task, format))
+}
+
+/// Take the latest entry of `text` on `date` out of `project`'s own log, if it has one
+pub fn remove_project_entry(project: &str, date: &str, text: &str) -> Result<(), String> {
+    let path = project_log_file(project)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let format = log_format();
+    rewrite_locked(&path, |content| {
+        let entry = parse_entries(content, format)
+            .into_iter()
+            .rev()
+            .find(|e| e.date == date && e.task.text == text);
+        Ok(entry.map(|entry| without_entry(content, &entry, format)))
+    })
+}
+
+/// Archive every project log alongside the main one as `done_<stamp>_<project>.<ext>`
+/// and empty them
+pub fn archive_project_logs(stamp: &str) -> Result<Vec<ArchivedLog>, String> {
+    let dir = archive_dir()?;
+    let extension = log_format().extension();
+    let mut archived = Vec::new();
+    for item in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let path = item.map_err(|e| e.to_string())?.path();
+        let Some(project) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("done-"))
+            .and_then(|n| n.strip_suffix(extension))
+            .and_then(|n| n.strip_suffix('.'))
+        else {
+            continue;
+        };
+        if fs::metadata(&path).map(|m| m.len()).unwrap_or(0) == 0 {
+            continue;
+        }
+        let archive = dir.join(archive_name(&format!("{}_{}", stamp, project)));
+        let len = archive_log(&path, &archive)?;
+        archived.push(ArchivedLog { log: path, archive, len });
+    }
+    Ok(archived)
+}
+
+/// Copy the log at `path` into `archive` and empty it, holding the log's lock throughout
+/// so a completion logged meanwhile isn't lost; returns the bytes archived
+pub fn archive_log(path: &Path, archive: &Path) -> Result<u64, String> {
+    let mut len = 0;
+    rewrite_locked(path, |content| {
+        fs::write(archive, content).map_err(|e| e.to_string())?;
+        len = content.len() as u64;
+        Ok(Some(String::new()))
+    })?;
+    Ok(len)
+}
+
+/// A done log copied into an archive and emptied, remembered so it can be undone
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArchivedLog {
+    pub log: PathBuf,
+    pub archive: PathBuf,
+    /// Bytes at the start of the archive that came from the log; a summary may follow
+    pub len: u64,
+}
+
+impl ArchivedLog {
+    fn changed(&self) -> String {
+        format!("{} has changed since it was archived", self.archive.display())
+    }
+
+    /// Put the archived entries back at the top of the log, ahead of any logged since,
+    /// and delete the archive
+    pub fn unarchive(&self) -> Result<(), String> {
+        let archived =
+            fs::read(&self.archive).map_err(|e| format!("Failed to read {}: {}", self.archive.display(), e))?;
+        let archived = archived.get(..self.len as usize).ok_or_else(|| self.changed())?;
+        let archived = String::from_utf8(archived.to_vec()).map_err(|_| self.changed())?;
+        let format = log_format();
+        rewrite_locked(&self.log, |since| {
+            let mut content = archived;
+            for entry in parse_entries(since, format) {
+                let logged = format_entry(&content, &entry.date, entry.time.as_deref(), &entry.task, format);
+                content.push_str(&logged);
+            }
+            Ok(Some(content))
+        })?;
+        fs::remove_file(&self.archive).map_err(|e| format!("Failed to remove {}: {}", self.archive.display(), e))
+    }
+
+    /// Archive again what `unarchive` put back, leaving the entries logged since in the log
+    pub fn rearchive(&self) -> Result<(), String> {
+        let format = log_format();
+        rewrite_locked(&self.log, |content| {
+            let head = content.get(..self.len as usize).ok_or_else(|| self.changed())?;
+            let kept = parse_entries(head, format).len();
+            let mut rest = String::new();
+            for entry in parse_entries(content, format).into_iter().skip(kept) {
+                let logged = format_entry(&rest, &entry.date, entry.time.as_deref(), &entry.task, format);
+                rest.push_str(&logged);
+            }
+            fs::write(&self.archive, head).map_err(|e| format!("Failed to write {}: {}", self.archive.display(), e))?;
+            Ok(Some(rest))
+        })
+    }
+}
+
+/// Add a completion to done.jsonl
+pub fn append_record(record: &DoneRecord) -> Result<(), String> {
+    let mut line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    line.push('\n');
+
+    let path = get_done_records_file()?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    file.lock().map_err(|e| format!("Failed to lock {}: {}", path.display(), e))?;
+    file.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Every completion in done.jsonl, oldest first; unreadable lines are skipped
+pub fn read_records() -> Result<Vec<DoneRecord>, String> {
+    let path = get_done_records_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(parse_records(&content))
+}
+
+/// The records in `content`, lines of done.jsonl; unreadable ones are skipped
+pub fn parse_records(content: &str) -> Vec<DoneRecord> {
+    content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter_map(|l| match serde_json::from_str(l) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                eprintln!("Warning: Skipping unreadable line in done.jsonl: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Drop the latest record of `text` completed on `date` (YYYY-MM-DD) from done.jsonl,
+/// keeping it in step with an entry taken out of done.md; returns the record removed
+pub fn remove_record(text: &str, date: &str) -> Result<Option<DoneRecord>, String> {
+    let path = get_done_records_file()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut removed = None;
+    rewrite_locked(&path, |content| {
+        let lines: Vec<&str> = content.lines().collect();
+        let found = lines.iter().enumerate().rev().find_map(|(i, l)| {
+            serde_json::from_str::<DoneRecord>(l)
+                .ok()
+                .filter(|r| {
+                    let logged_as = r.project.as_deref().map(|p| prefixed(p, &r.text));
+                    (r.text == text || logged_as.as_deref() == Some(text))
+                        && r.completed_at.format("%Y-%m-%d").to_string() == date
+                })
+                .map(|r| (i, r))
+        });
+        let Some((found, record)) = found else {
+            return Ok(None);
+        };
+        removed = Some(record);
+        Ok(Some(
+            lines
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != found)
+                .map(|(_, l)| format!("{}\n", l))
+                .collect(),
+        ))
+    })?;
+    Ok(removed)
+}
@@ -0,0 +1,76 @@
+//! Near-duplicate tasks in `current` and on the shelf
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::search::SearchList;
+use crate::TaskState;
+
+/// A task that another one duplicates
+#[derive(Debug, Serialize, Clone)]
+pub struct Duplicate {
+    pub list: SearchList,
+    /// Position in `current` or `shelf`
+    pub index: usize,
+    pub text: String,
+}
+
+/// Tasks whose texts only differ in case, spacing or punctuation
+#[derive(Debug, Serialize, Clone)]
+pub struct DuplicateGroup {
+    /// The text as the first of them writes it
+    pub text: String,
+    pub tasks: Vec<Duplicate>,
+}
+
+/// What two texts compare as: their words, lowercased and without punctuation, so
+/// "Call Bob!" and "call  bob" are the same task
+fn key(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn open_tasks(state: &TaskState) -> impl Iterator<Item = Duplicate> + '_ {
+    let lists = [(SearchList::Current, &state.current), (SearchList::Shelf, &state.shelf)];
+    lists.into_iter().flat_map(|(list, tasks)| {
+        tasks.iter().enumerate().map(move |(index, task)| Duplicate {
+            list,
+            index,
+            text: task.text.clone(),
+        })
+    })
+}
+
+/// Tasks in `current` or on the shelf that `text` would duplicate
+pub fn matching(state: &TaskState, text: &str) -> Vec<Duplicate> {
+    let wanted = key(text);
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+    open_tasks(state).filter(|task| key(&task.text) == wanted).collect()
+}
+
+/// Every set of two or more open tasks that duplicate each other, in list order
+pub fn find_duplicates(state: &TaskState) -> Vec<DuplicateGroup> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for task in open_tasks(state) {
+        let key = key(&task.text);
+        if key.is_empty() {
+            continue;
+        }
+        let group = *by_key.entry(key).or_insert_with(|| {
+            groups.push(DuplicateGroup {
+                text: task.text.clone(),
+                tasks: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[group].tasks.push(task);
+    }
+    groups.retain(|group| group.tasks.len() > 1);
+    groups
+}
@@ -0,0 +1,319 @@
+//! What happened to each task over its life, in events.jsonl next to state.json: when
+//! it was added, edited, given notes, moved between lists, and completed or removed.
+//! Every save of the lists is compared with the one before it to find the changes, and
+//! replaying them from a snapshot, or from the tasks the log began with, gives back the
+//! lists as they were at any moment since
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::search::SearchList;
+use crate::{ensure_tasks_dir, get_tasks_dir, snapshots, Note, Task, TaskState};
+
+/// One thing that happened to a task
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TaskChange {
+    Added { list: SearchList, text: String },
+    Edited { from: String, to: String },
+    NoteAdded { text: String },
+    NoteCompleted { text: String },
+    NoteReopened { text: String },
+    NoteRemoved { text: String },
+    Moved { from: SearchList, to: SearchList },
+    Completed,
+    /// Deleted, or taken out of the lists some other way than completing it
+    Removed,
+}
+
+/// A line of events.jsonl
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskEvent {
+    pub at: NaiveDateTime,
+    /// Id of the task
+    pub task: u64,
+    #[serde(flatten)]
+    pub change: TaskChange,
+}
+
+struct Journal {
+    /// The lists as last saved, to compare the next save with
+    saved: Option<TaskState>,
+    /// Tasks logged as completed that haven't left the lists yet
+    completed: Vec<u64>,
+}
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal {
+    saved: None,
+    completed: Vec::new(),
+});
+
+fn events_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("events.jsonl"))
+}
+
+fn append(events: &[TaskEvent]) -> Result<(), String> {
+    ensure_tasks_dir()?;
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(events_file()?)
+        .map_err(|e| e.to_string())?;
+    file.write_all(lines.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Every event logged, oldest first; unreadable lines are skipped
+fn read() -> Result<Vec<TaskEvent>, String> {
+    let path = events_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<TaskEvent>(line).ok())
+        .collect())
+}
+
+/// Tasks with an id, in list order, with the list they're in
+fn placed(state: &TaskState) -> impl Iterator<Item = (u64, (SearchList, &Task))> {
+    let current = state.current.iter().map(|t| (SearchList::Current, t));
+    let shelf = state.shelf.iter().map(|t| (SearchList::Shelf, t));
+    current.chain(shelf).filter_map(|(list, t)| Some((t.id?, (list, t))))
+}
+
+/// What changed about `new` since it was `old`
+fn task_changes(old: &Task, old_list: SearchList, new: &Task, list: SearchList) -> Vec<TaskChange> {
+    let mut changes = Vec::new();
+    if old.text != new.text {
+        changes.push(TaskChange::Edited {
+            from: old.text.clone(),
+            to: new.text.clone(),
+        });
+    }
+    for note in &new.notes {
+        let text = note.text.clone();
+        match old.notes.iter().find(|n| n.text == note.text) {
+            None => changes.push(TaskChange::NoteAdded { text }),
+            Some(was) if was.completed != note.completed => changes.push(if note.completed {
+                TaskChange::NoteCompleted { text }
+            } else {
+                TaskChange::NoteReopened { text }
+            }),
+            Some(_) => {}
+        }
+    }
+    for note in old.notes.iter().filter(|n| !new.notes.iter().any(|m| m.text == n.text)) {
+        changes.push(TaskChange::NoteRemoved {
+            text: note.text.clone(),
+        });
+    }
+    if old_list != list {
+        changes.push(TaskChange::Moved { from: old_list, to: list });
+    }
+    changes
+}
+
+/// What happened to each task with an id between `old` and `new`; tasks that left the
+/// lists are `Removed`, whether or not they were completed
+pub fn diff(old: &TaskState, new: &TaskState) -> Vec<(u64, TaskChange)> {
+    let before: HashMap<u64, (SearchList, &Task)> = placed(old).collect();
+    let after: HashSet<u64> = placed(new).map(|(id, _)| id).collect();
+    let mut changes = Vec::new();
+    for (id, (list, task)) in placed(new) {
+        match before.get(&id) {
+            Some((old_list, old)) => {
+                changes.extend(task_changes(old, *old_list, task, list).into_iter().map(|c| (id, c)));
+            }
+            None => changes.push((
+                id,
+                TaskChange::Added {
+                    list,
+                    text: task.text.clone(),
+                },
+            )),
+        }
+    }
+    for (id, _) in placed(old).filter(|(id, _)| !after.contains(id)) {
+        changes.push((id, TaskChange::Removed));
+    }
+    changes
+}
+
+/// Log what changed between the lists as last saved and `state`, which was just saved
+pub fn record(state: &TaskState) {
+    let mut journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(saved) = journal.saved.replace(state.clone()) else {
+        return;
+    };
+    let at = Local::now().naive_local();
+    let mut events = Vec::new();
+    for (id, change) in diff(&saved, state) {
+        // Completions are logged when they happen
+        if change == TaskChange::Removed {
+            if let Some(i) = journal.completed.iter().position(|c| *c == id) {
+                journal.completed.remove(i);
+                continue;
+            }
+        }
+        events.push(TaskEvent { at, task: id, change });
+    }
+    if events.is_empty() {
+        return;
+    }
+    if let Err(e) = append(&events) {
+        eprintln!("Warning: Failed to write events.jsonl: {}", e);
+    }
+}
+
+/// Log that `task` was completed; its leaving the lists isn't logged again
+pub fn completed(task: &Task) {
+    let Some(id) = task.id else {
+        return;
+    };
+    JOURNAL.lock().unwrap_or_else(|e| e.into_inner()).completed.push(id);
+    let event = TaskEvent {
+        at: Local::now().naive_local(),
+        task: id,
+        change: TaskChange::Completed,
+    };
+    if let Err(e) = append(&[event]) {
+        eprintln!("Warning: Failed to write events.jsonl: {}", e);
+    }
+}
+
+/// Whether `task_id` was logged as completed and hasn't left the lists since
+pub fn is_completed(task_id: u64) -> bool {
+    JOURNAL.lock().unwrap_or_else(|e| e.into_inner()).completed.contains(&task_id)
+}
+
+/// Every task in `state` logged as added at `at`, with its notes, so replaying from the
+/// start of events.jsonl begins with the lists as they were when it was created
+fn baseline(state: &TaskState, at: NaiveDateTime) -> Vec<TaskEvent> {
+    let mut events = Vec::new();
+    for (id, (list, task)) in placed(state) {
+        let text = task.text.clone();
+        events.push(TaskEvent {
+            at,
+            task: id,
+            change: TaskChange::Added { list, text },
+        });
+        for note in &task.notes {
+            let text = note.text.clone();
+            events.push(TaskEvent {
+                at,
+                task: id,
+                change: TaskChange::NoteAdded { text: text.clone() },
+            });
+            if note.completed {
+                events.push(TaskEvent {
+                    at,
+                    task: id,
+                    change: TaskChange::NoteCompleted { text },
+                });
+            }
+        }
+    }
+    events
+}
+
+/// Compare the next save with `state`, as just loaded from disk. Starts events.jsonl
+/// with the tasks already there if it doesn't exist yet
+pub fn start(state: &TaskState) {
+    let mut journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    journal.saved = Some(state.clone());
+    journal.completed.clear();
+    if events_file().is_ok_and(|path| !path.exists()) {
+        let events = baseline(state, Local::now().naive_local());
+        if !events.is_empty() {
+            if let Err(e) = append(&events) {
+                eprintln!("Warning: Failed to write events.jsonl: {}", e);
+            }
+        }
+    }
+}
+
+/// Everything logged about the task with id `task_id`, oldest first; unreadable lines
+/// are skipped
+#[tauri::command]
+pub fn get_task_history(task_id: u64) -> Result<Vec<TaskEvent>, String> {
+    Ok(read()?.into_iter().filter(|event| event.task == task_id).collect())
+}
+
+fn list_mut(state: &mut TaskState, list: SearchList) -> &mut Vec<Task> {
+    match list {
+        SearchList::Current => &mut state.current,
+        _ => &mut state.shelf,
+    }
+}
+
+/// Make the change `event` logged to `state` again
+fn replay(state: &mut TaskState, event: &TaskEvent) {
+    let id = Some(event.task);
+    let found = [SearchList::Current, SearchList::Shelf]
+        .into_iter()
+        .find_map(|list| Some((list, list_mut(state, list).iter().position(|t| t.id == id)?)));
+    if let TaskChange::Added { list, text } = &event.change {
+        if found.is_none() {
+            list_mut(state, *list).push(Task {
+                id,
+                text: text.clone(),
+                created: Some(event.at),
+                ..Default::default()
+            });
+        }
+        return;
+    }
+    let Some((list, index)) = found else {
+        return;
+    };
+    let task = &mut list_mut(state, list)[index];
+    match &event.change {
+        TaskChange::Added { .. } => {}
+        TaskChange::Edited { to, .. } => task.text = to.clone(),
+        TaskChange::NoteAdded { text } => task.notes.push(Note {
+            text: text.clone(),
+            completed: false,
+        }),
+        TaskChange::NoteCompleted { text } | TaskChange::NoteReopened { text } => {
+            let completed = matches!(event.change, TaskChange::NoteCompleted { .. });
+            if let Some(note) = task.notes.iter_mut().find(|n| n.text == *text) {
+                note.completed = completed;
+            }
+        }
+        TaskChange::NoteRemoved { text } => task.notes.retain(|n| n.text != *text),
+        TaskChange::Moved { to, .. } => {
+            let task = list_mut(state, list).remove(index);
+            list_mut(state, *to).push(task);
+        }
+        TaskChange::Completed | TaskChange::Removed => {
+            list_mut(state, list).remove(index);
+        }
+    }
+}
+
+/// The task lists as they were at `at`: the last snapshot taken by then with the changes
+/// logged since made again, or every change logged by then if there's no snapshot. Tasks
+/// added after the snapshot come back with their text, notes and list but not their other
+/// fields, and the recurring templates are the snapshot's
+#[tauri::command]
+pub fn get_state_at(at: NaiveDateTime) -> Result<TaskState, String> {
+    let (since, mut state) = match snapshots::latest_before(at)? {
+        Some((created, tasks)) => (Some(created), tasks),
+        None => (None, TaskState::default()),
+    };
+    for event in read()?.iter().filter(|e| e.at <= at && since.is_none_or(|since| e.at > since)) {
+        replay(&mut state, event);
+    }
+    Ok(state)
+}
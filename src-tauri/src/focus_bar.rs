@@ -0,0 +1,64 @@
+//! The optional focus bar: a slim always-on-top strip showing only the task at
+//! the top of `current`, so the main window can stay hidden while working
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{save_config, tray, window, AppState};
+
+const LABEL: &str = "focus-bar";
+
+/// Text of the task being worked on, i.e. the first current task
+fn focus_task(app: &AppHandle) -> Option<String> {
+    let state = app.state::<AppState>();
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    tasks.current.first().map(|t| t.text.clone())
+}
+
+/// Show or hide the bar to match config
+pub fn apply(app: &AppHandle) {
+    let enabled = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).focus_bar;
+    let Some(bar) = app.get_webview_window(LABEL) else {
+        return;
+    };
+    if enabled {
+        if !bar.is_visible().unwrap_or(false) {
+            window::place_top_center(&bar);
+        }
+        let _ = bar.show();
+    } else {
+        let _ = bar.hide();
+    }
+}
+
+/// Push the current focus task to the bar
+pub fn refresh(app: &AppHandle) {
+    let _ = app.emit_to(LABEL, "focus-task-changed", focus_task(app));
+}
+
+pub fn set_enabled(app: &AppHandle, enabled: bool) -> Result<(), String> {
+    {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.focus_bar = enabled;
+        save_config(&config)?;
+    }
+    apply(app);
+    refresh(app);
+    tray::refresh(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_focus_task(app: AppHandle) -> Option<String> {
+    focus_task(&app)
+}
+
+#[tauri::command]
+pub fn get_focus_bar(state: tauri::State<AppState>) -> bool {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).focus_bar
+}
+
+#[tauri::command]
+pub fn set_focus_bar(enabled: bool, app: AppHandle) -> Result<(), String> {
+    set_enabled(&app, enabled)
+}
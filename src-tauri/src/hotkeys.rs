@@ -0,0 +1,633 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+
+use crate::audit::Source;
+use crate::{
+    complete_current_task, defaults, hide_window, notify, pomodoro, save_config, show_quick_add, show_window,
+    toggle_window, AppState,
+};
+
+/// Something a global shortcut can trigger
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Show or hide the main window
+    Toggle,
+    /// Open the quick-add prompt
+    QuickAdd,
+    /// Mark the first current task done without showing the window
+    CompleteTopTask,
+    /// Start a pomodoro, or resume the paused one
+    StartPomodoro,
+}
+
+/// How the toggle shortcut shows the window
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToggleMode {
+    /// Each press shows or hides the window
+    #[default]
+    Toggle,
+    /// The window is shown while the shortcut is held and hidden on release
+    Peek,
+}
+
+/// Set while the window is up only because the toggle shortcut is held
+static PEEKING: AtomicBool = AtomicBool::new(false);
+
+impl HotkeyAction {
+    fn run(self, app: &AppHandle) {
+        match self {
+            HotkeyAction::Toggle if toggle_mode(app) == ToggleMode::Peek => {
+                let visible = app
+                    .get_webview_window("main")
+                    .is_some_and(|w| w.is_visible().unwrap_or(false));
+                if !visible {
+                    show_window(app);
+                    PEEKING.store(true, Ordering::Relaxed);
+                }
+            }
+            HotkeyAction::Toggle => toggle_window(app),
+            HotkeyAction::QuickAdd => show_quick_add(app),
+            HotkeyAction::CompleteTopTask => {
+                let state = app.state::<AppState>();
+                let top = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).current.first().and_then(|t| t.id);
+                let Some(id) = top else {
+                    notify(app, "Nothing to complete", "There are no current tasks");
+                    return;
+                };
+                match complete_current_task(app, Source::Hotkey, id) {
+                    Ok(task) => notify(app, "Task completed", &task.text),
+                    Err(e) => eprintln!("Warning: Failed to complete top task: {}", e),
+                }
+            }
+            HotkeyAction::StartPomodoro => {
+                if let Err(e) = pomodoro::start_pomodoro(app.clone()) {
+                    eprintln!("Warning: Failed to start pomodoro: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Called when the shortcut is let go; only push-to-peek reacts to it
+    fn release(self, app: &AppHandle) {
+        if self == HotkeyAction::Toggle && PEEKING.swap(false, Ordering::Relaxed) {
+            hide_window(app.clone());
+        }
+    }
+}
+
+/// Whether an action's shortcut is actually registered with the OS
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum HotkeyStatus {
+    Active,
+    /// Registration failed (bad string, key taken, missing permission)
+    Inactive { reason: String },
+}
+
+/// Record the registration outcome for `action` and tell the UI
+fn set_status(app: &AppHandle, action: HotkeyAction, status: Option<HotkeyStatus>) {
+    let snapshot = {
+        let state = app.state::<AppState>();
+        let mut statuses = state.hotkey_status.lock().unwrap_or_else(|e| e.into_inner());
+        match status {
+            Some(status) => statuses.insert(action, status),
+            None => statuses.remove(&action),
+        };
+        statuses.clone()
+    };
+    let _ = app.emit("hotkey-status-changed", snapshot);
+}
+
+fn inactive(reason: impl Into<String>) -> Option<HotkeyStatus> {
+    Some(HotkeyStatus::Inactive { reason: reason.into() })
+}
+
+/// A single modifier key, told apart by side
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModifierKey {
+    LeftCmd,
+    RightCmd,
+    LeftShift,
+    RightShift,
+    LeftOption,
+    RightOption,
+    LeftControl,
+    RightControl,
+}
+
+fn toggle_mode(app: &AppHandle) -> ToggleMode {
+    app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).toggle_mode
+}
+
+/// Split a hotkey string on `+`. A `+` key is written as `\+` or as the final
+/// token (`Cmd++`), and `\\` escapes a backslash.
+fn split_hotkey(hotkey: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = hotkey.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let current = parts.last_mut().expect("parts is never empty");
+        match c {
+            '\\' => current.push(chars.next().unwrap_or('\\')),
+            '+' if current.is_empty() && chars.peek().is_none() => current.push('+'),
+            '+' => parts.push(String::new()),
+            _ => current.push(c),
+        }
+    }
+
+    parts.into_iter().map(|p| p.trim().to_string()).collect()
+}
+
+/// Look up a key by its friendly name. Anything not listed here can still be
+/// given by its W3C `KeyboardEvent.code` name, e.g. "NumpadClear" or "LaunchMail".
+fn parse_key(key: &str) -> Result<(Code, Modifiers), String> {
+    let code = match key.to_lowercase().as_str() {
+        "a" => Code::KeyA, "b" => Code::KeyB, "c" => Code::KeyC, "d" => Code::KeyD,
+        "e" => Code::KeyE, "f" => Code::KeyF, "g" => Code::KeyG, "h" => Code::KeyH,
+        "i" => Code::KeyI, "j" => Code::KeyJ, "k" => Code::KeyK, "l" => Code::KeyL,
+        "m" => Code::KeyM, "n" => Code::KeyN, "o" => Code::KeyO, "p" => Code::KeyP,
+        "q" => Code::KeyQ, "r" => Code::KeyR, "s" => Code::KeyS, "t" => Code::KeyT,
+        "u" => Code::KeyU, "v" => Code::KeyV, "w" => Code::KeyW, "x" => Code::KeyX,
+        "y" => Code::KeyY, "z" => Code::KeyZ,
+        "0" => Code::Digit0, "1" => Code::Digit1, "2" => Code::Digit2, "3" => Code::Digit3,
+        "4" => Code::Digit4, "5" => Code::Digit5, "6" => Code::Digit6, "7" => Code::Digit7,
+        "8" => Code::Digit8, "9" => Code::Digit9,
+        "=" | "equal" => Code::Equal,
+        // "+" is Shift+= on US layouts
+        "+" | "plus" => return Ok((Code::Equal, Modifiers::SHIFT)),
+        "-" | "minus" => Code::Minus,
+        "[" | "bracketleft" => Code::BracketLeft,
+        "]" | "bracketright" => Code::BracketRight,
+        "\\" | "backslash" => Code::Backslash,
+        ";" | "semicolon" => Code::Semicolon,
+        "'" | "quote" => Code::Quote,
+        "`" | "backquote" => Code::Backquote,
+        "," | "comma" => Code::Comma,
+        "." | "period" => Code::Period,
+        "/" | "slash" => Code::Slash,
+        "space" => Code::Space,
+        "enter" | "return" => Code::Enter,
+        "tab" => Code::Tab,
+        "escape" | "esc" => Code::Escape,
+        "backspace" => Code::Backspace,
+        "delete" | "del" => Code::Delete,
+        "insert" | "ins" => Code::Insert,
+        "home" => Code::Home,
+        "end" => Code::End,
+        "pageup" | "pgup" => Code::PageUp,
+        "pagedown" | "pgdn" => Code::PageDown,
+        "up" | "arrowup" => Code::ArrowUp,
+        "down" | "arrowdown" => Code::ArrowDown,
+        "left" | "arrowleft" => Code::ArrowLeft,
+        "right" | "arrowright" => Code::ArrowRight,
+        "printscreen" | "prtsc" => Code::PrintScreen,
+        "scrolllock" => Code::ScrollLock,
+        "pause" => Code::Pause,
+        "capslock" => Code::CapsLock,
+        "menu" | "contextmenu" => Code::ContextMenu,
+        "num0" | "numpad0" => Code::Numpad0, "num1" | "numpad1" => Code::Numpad1,
+        "num2" | "numpad2" => Code::Numpad2, "num3" | "numpad3" => Code::Numpad3,
+        "num4" | "numpad4" => Code::Numpad4, "num5" | "numpad5" => Code::Numpad5,
+        "num6" | "numpad6" => Code::Numpad6, "num7" | "numpad7" => Code::Numpad7,
+        "num8" | "numpad8" => Code::Numpad8, "num9" | "numpad9" => Code::Numpad9,
+        "num+" | "numpad+" | "numpadadd" => Code::NumpadAdd,
+        "num-" | "numpad-" | "numpadsubtract" => Code::NumpadSubtract,
+        "num*" | "numpad*" | "numpadmultiply" => Code::NumpadMultiply,
+        "num/" | "numpad/" | "numpaddivide" => Code::NumpadDivide,
+        "num." | "numpad." | "numpaddecimal" => Code::NumpadDecimal,
+        "num=" | "numpad=" | "numpadequal" => Code::NumpadEqual,
+        "numenter" | "numpadenter" => Code::NumpadEnter,
+        "numlock" => Code::NumLock,
+        "f1" => Code::F1, "f2" => Code::F2, "f3" => Code::F3, "f4" => Code::F4,
+        "f5" => Code::F5, "f6" => Code::F6, "f7" => Code::F7, "f8" => Code::F8,
+        "f9" => Code::F9, "f10" => Code::F10, "f11" => Code::F11, "f12" => Code::F12,
+        "f13" => Code::F13, "f14" => Code::F14, "f15" => Code::F15, "f16" => Code::F16,
+        "f17" => Code::F17, "f18" => Code::F18, "f19" => Code::F19, "f20" => Code::F20,
+        "f21" => Code::F21, "f22" => Code::F22, "f23" => Code::F23, "f24" => Code::F24,
+        "playpause" | "mediaplaypause" => Code::MediaPlayPause,
+        "stop" | "mediastop" => Code::MediaStop,
+        "next" | "nexttrack" | "mediatracknext" => Code::MediaTrackNext,
+        "prev" | "previous" | "prevtrack" | "mediatrackprevious" => Code::MediaTrackPrevious,
+        "volumeup" | "audiovolumeup" => Code::AudioVolumeUp,
+        "volumedown" | "audiovolumedown" => Code::AudioVolumeDown,
+        "mute" | "volumemute" | "audiovolumemute" => Code::AudioVolumeMute,
+        _ => key.parse::<Code>().map_err(|_| format!("Unknown key: {}", key))?,
+    };
+    Ok((code, Modifiers::empty()))
+}
+
+/// Parse a hotkey string like "Cmd+Ctrl+Alt+Shift+=" into a Shortcut
+fn parse_hotkey(hotkey: &str) -> Result<Shortcut, String> {
+    let parts = split_hotkey(hotkey);
+    let (key_str, modifier_parts) = parts.split_last().ok_or("Empty hotkey")?;
+    if key_str.is_empty() {
+        return Err("No key specified".to_string());
+    }
+
+    let mut modifiers = Modifiers::empty();
+    for part in modifier_parts {
+        match part.to_lowercase().as_str() {
+            "cmd" | "command" | "super" | "meta" => modifiers |= Modifiers::SUPER,
+            "ctrl" | "control" => modifiers |= Modifiers::CONTROL,
+            "alt" | "option" => modifiers |= Modifiers::ALT,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            _ => return Err(format!("Unknown modifier: {}", part)),
+        }
+    }
+
+    let (code, implied) = parse_key(key_str)?;
+    modifiers |= implied;
+
+    let mods = if modifiers.is_empty() { None } else { Some(modifiers) };
+    Ok(Shortcut::new(mods, code))
+}
+
+/// Build a shortcut from `KeyboardEvent.code` plus modifier bits
+/// (Alt 0x1, Control 0x8, Shift 0x200, Super 0x2000; Meta is treated as Super)
+fn shortcut_from_raw(modifiers: u32, code: &str) -> Result<Shortcut, String> {
+    let mut mods = Modifiers::from_bits(modifiers).ok_or_else(|| format!("Unknown modifier bits: {:#x}", modifiers))?;
+    if mods.contains(Modifiers::META) {
+        mods = (mods - Modifiers::META) | Modifiers::SUPER;
+    }
+    let supported = Modifiers::ALT | Modifiers::CONTROL | Modifiers::SHIFT | Modifiers::SUPER;
+    if !supported.contains(mods) {
+        return Err("Only Cmd, Ctrl, Alt and Shift can be used as modifiers".to_string());
+    }
+
+    let code = code.parse::<Code>().map_err(|_| format!("Unknown key: {}", code))?;
+    if matches!(
+        code,
+        Code::AltLeft | Code::AltRight | Code::ControlLeft | Code::ControlRight | Code::ShiftLeft
+            | Code::ShiftRight | Code::MetaLeft | Code::MetaRight
+    ) {
+        return Err("A shortcut needs a non-modifier key".to_string());
+    }
+
+    let mods = if mods.is_empty() { None } else { Some(mods) };
+    Ok(Shortcut::new(mods, code))
+}
+
+/// Write a shortcut back out in the "Cmd+Ctrl+Alt+Shift+=" form stored in config
+fn format_hotkey(shortcut: &Shortcut) -> String {
+    let mut parts = Vec::new();
+    for (modifier, name) in [
+        (Modifiers::SUPER, "Cmd"),
+        (Modifiers::CONTROL, "Ctrl"),
+        (Modifiers::ALT, "Alt"),
+        (Modifiers::SHIFT, "Shift"),
+    ] {
+        if shortcut.mods.contains(modifier) {
+            parts.push(name.to_string());
+        }
+    }
+
+    let code = shortcut.key.to_string();
+    let key = code
+        .strip_prefix("Key")
+        .or_else(|| code.strip_prefix("Digit"))
+        .unwrap_or(&code);
+    parts.push(key.to_string());
+    parts.join("+")
+}
+
+/// Register `shortcut` to run `action` on key press
+fn register_shortcut(app: &AppHandle, shortcut: Shortcut, action: HotkeyAction) -> Result<(), String> {
+    let app_handle = app.clone();
+    app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
+        match event.state {
+            ShortcutState::Pressed => action.run(&app_handle),
+            ShortcutState::Released => action.release(&app_handle),
+        }
+    }).map_err(|e| e.to_string())
+}
+
+/// Point `action` at `shortcut`, replacing whatever it was bound to before. Neither lock
+/// is held while registering, which waits on the main thread, where the tray takes them
+fn bind(app: &AppHandle, action: HotkeyAction, shortcut: Shortcut) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let old_shortcut = {
+        // Taken before `shortcuts`, as `set_suspended` does
+        let suspended = state.hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner());
+        let mut shortcuts = state.shortcuts.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some((other, _)) = shortcuts.iter().find(|(a, s)| **a != action && **s == shortcut) {
+            return Err(format!("Shortcut is already used for {:?}", other));
+        }
+
+        // While suspended nothing is registered; the new binding takes effect on resume
+        if *suspended {
+            shortcuts.insert(action, shortcut);
+            return Ok(());
+        }
+        shortcuts.remove(&action)
+    };
+
+    if let Some(old_shortcut) = old_shortcut {
+        let _ = app.global_shortcut().unregister(old_shortcut);
+    }
+
+    if let Err(e) = register_shortcut(app, shortcut, action) {
+        set_status(app, action, inactive(e.clone()));
+        return Err(e);
+    }
+    let suspended = {
+        let suspended = state.hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner());
+        state.shortcuts.lock().unwrap_or_else(|e| e.into_inner()).insert(action, shortcut);
+        *suspended
+    };
+    // Suspended in the meantime, after `set_suspended` went through the shortcuts
+    if suspended {
+        let _ = app.global_shortcut().unregister(shortcut);
+        return Ok(());
+    }
+    set_status(app, action, Some(HotkeyStatus::Active));
+    Ok(())
+}
+
+/// Check that `shortcut` could be bound to `action` without saving anything.
+/// Keys used by other apps are found by registering and immediately unregistering.
+fn check_available(app: &AppHandle, action: Option<HotkeyAction>, shortcut: Shortcut) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let owner = state
+        .shortcuts
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find_map(|(a, s)| (*s == shortcut).then_some(*a));
+
+    if let Some(owner) = owner {
+        if Some(owner) == action {
+            return Ok(());
+        }
+        return Err(format!("Shortcut is already used for {:?}", owner));
+    }
+
+    let global_shortcut = app.global_shortcut();
+    match global_shortcut.register(shortcut) {
+        Ok(()) => {
+            let _ = global_shortcut.unregister(shortcut);
+            Ok(())
+        }
+        // global-hotkey reports OS-level conflicts as "Unable to register hotkey: ..."
+        Err(e) if e.to_string().starts_with("Unable to register") => {
+            Err("Shortcut is already in use by another app".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn unbind(app: &AppHandle, action: HotkeyAction) {
+    let state = app.state::<AppState>();
+    let old_shortcut = state.shortcuts.lock().unwrap_or_else(|e| e.into_inner()).remove(&action);
+    if let Some(old_shortcut) = old_shortcut {
+        let _ = app.global_shortcut().unregister(old_shortcut);
+    }
+    set_status(app, action, None);
+}
+
+/// Unregister every shortcut (and the double-tap watcher) without forgetting
+/// them, or register them all again. Reports shortcuts that failed to come back.
+pub fn set_suspended(app: &AppHandle, suspended: bool) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut failed = Vec::new();
+    let mut restored = Vec::new();
+    let shortcuts: Vec<(HotkeyAction, Shortcut)> = {
+        let mut current = state.hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner());
+        if *current == suspended {
+            return Ok(());
+        }
+        *current = suspended;
+        let shortcuts = state.shortcuts.lock().unwrap_or_else(|e| e.into_inner());
+        shortcuts.iter().map(|(action, shortcut)| (*action, *shortcut)).collect()
+    };
+    // Registering waits on the main thread, so neither lock is held for it
+    for (action, shortcut) in shortcuts {
+        if suspended {
+            let _ = app.global_shortcut().unregister(shortcut);
+        } else {
+            let result = register_shortcut(app, shortcut, action);
+            if let Err(e) = &result {
+                failed.push(format!("{:?}: {}", action, e));
+            }
+            restored.push((action, result));
+        }
+    }
+    for (action, result) in restored {
+        let status = match result {
+            Ok(()) => Some(HotkeyStatus::Active),
+            Err(e) => inactive(e),
+        };
+        set_status(app, action, status);
+    }
+
+    #[cfg(target_os = "macos")]
+    crate::modifier_tap::set_paused(suspended);
+
+    let _ = app.emit("hotkeys-suspended-changed", suspended);
+    crate::tray::refresh(app);
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to restore shortcuts: {}", failed.join(", ")))
+    }
+}
+
+/// Toggle shortcut to use in place of the configured one, without saving it
+const ENV_HOTKEY: &str = "TASK_LOG_HOTKEY";
+
+/// Register every configured shortcut at startup. A bad or taken key only
+/// disables that action; the toggle falls back to the default chord.
+/// `TASK_LOG_HOTKEY` overrides the toggle's configured key.
+pub fn register_all(app: &AppHandle, hotkeys: &HashMap<HotkeyAction, String>) {
+    let mut hotkeys = hotkeys.clone();
+    if let Some(hotkey) = std::env::var(ENV_HOTKEY).ok().filter(|h| !h.trim().is_empty()) {
+        hotkeys.insert(HotkeyAction::Toggle, hotkey);
+    }
+    for (action, hotkey) in &hotkeys {
+        let parsed = match parse_hotkey(hotkey) {
+            Err(_) if *action == HotkeyAction::Toggle => parse_hotkey(defaults::TOGGLE_HOTKEY),
+            parsed => parsed,
+        };
+        let shortcut = match parsed {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                eprintln!("Warning: Invalid hotkey for {:?}: {}", action, e);
+                set_status(app, *action, inactive(e));
+                continue;
+            }
+        };
+        if let Err(e) = bind(app, *action, shortcut) {
+            eprintln!("Warning: Failed to register hotkey for {:?}: {}", action, e);
+            set_status(app, *action, inactive(e));
+        }
+    }
+}
+
+/// Check that every hotkey in `hotkeys` parses, no two share a shortcut and the
+/// toggle is still there
+pub fn validate_all(hotkeys: &HashMap<HotkeyAction, String>) -> Result<(), String> {
+    if !hotkeys.contains_key(&HotkeyAction::Toggle) {
+        return Err("The toggle hotkey can't be removed".to_string());
+    }
+    let mut seen: Vec<(HotkeyAction, Shortcut)> = Vec::new();
+    for (action, hotkey) in hotkeys {
+        let shortcut = parse_hotkey(hotkey).map_err(|e| format!("{:?}: {}", action, e))?;
+        if let Some((other, _)) = seen.iter().find(|(_, s)| *s == shortcut) {
+            return Err(format!("{:?} and {:?} use the same shortcut", other, action));
+        }
+        seen.push((*action, shortcut));
+    }
+    Ok(())
+}
+
+/// Replace every registered shortcut with those in `hotkeys`, after a settings change
+pub fn rebind_all(app: &AppHandle, hotkeys: &HashMap<HotkeyAction, String>) {
+    let bound: Vec<HotkeyAction> = {
+        let state = app.state::<AppState>();
+        let shortcuts = state.shortcuts.lock().unwrap_or_else(|e| e.into_inner());
+        shortcuts.keys().copied().collect()
+    };
+    for action in bound {
+        unbind(app, action);
+    }
+    register_all(app, hotkeys);
+}
+
+#[tauri::command]
+pub fn get_hotkey(state: tauri::State<AppState>) -> String {
+    let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.hotkeys.get(&HotkeyAction::Toggle).cloned().unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_hotkey(hotkey: String, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    set_action_hotkey(HotkeyAction::Toggle, hotkey, app, state)
+}
+
+#[tauri::command]
+pub fn get_hotkeys(state: tauri::State<AppState>) -> HashMap<HotkeyAction, String> {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).hotkeys.clone()
+}
+
+#[tauri::command]
+pub fn set_action_hotkey(
+    action: HotkeyAction,
+    hotkey: String,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<(), String> {
+    // Validate the hotkey can be parsed
+    let shortcut = parse_hotkey(&hotkey)?;
+    apply_hotkey(&app, &state, action, shortcut, hotkey)
+}
+
+/// Set a hotkey from a recorded key press instead of a hotkey string.
+/// `action` defaults to the window toggle.
+#[tauri::command]
+pub fn set_hotkey_raw(
+    modifiers: u32,
+    code: String,
+    action: Option<HotkeyAction>,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<String, String> {
+    let shortcut = shortcut_from_raw(modifiers, &code)?;
+    let hotkey = format_hotkey(&shortcut);
+    apply_hotkey(&app, &state, action.unwrap_or(HotkeyAction::Toggle), shortcut, hotkey.clone())?;
+    Ok(hotkey)
+}
+
+fn apply_hotkey(
+    app: &AppHandle,
+    state: &AppState,
+    action: HotkeyAction,
+    shortcut: Shortcut,
+    hotkey: String,
+) -> Result<(), String> {
+    bind(app, action, shortcut)?;
+
+    // Save to config
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.hotkeys.insert(action, hotkey);
+    save_config(&config)
+}
+
+/// Dry-run a hotkey before saving it. Pass `action` when editing an existing
+/// binding so its own current shortcut isn't reported as a conflict.
+#[tauri::command]
+pub fn validate_hotkey(hotkey: String, action: Option<HotkeyAction>, app: AppHandle) -> Result<(), String> {
+    let shortcut = parse_hotkey(&hotkey)?;
+    check_available(&app, action, shortcut)
+}
+
+#[tauri::command]
+pub fn clear_action_hotkey(action: HotkeyAction, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    if action == HotkeyAction::Toggle {
+        return Err("The toggle hotkey can't be removed".to_string());
+    }
+    unbind(&app, action);
+
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.hotkeys.remove(&action);
+    save_config(&config)
+}
+
+/// Registration state per configured action, so settings can flag dead shortcuts
+#[tauri::command]
+pub fn get_hotkey_status(state: tauri::State<AppState>) -> HashMap<HotkeyAction, HotkeyStatus> {
+    state.hotkey_status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[tauri::command]
+pub fn get_toggle_mode(state: tauri::State<AppState>) -> ToggleMode {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).toggle_mode
+}
+
+#[tauri::command]
+pub fn set_toggle_mode(mode: ToggleMode, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.toggle_mode = mode;
+    save_config(&config)
+}
+
+#[tauri::command]
+pub fn suspend_hotkeys(app: AppHandle) -> Result<(), String> {
+    set_suspended(&app, true)
+}
+
+#[tauri::command]
+pub fn resume_hotkeys(app: AppHandle) -> Result<(), String> {
+    set_suspended(&app, false)
+}
+
+#[tauri::command]
+pub fn get_hotkeys_suspended(state: tauri::State<AppState>) -> bool {
+    *state.hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[tauri::command]
+pub fn get_double_tap(state: tauri::State<AppState>) -> Option<ModifierKey> {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).double_tap
+}
+
+/// Toggle the window by double-tapping `key`, or turn that off with `None`
+#[tauri::command]
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn set_double_tap(key: Option<ModifierKey>, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    if key.is_some() && cfg!(not(target_os = "macos")) {
+        return Err("Double-tap activation is only available on macOS".to_string());
+    }
+    #[cfg(target_os = "macos")]
+    crate::modifier_tap::set_key(&app, key)?;
+
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.double_tap = key;
+    save_config(&config)
+}
@@ -0,0 +1,157 @@
+use tauri::image::Image;
+
+/// 3x5 bitmap glyphs for the digits drawn into the count badge
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+pub type Rgb = [u8; 3];
+
+pub const BADGE_RED: Rgb = [229, 72, 77];
+pub const PROGRESS_GREEN: Rgb = [48, 164, 108];
+const WHITE: Rgb = [255, 255, 255];
+pub const BLACK: Rgb = [0, 0, 0];
+
+/// Glyph color that stays readable on the current menu bar appearance
+pub fn foreground(dark: bool) -> Rgb {
+    if dark {
+        WHITE
+    } else {
+        BLACK
+    }
+}
+
+/// An owned RGBA bitmap that the tray variants are drawn into
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn from_image(image: &Image<'_>) -> Self {
+        Self {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.rgba().to_vec(),
+        }
+    }
+
+    /// Keep only the light strokes of the app icon, drawn in a single color.
+    /// With `BLACK` this is a valid macOS template image.
+    pub fn monochrome(image: &Image<'_>, color: Rgb) -> Self {
+        let mut canvas = Self::from_image(image);
+        for px in canvas.rgba.chunks_exact_mut(4) {
+            let light = px[0].min(px[1]).min(px[2]) > 200;
+            let alpha = if light { px[3] } else { 0 };
+            px.copy_from_slice(&[color[0], color[1], color[2], alpha]);
+        }
+        canvas
+    }
+
+    pub fn set_pixel(&mut self, x: i64, y: i64, color: Rgb) {
+        self.set_pixel_alpha(x, y, color, 255);
+    }
+
+    pub fn set_pixel_alpha(&mut self, x: i64, y: i64, color: Rgb, alpha: u8) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        self.rgba[i..i + 4].copy_from_slice(&[color[0], color[1], color[2], alpha]);
+    }
+
+    pub fn fill_circle(&mut self, cx: f64, cy: f64, r: f64, color: Rgb) {
+        for y in (cy - r).floor() as i64..=(cy + r).ceil() as i64 {
+            for x in (cx - r).floor() as i64..=(cx + r).ceil() as i64 {
+                let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+                if dx * dx + dy * dy <= r * r {
+                    self.set_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// Draw `value` centered on (cx, cy) using the bitmap digits, `height` pixels tall
+    pub fn draw_number(&mut self, value: usize, cx: f64, cy: f64, height: f64, color: Rgb) {
+        let text = value.to_string();
+        let scale = (height / 5.0).floor().max(1.0) as i64;
+        let glyphs = text.len() as i64;
+        let text_width = glyphs * 3 * scale + (glyphs - 1) * scale;
+        let left = cx.round() as i64 - text_width / 2;
+        let top = cy.round() as i64 - (5 * scale) / 2;
+
+        for (n, ch) in text.chars().enumerate() {
+            let Some(glyph) = ch.to_digit(10).map(|d| DIGITS[d as usize]) else {
+                continue;
+            };
+            let origin = left + n as i64 * 4 * scale;
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            self.set_pixel(
+                                origin + col * scale + sx,
+                                top + row as i64 * scale + sy,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// A filled circle in the top-right corner with `count` inside it
+    pub fn draw_badge(&mut self, count: usize, color: Rgb) {
+        let count = count.min(99);
+        let size = self.width.min(self.height) as f64;
+        let r = size * 0.3;
+        let (cx, cy) = (self.width as f64 - r, r);
+        self.fill_circle(cx, cy, r, color);
+        self.draw_number(count, cx, cy, r * 1.1, WHITE);
+    }
+
+    /// A ring along the icon's edge, filled clockwise from the top up to `fraction`,
+    /// with the remainder drawn as a faint track
+    pub fn draw_ring(&mut self, fraction: f64, color: Rgb, track: Rgb) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let size = self.width.min(self.height) as f64;
+        let outer = size / 2.0;
+        let inner = outer - (size * 0.1).max(1.0);
+        let (cx, cy) = (self.width as f64 / 2.0, self.height as f64 / 2.0);
+
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+                let dist = (dx * dx + dy * dy).sqrt();
+                if dist < inner || dist > outer {
+                    continue;
+                }
+                // 0 at twelve o'clock, growing clockwise
+                let angle = dx.atan2(-dy).rem_euclid(std::f64::consts::TAU);
+                if angle / std::f64::consts::TAU <= fraction && fraction > 0.0 {
+                    self.set_pixel(x, y, color);
+                } else {
+                    self.set_pixel_alpha(x, y, track, 70);
+                }
+            }
+        }
+    }
+
+    pub fn into_image(self) -> Image<'static> {
+        Image::new_owned(self.rgba, self.width, self.height)
+    }
+}
@@ -0,0 +1,38 @@
+//! How long the user has been away from the keyboard and mouse, as reported by the OS.
+
+use std::time::Duration;
+
+/// Time since the last keyboard or mouse input
+#[cfg(target_os = "macos")]
+pub fn idle_time() -> Option<Duration> {
+    use objc2_core_graphics::{CGEventSource, CGEventSourceStateID, CGEventType};
+
+    // kCGAnyInputEventType
+    let any_input = CGEventType(u32::MAX);
+    let seconds = CGEventSource::seconds_since_last_event_type(CGEventSourceStateID::CombinedSessionState, any_input);
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Time since the last keyboard or mouse input
+#[cfg(target_os = "windows")]
+pub fn idle_time() -> Option<Duration> {
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return None;
+    }
+    // Both are milliseconds since boot and wrap after ~49 days
+    let now = unsafe { GetTickCount() };
+    Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+}
+
+/// Not available here; the user always counts as present
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn idle_time() -> Option<Duration> {
+    None
+}
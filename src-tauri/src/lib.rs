@@ -1,83 +1,538 @@
-use chrono::Local;
+mod audit;
+mod defaults;
+mod deleted;
+mod done;
+mod duplicates;
+mod events;
+mod focus_bar;
+mod hotkeys;
+mod icon;
+mod idle;
+mod locale;
+mod merge;
+mod pomodoro;
+mod profiles;
+mod query;
+mod recurrence;
+mod report;
+mod scheduler;
+mod search;
+mod search_index;
+mod settings;
+mod snapshots;
+mod stats;
+mod suggest;
+mod timer;
+mod tray;
+mod undo;
+mod view;
+mod window;
+#[cfg(target_os = "macos")]
+mod tray_drop;
+#[cfg(target_os = "macos")]
+mod modifier_tap;
+#[cfg(target_os = "macos")]
+mod panel;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
 use dirs::home_dir;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use tauri::{
-    menu::{Menu, MenuItem},
-    tray::TrayIconBuilder,
-    AppHandle, Manager,
-};
-use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri::{tray::TrayIconBuilder, AppHandle, Emitter, Manager};
+
+use query::SavedFilter;
+use recurrence::{Recurrence, RecurringTask};
+use done::{ArchiveRetention, DoneLogConfig, ProjectLogs};
+use pomodoro::PomodoroConfig;
+use scheduler::{AutoArchive, OverdueConfig, WorkWeek};
+use hotkeys::{HotkeyAction, HotkeyStatus, ModifierKey, ToggleMode};
+use window::{ScreenPosition, ThemeConfig, WindowAnchor, WindowAppearance, WindowBounds, WindowMargin};
+use tauri_plugin_global_shortcut::Shortcut;
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+use tauri_plugin_notification::NotificationExt;
 
 #[cfg(target_os = "macos")]
 use objc2::MainThreadMarker;
 #[cfg(target_os = "macos")]
-use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy, NSEvent, NSScreen};
+use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Note {
     pub text: String,
     #[serde(default)]
     pub completed: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// How important a task is; filters compare them in this order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct Task {
+    /// Stable id, given when the task is added; its history in events.jsonl goes by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
     pub text: String,
     #[serde(default)]
     pub notes: Vec<Note>,
+    /// Local date and time the task is due, e.g. "2026-03-02T17:00:00"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDateTime>,
+    /// A shelved task returns to `current` at this local time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snoozed_until: Option<NaiveDateTime>,
+    /// Client or project the task belongs to; see `project_logs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// When the task was added; tasks from before this was kept have none until saved again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<NaiveDateTime>,
+    /// Seconds timed on the task with `start_timer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_spent: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct TaskState {
     pub current: Vec<Task>,
     pub shelf: Vec<Task>,
+    /// Templates the scheduler copies into `current` on their schedule
+    #[serde(default)]
+    pub recurring: Vec<RecurringTask>,
+    /// Goes up with every save; a save from the UI has to carry the revision it last saw
+    #[serde(default)]
+    pub revision: u64,
+}
+
+/// Which variant of the tray icon to show
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayIconStyle {
+    /// Monochrome glyph that follows the menu bar appearance
+    Template,
+    /// The full-color app icon
+    #[default]
+    Colored,
+    /// Monochrome glyph with the number of current tasks in a badge
+    Badge,
+    /// Monochrome glyph inside a ring showing completed vs. planned today
+    Progress,
+}
+
+/// An entry in the tray menu; `tray_menu` in the config lists them in display order
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayMenuEntry {
+    /// The first `tray_task_limit` current tasks, click to complete
+    Tasks,
+    /// "Recently Completed" submenu with undo
+    Recent,
+    /// Opens the window on today's completed/open summary
+    Summary,
+    QuickAdd,
+    Dnd,
+    /// Checkbox that suspends global shortcuts
+    PauseHotkeys,
+    /// Checkbox that shows the floating focus bar
+    FocusBar,
+    /// "Start Pomodoro", or a submenu to pause, skip or stop the one under way
+    Pomodoro,
+    Settings,
+    Archive,
+    Separator,
+    Quit,
+    /// Entries this version doesn't know about are skipped instead of failing the whole config
+    #[serde(other)]
+    Unknown,
+}
+
+fn default_tray_menu() -> Vec<TrayMenuEntry> {
+    vec![
+        TrayMenuEntry::Tasks,
+        TrayMenuEntry::Separator,
+        TrayMenuEntry::Recent,
+        TrayMenuEntry::Summary,
+        TrayMenuEntry::QuickAdd,
+        TrayMenuEntry::Dnd,
+        TrayMenuEntry::PauseHotkeys,
+        TrayMenuEntry::FocusBar,
+        TrayMenuEntry::Pomodoro,
+        TrayMenuEntry::Archive,
+        TrayMenuEntry::Quit,
+    ]
+}
+
+/// What a click on the tray icon does
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayClickAction {
+    ToggleWindow,
+    /// Leave it to the OS to open the tray menu
+    ShowMenu,
+    QuickAdd,
+    /// Add the clipboard's text as a new task
+    QuickAddClipboard,
+    OpenSettings,
+    ArchiveCompleted,
+    Nothing,
+}
+
+/// Per-button tray click actions
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct TrayClickConfig {
+    pub left: TrayClickAction,
+    pub right: TrayClickAction,
+    pub middle: TrayClickAction,
+    /// Left double-click; when set, single left clicks wait briefly to tell the two apart
+    pub double: TrayClickAction,
+}
+
+impl Default for TrayClickConfig {
+    fn default() -> Self {
+        Self {
+            left: TrayClickAction::ToggleWindow,
+            right: TrayClickAction::ShowMenu,
+            middle: TrayClickAction::QuickAddClipboard,
+            double: TrayClickAction::Nothing,
+        }
+    }
 }
 
 /// App configuration including hotkey settings
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
-    #[serde(default = "default_hotkey")]
-    pub hotkey: String,
+    /// Layout of this file, see `settings::CONFIG_VERSION`; 0 for files from before it was versioned
+    #[serde(default)]
+    pub version: u32,
+    /// Global shortcuts by action, e.g. {"toggle": "Cmd+Ctrl+Alt+Shift+="}
+    #[serde(default = "defaults::hotkeys")]
+    pub hotkeys: HashMap<HotkeyAction, String>,
+    #[serde(default)]
+    pub toggle_mode: ToggleMode,
+    /// How many current tasks to list in the tray menu
+    #[serde(default = "default_tray_task_limit")]
+    pub tray_task_limit: usize,
+    /// How many entries the tray's "Recently Completed" submenu shows
+    #[serde(default = "default_tray_recent_limit")]
+    pub tray_recent_limit: usize,
+    #[serde(default)]
+    pub tray_icon_style: TrayIconStyle,
+    #[serde(default = "default_tray_menu")]
+    pub tray_menu: Vec<TrayMenuEntry>,
+    #[serde(default)]
+    pub tray_click: TrayClickConfig,
+    /// Modifier that toggles the window when double-tapped (macOS only)
+    #[serde(default)]
+    pub double_tap: Option<ModifierKey>,
+    #[serde(default)]
+    pub window_anchor: WindowAnchor,
+    /// Hide the window when it loses focus, like a menu bar dropdown
+    #[serde(default)]
+    pub hide_on_blur: bool,
+    /// Keep the window on top of other apps and never auto-hide it
+    #[serde(default)]
+    pub pinned: bool,
+    /// macOS: show the window as a non-activating panel that leaves the app in front focused
+    #[serde(default)]
+    pub panel_mode: bool,
+    /// Extra offset per anchor, e.g. {"top_right": {"x": 8, "y": 4}}
+    #[serde(default)]
+    pub window_margins: HashMap<WindowAnchor, WindowMargin>,
+    /// Where the window was last dragged to on each screen, keyed by screen name;
+    /// overrides the anchor on that screen
+    #[serde(default)]
+    pub screen_positions: HashMap<String, ScreenPosition>,
+    /// Show the slim always-on-top bar with the current focus task
+    #[serde(default)]
+    pub focus_bar: bool,
+    /// Background opacity and native vibrancy/acrylic effect
+    #[serde(default)]
+    pub window_appearance: WindowAppearance,
+    /// Light, dark or following the OS, plus an accent color for the UI and tray
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// Days off (weekends, holidays) on which reminders and daily prompts stay quiet
+    #[serde(default)]
+    pub work_week: WorkWeek,
+    /// Where the done log is kept and whether it's markdown, org or CSV
+    #[serde(default)]
+    pub done_log: DoneLogConfig,
+    /// How the time of day is written next to each entry in done.md (chrono strftime); empty for none
+    #[serde(default = "default_done_time_format")]
+    pub done_time_format: String,
+    /// How dates are shown in reports, reminders and stats exports (chrono strftime), and next to
+    /// the ISO dates of the done log's headings
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Language of month and weekday names, e.g. "de_DE"; English when unset
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// First day of the week for stats, rollups, reviews and weekly auto-archiving, e.g. "Sun"
+    #[serde(default = "default_week_start")]
+    pub week_start: Weekday,
+    /// Give tasks with a project their own done log, or prefix them with it
+    #[serde(default)]
+    pub project_logs: ProjectLogs,
+    /// When `prune_archives` compresses or deletes old archives
+    #[serde(default)]
+    pub archive_retention: ArchiveRetention,
+    /// Move earlier months out of done.md into done_YYYY-MM.md on the first completion of a month
+    #[serde(default)]
+    pub rotate_done_monthly: bool,
+    /// Roll done.md into an archive file every day, week or month
+    #[serde(default)]
+    pub auto_archive: AutoArchive,
+    /// Repeat notifications and a tray badge for tasks past their due time
+    #[serde(default)]
+    pub overdue: OverdueConfig,
+    /// How long "Snooze Again" puts a task that just came back off the shelf away for
+    #[serde(default = "default_snooze_again_minutes")]
+    pub snooze_again_minutes: u32,
+    /// Hold reminders after this many minutes without input and deliver them on return; 0 turns it off
+    #[serde(default = "defaults::idle_minutes")]
+    pub idle_minutes: u32,
+    /// Extra reminders this many minutes before a task's due time (one always fires at it)
+    #[serde(default = "default_reminder_lead_minutes")]
+    pub reminder_lead_minutes: Vec<u32>,
+    /// Time of day, e.g. "09:00", to open the window on the planning view
+    #[serde(default)]
+    pub planning_time: Option<NaiveTime>,
+    /// Time of day, e.g. "18:00", to send a summary of the day's progress
+    #[serde(default)]
+    pub summary_time: Option<NaiveTime>,
+    /// Also open the window on the day's summary at `summary_time`
+    #[serde(default)]
+    pub summary_opens_window: bool,
+    /// Where the window was last left; kept up to date as it's moved or resized
+    #[serde(default)]
+    pub window_bounds: Option<WindowBounds>,
+    /// Named `query_tasks` expressions the UI shows as smart lists, e.g. {"name": "Waiting", "query": "#waiting"}
+    #[serde(default)]
+    pub saved_filters: Vec<SavedFilter>,
+    /// Tasks to complete each day, shown by the tray's progress ring and the day summary; 0 for no goal
+    #[serde(default)]
+    pub daily_goal: u32,
+    /// Start the app, window hidden, when the user logs in
+    #[serde(default)]
+    pub launch_at_login: bool,
+    /// Phase lengths and notification sounds of the pomodoro clock
+    #[serde(default)]
+    pub pomodoro: PomodoroConfig,
+}
+
+fn default_tray_task_limit() -> usize {
+    5
+}
+
+fn default_tray_recent_limit() -> usize {
+    5
+}
+
+fn default_snooze_again_minutes() -> u32 {
+    60
 }
 
-fn default_hotkey() -> String {
-    "Cmd+Ctrl+Alt+Shift+=".to_string()
+fn default_done_time_format() -> String {
+    "%H:%M".to_string()
+}
+
+fn default_date_format() -> String {
+    locale::ISO_DATE.to_string()
+}
+
+fn default_week_start() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_reminder_lead_minutes() -> Vec<u32> {
+    vec![15]
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
-            hotkey: default_hotkey(),
+            version: settings::CONFIG_VERSION,
+            hotkeys: defaults::hotkeys(),
+            toggle_mode: ToggleMode::default(),
+            tray_task_limit: default_tray_task_limit(),
+            tray_recent_limit: default_tray_recent_limit(),
+            tray_icon_style: TrayIconStyle::default(),
+            tray_menu: default_tray_menu(),
+            tray_click: TrayClickConfig::default(),
+            double_tap: None,
+            window_anchor: WindowAnchor::default(),
+            window_margins: HashMap::new(),
+            screen_positions: HashMap::new(),
+            hide_on_blur: false,
+            pinned: false,
+            panel_mode: false,
+            window_appearance: WindowAppearance::default(),
+            theme: ThemeConfig::default(),
+            focus_bar: false,
+            snooze_again_minutes: default_snooze_again_minutes(),
+            overdue: OverdueConfig::default(),
+            auto_archive: AutoArchive::Off,
+            rotate_done_monthly: false,
+            archive_retention: ArchiveRetention::default(),
+            project_logs: ProjectLogs::Off,
+            done_time_format: default_done_time_format(),
+            date_format: default_date_format(),
+            locale: None,
+            week_start: default_week_start(),
+            done_log: DoneLogConfig::default(),
+            work_week: WorkWeek::default(),
+            idle_minutes: defaults::idle_minutes(),
+            reminder_lead_minutes: default_reminder_lead_minutes(),
+            planning_time: None,
+            summary_time: None,
+            summary_opens_window: false,
+            window_bounds: None,
+            saved_filters: Vec::new(),
+            daily_goal: 0,
+            launch_at_login: false,
+            pomodoro: PomodoroConfig::default(),
+        }
+    }
+}
+
+/// What got done today and what's left, for the end-of-day summary
+#[derive(Debug, Serialize, Clone)]
+pub struct DaySummary {
+    /// Texts of the tasks completed today, oldest first
+    pub completed: Vec<String>,
+    /// Tasks still in `current`
+    pub open: usize,
+}
+
+/// Today's completions against the configured `daily_goal`
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct GoalProgress {
+    pub date: Option<NaiveDate>,
+    /// 0 when no goal is set
+    pub goal: u32,
+    pub completed: usize,
+    pub reached: bool,
+}
+
+impl GoalProgress {
+    /// How far along the goal is, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.goal == 0 {
+            0.0
+        } else {
+            (self.completed as f64 / self.goal as f64).min(1.0)
         }
     }
 }
 
+/// Matches the UI's cap on the current pane; anything past it lands on the shelf
+const MAX_CURRENT: usize = 10;
+
 pub struct AppState {
     pub tasks: Mutex<TaskState>,
     pub config: Mutex<AppConfig>,
-    pub current_shortcut: Mutex<Option<Shortcut>>,
+    /// Currently registered global shortcuts
+    pub shortcuts: Mutex<HashMap<HotkeyAction, Shortcut>>,
+    /// Whether each configured shortcut registered, and why not if it didn't
+    pub hotkey_status: Mutex<HashMap<HotkeyAction, HotkeyStatus>>,
+    /// Do-not-disturb: suppresses notifications and reminders while set
+    pub dnd: Mutex<bool>,
+    /// Global shortcuts stay unregistered while set (e.g. during games or screen sharing).
+    /// Locked before `shortcuts` where both are needed
+    pub hotkeys_suspended: Mutex<bool>,
+    pub tray_clicks: Mutex<tray::ClickDebounce>,
+    /// Texts of the snoozed tasks the scheduler last brought back, for "Snooze Again"
+    pub woken: Mutex<Vec<String>>,
+    /// Progress toward the daily goal as of the last change, to notice when it's reached
+    pub goal_progress: Mutex<GoalProgress>,
+    /// Changes to `tasks` this session, for undo and redo
+    pub history: Mutex<undo::History>,
+    /// The task being timed, also kept in timer.json
+    pub timer: Mutex<Option<timer::Running>>,
+    /// The pomodoro under way; one isn't carried over a restart
+    pub pomodoro: Mutex<Option<pomodoro::Session>>,
 }
 
-fn get_tasks_dir() -> Result<PathBuf, String> {
+/// Data directory to use in place of ~/.tasks, for portable installs and side-by-side instances
+const ENV_DIR: &str = "TASK_LOG_DIR";
+/// Settings file to use in place of the data directory's config.json or config.toml
+const ENV_CONFIG: &str = "TASK_LOG_CONFIG";
+
+/// Path in environment variable `name`, if it's set
+fn env_path(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(std::env::var_os(name).filter(|v| !v.is_empty())?);
+    Some(std::path::absolute(&path).unwrap_or(path))
+}
+
+/// ~/.tasks (or `TASK_LOG_DIR`), the default profile's data directory and home of profiles.json
+fn get_home_tasks_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = env_path(ENV_DIR) {
+        return Ok(dir);
+    }
     home_dir()
         .map(|h| h.join(".tasks"))
         .ok_or_else(|| "Could not determine home directory".to_string())
 }
 
+/// Data directory of the active profile
+fn get_tasks_dir() -> Result<PathBuf, String> {
+    match profiles::data_dir() {
+        Some(dir) => Ok(dir),
+        None => get_home_tasks_dir(),
+    }
+}
+
 fn get_state_file() -> Result<PathBuf, String> {
     Ok(get_tasks_dir()?.join("state.json"))
 }
 
 fn get_done_file() -> Result<PathBuf, String> {
-    Ok(get_tasks_dir()?.join("done.md"))
+    done::log_file()
+}
+
+/// Machine-readable copy of the completed log, one JSON object per line
+fn get_done_records_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("done.jsonl"))
+}
+
+/// Settings as JSON, the original format
+const CONFIG_JSON: &str = "config.json";
+/// Settings as TOML, read in place of config.json when both exist
+const CONFIG_TOML: &str = "config.toml";
+
+/// Files settings may be read from, in order of preference: `TASK_LOG_CONFIG` alone when
+/// it's set, otherwise config.toml and config.json
+fn get_config_candidates() -> Result<Vec<PathBuf>, String> {
+    if let Some(path) = env_path(ENV_CONFIG) {
+        return Ok(vec![path]);
+    }
+    let dir = get_tasks_dir()?;
+    Ok(vec![dir.join(CONFIG_TOML), dir.join(CONFIG_JSON)])
 }
 
+/// The first settings file that exists, or the last candidate (config.json) to create;
+/// settings are saved back to the same file
 fn get_config_file() -> Result<PathBuf, String> {
-    Ok(get_tasks_dir()?.join("config.json"))
+    let mut candidates = get_config_candidates()?;
+    match candidates.iter().position(|path| path.exists()) {
+        Some(i) => Ok(candidates.swap_remove(i)),
+        None => candidates.pop().ok_or_else(|| "No config file".to_string()),
+    }
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
 }
 
 fn ensure_tasks_dir() -> Result<(), String> {
@@ -88,6 +543,42 @@ fn ensure_tasks_dir() -> Result<(), String> {
     Ok(())
 }
 
+/// Parse the contents of config.json, or config.toml with `toml`, upgrading it from older
+/// versions and dropping what can't be used. Also says whether it was upgraded, so it can be
+/// saved in the new layout
+fn parse_config(content: &str, toml: bool) -> Result<(AppConfig, bool), String> {
+    let mut value: serde_json::Value = if toml {
+        toml::from_str(content).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(content).map_err(|e| e.to_string())?
+    };
+    let migrated = settings::migrate(&mut value)?;
+    let mut config = serde_json::from_value::<AppConfig>(value).map_err(|e| e.to_string())?;
+    if let Err(e) = config.done_log.validate() {
+        eprintln!("Warning: {}. Using the default done log.", e);
+        config.done_log = DoneLogConfig::default();
+    }
+    if let Err(e) = locale::validate_format(&config.date_format) {
+        eprintln!("Warning: {}. Using {}.", e, locale::ISO_DATE);
+        config.date_format = default_date_format();
+    }
+    if let Some(Err(e)) = config.locale.as_deref().map(locale::parse_locale) {
+        eprintln!("Warning: {}. Using English.", e);
+        config.locale = None;
+    }
+    if let Err(e) = config.theme.validate() {
+        eprintln!("Warning: {}. Using the default accent.", e);
+        config.theme.accent = None;
+    }
+    let today = Local::now().date_naive();
+    for filter in &config.saved_filters {
+        if let Err(e) = query::Query::parse(&filter.query, today) {
+            eprintln!("Warning: Saved filter \"{}\": {}", filter.name, e);
+        }
+    }
+    Ok((config, migrated))
+}
+
 fn load_config() -> AppConfig {
     let path = match get_config_file() {
         Ok(p) => p,
@@ -95,85 +586,63 @@ fn load_config() -> AppConfig {
     };
 
     if path.exists() {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(config) = serde_json::from_str(&content) {
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| parse_config(&content, is_toml(&path)));
+        match parsed {
+            Ok((config, migrated)) => {
+                if migrated {
+                    if let Err(e) = save_config(&config) {
+                        eprintln!("Warning: Failed to save the upgraded {}: {}", path.display(), e);
+                    }
+                }
                 return config;
             }
+            Err(e) => {
+                // Keep a copy, since the next change of a setting overwrites the file
+                let mut backup_path = path.clone().into_os_string();
+                backup_path.push(".invalid");
+                let backup_path = PathBuf::from(backup_path);
+                eprintln!(
+                    "Warning: Failed to load {}: {}. Using defaults; a copy is in {}.",
+                    path.display(),
+                    e,
+                    backup_path.display()
+                );
+                let _ = fs::copy(&path, &backup_path);
+            }
         }
     }
     AppConfig::default()
 }
 
 fn save_config(config: &AppConfig) -> Result<(), String> {
-    ensure_tasks_dir()?;
     let path = get_config_file()?;
-    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let content = if is_toml(&path) {
+        settings::to_toml(config, fs::read_to_string(&path).ok().as_deref())?
+    } else {
+        serde_json::to_string_pretty(config).map_err(|e| e.to_string())?
+    };
     fs::write(path, content).map_err(|e| e.to_string())
 }
 
-/// Parse a hotkey string like "Cmd+Ctrl+Alt+Shift+=" into a Shortcut
-fn parse_hotkey(hotkey: &str) -> Result<Shortcut, String> {
-    let parts: Vec<&str> = hotkey.split('+').collect();
-    if parts.is_empty() {
-        return Err("Empty hotkey".to_string());
-    }
-
-    let mut modifiers = Modifiers::empty();
-    let key_str = parts.last().ok_or("No key specified")?;
-
-    for part in &parts[..parts.len() - 1] {
-        match part.to_lowercase().as_str() {
-            "cmd" | "command" | "super" | "meta" => modifiers |= Modifiers::SUPER,
-            "ctrl" | "control" => modifiers |= Modifiers::CONTROL,
-            "alt" | "option" => modifiers |= Modifiers::ALT,
-            "shift" => modifiers |= Modifiers::SHIFT,
-            _ => return Err(format!("Unknown modifier: {}", part)),
+fn load_tasks() -> TaskState {
+    let mut state = read_state_file();
+    // Tasks from before ids were kept get one, saved so it sticks
+    let stamped = stamp_ids(&mut state);
+    events::start(&state);
+    if stamped {
+        if let Err(e) = save_tasks(&mut state) {
+            eprintln!("Warning: Failed to save task ids: {}", e);
         }
     }
-
-    let code = match key_str.to_lowercase().as_str() {
-        "a" => Code::KeyA, "b" => Code::KeyB, "c" => Code::KeyC, "d" => Code::KeyD,
-        "e" => Code::KeyE, "f" => Code::KeyF, "g" => Code::KeyG, "h" => Code::KeyH,
-        "i" => Code::KeyI, "j" => Code::KeyJ, "k" => Code::KeyK, "l" => Code::KeyL,
-        "m" => Code::KeyM, "n" => Code::KeyN, "o" => Code::KeyO, "p" => Code::KeyP,
-        "q" => Code::KeyQ, "r" => Code::KeyR, "s" => Code::KeyS, "t" => Code::KeyT,
-        "u" => Code::KeyU, "v" => Code::KeyV, "w" => Code::KeyW, "x" => Code::KeyX,
-        "y" => Code::KeyY, "z" => Code::KeyZ,
-        "0" => Code::Digit0, "1" => Code::Digit1, "2" => Code::Digit2, "3" => Code::Digit3,
-        "4" => Code::Digit4, "5" => Code::Digit5, "6" => Code::Digit6, "7" => Code::Digit7,
-        "8" => Code::Digit8, "9" => Code::Digit9,
-        "=" | "equal" => Code::Equal,
-        "-" | "minus" => Code::Minus,
-        "[" | "bracketleft" => Code::BracketLeft,
-        "]" | "bracketright" => Code::BracketRight,
-        "\\" | "backslash" => Code::Backslash,
-        ";" | "semicolon" => Code::Semicolon,
-        "'" | "quote" => Code::Quote,
-        "`" | "backquote" => Code::Backquote,
-        "," | "comma" => Code::Comma,
-        "." | "period" => Code::Period,
-        "/" | "slash" => Code::Slash,
-        "space" => Code::Space,
-        "enter" | "return" => Code::Enter,
-        "tab" => Code::Tab,
-        "escape" | "esc" => Code::Escape,
-        "backspace" => Code::Backspace,
-        "delete" => Code::Delete,
-        "up" => Code::ArrowUp,
-        "down" => Code::ArrowDown,
-        "left" => Code::ArrowLeft,
-        "right" => Code::ArrowRight,
-        "f1" => Code::F1, "f2" => Code::F2, "f3" => Code::F3, "f4" => Code::F4,
-        "f5" => Code::F5, "f6" => Code::F6, "f7" => Code::F7, "f8" => Code::F8,
-        "f9" => Code::F9, "f10" => Code::F10, "f11" => Code::F11, "f12" => Code::F12,
-        _ => return Err(format!("Unknown key: {}", key_str)),
-    };
-
-    let mods = if modifiers.is_empty() { None } else { Some(modifiers) };
-    Ok(Shortcut::new(mods, code))
+    state
 }
 
-fn load_tasks() -> TaskState {
+fn read_state_file() -> TaskState {
     let path = match get_state_file() {
         Ok(p) => p,
         Err(e) => {
@@ -201,33 +670,505 @@ fn load_tasks() -> TaskState {
     TaskState::default()
 }
 
-fn save_tasks(state: &TaskState) -> Result<(), String> {
+/// Write the lists to state.json as the next revision
+fn save_tasks(state: &mut TaskState) -> Result<(), String> {
     ensure_tasks_dir()?;
+    state.revision += 1;
     let path = get_state_file()?;
     let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())
+    fs::write(path, content).map_err(|e| e.to_string())?;
+    events::record(state);
+    Ok(())
 }
 
-fn append_done(task: &Task) -> Result<(), String> {
+/// Log `task` as completed now; returns what undo needs to take it back out
+fn append_done(task: &Task, config: &AppConfig) -> Result<undo::Completion, String> {
     ensure_tasks_dir()?;
-    let path = get_done_file()?;
-    let date = Local::now().format("%Y-%m-%d").to_string();
+    let now = Local::now();
 
-    let mut content = format!("- {}: {}\n", date, task.text);
+    // The first completion of a month rolls the previous months out of done.md
+    if config.rotate_done_monthly {
+        if let Err(e) = done::rotate_months(now.date_naive()) {
+            eprintln!("Warning: Failed to rotate done.md: {}", e);
+        }
+    }
+    let date = now.format("%Y-%m-%d").to_string();
+    // A malformed format is reported by the formatter rather than panicking
+    use std::fmt::Write as _;
+    let mut time = String::new();
+    if !config.done_time_format.is_empty() && write!(time, "{}", now.format(&config.done_time_format)).is_err() {
+        eprintln!("Warning: Invalid done_time_format: {}", config.done_time_format);
+        time.clear();
+    }
+    let time = Some(time.as_str()).filter(|t| !t.is_empty());
+    let project = task.project.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let logged = match project {
+        Some(project) if config.project_logs == ProjectLogs::Prefix => Task {
+            text: done::prefixed(project, &task.text),
+            ..task.clone()
+        },
+        _ => task.clone(),
+    };
+    done::append_entry(&date, time, &logged)?;
+
+    if let (Some(project), ProjectLogs::Separate) = (project, config.project_logs) {
+        if let Err(e) = done::append_project_entry(project, &date, time, task) {
+            eprintln!("Warning: Failed to write the done log for {}: {}", project, e);
+        }
+    }
+
+    // done.md stays the record of truth; the structured copy is best effort
+    if let Err(e) = done::append_record(&done::DoneRecord::new(task, now.naive_local())) {
+        eprintln!("Warning: Failed to write done.jsonl: {}", e);
+    }
+    events::completed(task);
+    search_index::update_in_background();
+    Ok(undo::Completion {
+        task_id: task.id,
+        date,
+        text: logged.text,
+    })
+}
+
+/// Push backend-originated state changes to the UI and the tray
+fn notify_tasks_changed(app: &AppHandle) {
+    let snapshot = {
+        let state = app.state::<AppState>();
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.clone()
+    };
+    let _ = app.emit("tasks-changed", snapshot);
+    tray::refresh(app);
+    focus_bar::refresh(app);
+}
+
+/// Show a system notification unless do-not-disturb is on
+fn notify(app: &AppHandle, title: &str, body: &str) {
+    notify_with_sound(app, title, body, "");
+}
+
+/// `notify` playing the named system sound; "" plays the platform's default, if any
+fn notify_with_sound(app: &AppHandle, title: &str, body: &str, sound: &str) {
+    if *app.state::<AppState>().dnd.lock().unwrap_or_else(|e| e.into_inner()) {
+        return;
+    }
+    let mut builder = app.notification().builder().title(title).body(body);
+    if !sound.is_empty() {
+        builder = builder.sound(sound);
+    }
+    if let Err(e) = builder.show() {
+        eprintln!("Warning: Failed to show notification: {}", e);
+    }
+}
+
+/// Complete the task with id `id` in `current` without going through the UI
+fn complete_current_task(app: &AppHandle, source: audit::Source, id: u64) -> Result<Task, String> {
+    // The timer's time goes in the log with the task
+    timer::stop_for(app, id)?;
+    let task = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let index = tasks
+            .current
+            .iter()
+            .position(|t| t.id == Some(id))
+            .ok_or_else(|| format!("No current task with id {}", id))?;
+        let task = tasks.current[index].clone();
+        let completion = append_done(&task, &config)?;
+        undo::completed(app, completion);
+        tasks.current.remove(index);
+        save_tasks(&mut tasks)?;
+        undo::record(app, source, "Complete", &tasks);
+        task
+    };
+    notify_tasks_changed(app);
+    Ok(task)
+}
 
-    // Include notes if any exist
-    for note in &task.notes {
-        let status = if note.completed { "✓" } else { "○" };
-        content.push_str(&format!("  {} {}\n", status, note.text));
+/// Last task id handed out by `new_task_id`
+static LAST_TASK_ID: Mutex<u64> = Mutex::new(0);
+
+/// An id for a new task: the time in milliseconds, moved on if that's taken
+fn new_task_id() -> u64 {
+    let mut last = LAST_TASK_ID.lock().unwrap_or_else(|e| e.into_inner());
+    *last = (*last + 1).max(Local::now().timestamp_millis() as u64);
+    *last
+}
+
+/// Give tasks without an id a new one; returns whether any needed it
+fn stamp_ids(state: &mut TaskState) -> bool {
+    let mut stamped = false;
+    for task in state.current.iter_mut().chain(&mut state.shelf).filter(|t| t.id.is_none()) {
+        task.id = Some(new_task_id());
+        stamped = true;
     }
+    stamped
+}
 
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-        .map_err(|e| e.to_string())?;
+/// Append to `current`, overflowing to the shelf once it is full
+fn insert_task(tasks: &mut TaskState, mut task: Task) {
+    task.created.get_or_insert_with(|| Local::now().naive_local());
+    task.id.get_or_insert_with(new_task_id);
+    if tasks.current.len() >= MAX_CURRENT {
+        tasks.shelf.push(task);
+    } else {
+        tasks.current.push(task);
+    }
+}
 
-    file.write_all(content.as_bytes()).map_err(|e| e.to_string())
+/// Give tasks the UI sent without a creation time or id the ones they had before, going
+/// by their text, or `now` and a new id if they're new
+fn stamp_created(new_state: &mut TaskState, old: &TaskState, now: NaiveDateTime) {
+    let known: HashMap<&str, &Task> = old.current.iter().chain(&old.shelf).map(|t| (t.text.as_str(), t)).collect();
+    let mut taken: Vec<u64> = new_state.current.iter().chain(&new_state.shelf).filter_map(|t| t.id).collect();
+    for task in new_state.current.iter_mut().chain(&mut new_state.shelf) {
+        let before = known.get(task.text.as_str());
+        if task.created.is_none() {
+            task.created = Some(before.and_then(|t| t.created).unwrap_or(now));
+        }
+        if task.id.is_none() {
+            let id = before.and_then(|t| t.id).filter(|id| !taken.contains(id)).unwrap_or_else(new_task_id);
+            taken.push(id);
+            task.id = Some(id);
+        }
+    }
+}
+
+/// Add a task from outside the main list UI (tray, capture prompts)
+fn add_task(app: &AppHandle, source: audit::Source, text: &str) -> Result<(), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Task text is empty".to_string());
+    }
+
+    add_new_task(
+        app,
+        source,
+        Task {
+            text: text.to_string(),
+            ..Default::default()
+        },
+    )
+}
+
+fn add_new_task(app: &AppHandle, source: audit::Source, task: Task) -> Result<(), String> {
+    {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        insert_task(&mut tasks, task);
+        save_tasks(&mut tasks)?;
+        undo::record(app, source, "Add Task", &tasks);
+    }
+    notify_tasks_changed(app);
+    Ok(())
+}
+
+/// Take the completed task `text` out of done.md, done.jsonl and its project's log, and
+/// return it as it was before it was completed. `line` is where done.md had it, if known
+fn unlog_completed(line: Option<usize>, date: &str, text: &str) -> Result<Task, String> {
+    let entry = done::remove_entry(line, date, text)?;
+    // done.md only has the text and notes; the structured copy also knows the due
+    // time, project, priority and creation time
+    match done::remove_record(&entry.task.text, &entry.date) {
+        Ok(Some(record)) => {
+            if let Some(project) = &record.project {
+                if let Err(e) = done::remove_project_entry(project, &entry.date, &record.text) {
+                    eprintln!("Warning: Failed to update the done log for {}: {}", project, e);
+                }
+            }
+            Ok(Task {
+                id: record.id,
+                text: record.text,
+                due: record.due,
+                project: record.project,
+                priority: record.priority,
+                created: record.created,
+                time_spent: record.time_spent,
+                ..entry.task
+            })
+        }
+        Ok(None) => Ok(entry.task),
+        Err(e) => {
+            eprintln!("Warning: Failed to update done.jsonl: {}", e);
+            Ok(entry.task)
+        }
+    }
+}
+
+/// Take the completed task `text` back out of done.md (and done.jsonl) and put it in `current`
+/// again. `line` and `date` are from the `DoneEntry` it was picked from
+fn restore_completed(
+    app: &AppHandle,
+    source: audit::Source,
+    line: usize,
+    date: &str,
+    text: &str,
+) -> Result<Task, String> {
+    let task = {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let task = unlog_completed(Some(line), date, text)?;
+        insert_task(&mut tasks, task.clone());
+        save_tasks(&mut tasks)?;
+        undo::record(app, source, "Restore Completed", &tasks);
+        task
+    };
+    notify_tasks_changed(app);
+    Ok(task)
+}
+
+fn day_summary(app: &AppHandle) -> Result<DaySummary, String> {
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let completed = done::entries_on(&today)?.into_iter().map(|e| e.task.text).collect();
+    let open = app.state::<AppState>().tasks.lock().unwrap_or_else(|e| e.into_inner()).current.len();
+    Ok(DaySummary { completed, open })
+}
+
+#[tauri::command]
+fn get_day_summary(app: AppHandle) -> Result<DaySummary, String> {
+    day_summary(&app)
+}
+
+/// Recount today's completions against the daily goal, with a notification the first
+/// time each day that it's reached
+fn update_goal_progress(app: &AppHandle) -> GoalProgress {
+    let state = app.state::<AppState>();
+    let goal = state.config.lock().unwrap_or_else(|e| e.into_inner()).daily_goal;
+    let today = Local::now().date_naive();
+    let completed = done::completed_on(&today.format("%Y-%m-%d").to_string()).unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to count today's completions: {}", e);
+        0
+    });
+    let progress = GoalProgress {
+        date: Some(today),
+        goal,
+        completed,
+        reached: goal > 0 && completed >= goal as usize,
+    };
+    let previous = {
+        let mut stored = state.goal_progress.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *stored, progress.clone())
+    };
+    // Not on the first count after launch, which would repeat an earlier session's notification
+    if progress.reached && previous.date == Some(today) && !previous.reached {
+        notify(app, "Daily goal reached", &format!("{} completed today", completed));
+    }
+    progress
+}
+
+#[tauri::command]
+fn get_daily_goal_progress(app: AppHandle) -> GoalProgress {
+    update_goal_progress(&app)
+}
+
+#[tauri::command]
+fn get_done_log(state: tauri::State<AppState>) -> DoneLogConfig {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).done_log.clone()
+}
+
+/// Point the done log at another file or format; later completions and archives go there
+#[tauri::command]
+fn set_done_log(done_log: DoneLogConfig, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    done_log.validate()?;
+    {
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.done_log = done_log;
+        save_config(&config)?;
+        done::configure(&config.done_log);
+    }
+    tray::refresh(&app);
+    Ok(())
+}
+
+/// The most recent entries in done.md, newest first; `uncomplete_task` takes their line, date and text
+#[tauri::command]
+fn get_recent_done(limit: usize) -> Result<Vec<done::DoneEntry>, String> {
+    done::recent_entries(limit)
+}
+
+/// Undo a completion: the entry leaves the done log and the task goes back to `current`
+#[tauri::command]
+fn uncomplete_task(entry_id: usize, date: String, text: String, app: AppHandle) -> Result<Task, String> {
+    restore_completed(&app, audit::Source::Ui, entry_id, &date, &text)
+}
+
+/// Write a summary of the week containing `week` (default: this week) next to the done log
+#[tauri::command]
+fn generate_weekly_rollup(
+    week: Option<NaiveDate>,
+    state: tauri::State<AppState>,
+) -> Result<report::WeeklyRollup, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    report::weekly_rollup(week.unwrap_or_else(|| Local::now().date_naive()), first_day)
+}
+
+/// Remove same-day duplicates from the done log, merging in the done log at `merge_from` first
+#[tauri::command]
+fn dedupe_done(merge_from: Option<String>, app: AppHandle) -> Result<done::DedupeReport, String> {
+    let report = done::dedupe(merge_from.as_deref().map(Path::new))?;
+    tray::refresh(&app);
+    Ok(report)
+}
+
+/// Compress or delete archives per `archive_retention`; `dry_run` only reports what would happen
+#[tauri::command]
+fn prune_archives(dry_run: bool, state: tauri::State<AppState>) -> Result<Vec<done::PruneAction>, String> {
+    let retention = state.config.lock().unwrap_or_else(|e| e.into_inner()).archive_retention.clone();
+    done::prune_archives(&retention, Local::now().date_naive(), dry_run)
+}
+
+/// Write everything completed in `range`, across the done log and all archives, into one file at `path`
+#[tauri::command]
+fn export_history(range: done::DateRange, path: String) -> Result<usize, String> {
+    report::export_history(range, &path)
+}
+
+/// Archived done logs, newest first
+#[tauri::command]
+fn list_archives() -> Result<Vec<done::ArchiveInfo>, String> {
+    done::list_archives()
+}
+
+/// Entries of one archived done log, oldest first
+#[tauri::command]
+fn read_archive(name: String) -> Result<Vec<done::DoneEntry>, String> {
+    done::read_archive(&name)
+}
+
+/// Completed tasks in done.md, the archives and done.jsonl whose text or notes contain
+/// `query`, or match it as a pattern with `regex`
+#[tauri::command]
+fn search_done(
+    query: String,
+    range: Option<done::DateRange>,
+    regex: Option<bool>,
+) -> Result<Vec<done::DoneMatch>, String> {
+    search_index::search(&query, range.unwrap_or_default(), regex.unwrap_or(false))
+}
+
+/// Completions in `range` counted per day, week or month, for charts
+#[tauri::command]
+fn get_stats(
+    range: Option<done::DateRange>,
+    granularity: Option<stats::Granularity>,
+    state: tauri::State<AppState>,
+) -> Result<stats::Stats, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    stats::get_stats(range.unwrap_or_default(), granularity.unwrap_or_default(), first_day)
+}
+
+/// Completions on every day of `year`, for a heatmap
+#[tauri::command]
+fn get_heatmap(year: i32, state: tauri::State<AppState>) -> Result<stats::Heatmap, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    stats::get_heatmap(year, first_day)
+}
+
+/// Lead time, age of open tasks and weekly throughput over `range`
+#[tauri::command]
+fn get_flow_metrics(
+    range: Option<done::DateRange>,
+    state: tauri::State<AppState>,
+) -> Result<stats::FlowMetrics, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    stats::get_flow_metrics(&tasks, range.unwrap_or_default(), first_day, Local::now().naive_local())
+}
+
+/// Completions by hour of the day and day of the week over `range` (all time if unset)
+#[tauri::command]
+fn get_completion_patterns(
+    range: Option<done::DateRange>,
+    state: tauri::State<AppState>,
+) -> Result<stats::CompletionPatterns, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    stats::get_completion_patterns(range.unwrap_or_default(), first_day)
+}
+
+/// This week's completions by project, unfinished additions, stale shelf tasks and the
+/// streak; with `write`, also saved as a markdown file next to the done log
+#[tauri::command]
+fn weekly_review(write: Option<bool>, state: tauri::State<AppState>) -> Result<report::WeeklyReview, String> {
+    let (work_week, first_day) = {
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        (config.work_week.clone(), config.week_start)
+    };
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    report::weekly_review(&tasks, &work_week, first_day, Local::now().naive_local(), write.unwrap_or(false))
+}
+
+/// How long the open tasks in a list ("current" or "shelf") or project will take at the recent pace
+#[tauri::command]
+fn estimate_completion(list_or_project: String, state: tauri::State<AppState>) -> Result<stats::Forecast, String> {
+    let work_week = state.config.lock().unwrap_or_else(|e| e.into_inner()).work_week.clone();
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    stats::estimate_completion(&tasks, &list_or_project, &work_week, Local::now().date_naive())
+}
+
+/// Totals, busiest weeks, longest streak and top projects and tags of `year`, written up as
+/// markdown; `write` also saves it next to the done log
+#[tauri::command]
+fn year_in_review(
+    year: i32,
+    write: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<report::YearInReview, String> {
+    let (work_week, first_day) = {
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        (config.work_week.clone(), config.week_start)
+    };
+    report::year_in_review(year, &work_week, first_day, Local::now().date_naive(), write.unwrap_or(false))
+}
+
+/// Completions in `range` and open tasks per tag or project, with their shares
+#[tauri::command]
+fn get_breakdown(
+    dimension: stats::Dimension,
+    range: Option<done::DateRange>,
+    state: tauri::State<AppState>,
+) -> Result<stats::Breakdown, String> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    stats::get_breakdown(&tasks, dimension, range.unwrap_or_default())
+}
+
+/// Daily open and done counts of `project`'s tasks over `range`, for a burndown chart
+#[tauri::command]
+fn get_burndown(
+    project: String,
+    range: Option<done::DateRange>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<stats::BurndownDay>, String> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    stats::get_burndown(&tasks, &project, range.unwrap_or_default(), Local::now().date_naive())
+}
+
+/// Write one of the computed metrics over `range` to `path` as CSV or JSON; returns the rows written
+#[tauri::command]
+fn export_stats(
+    metric: stats::StatsMetric,
+    range: Option<done::DateRange>,
+    format: stats::ExportFormat,
+    path: String,
+    state: tauri::State<AppState>,
+) -> Result<usize, String> {
+    let first_day = state.config.lock().unwrap_or_else(|e| e.into_inner()).week_start;
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let now = Local::now().naive_local();
+    stats::export_stats(&tasks, metric, range.unwrap_or_default(), format, &path, first_day, now)
+}
+
+/// Every completion in done.jsonl, oldest first
+#[tauri::command]
+fn get_done_records() -> Result<Vec<done::DoneRecord>, String> {
+    done::read_records()
+}
+
+/// Tasks past their due time, longest overdue first
+#[tauri::command]
+fn get_overdue_tasks(state: tauri::State<AppState>) -> Vec<Task> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    scheduler::overdue_tasks(&tasks, Local::now().naive_local())
 }
 
 #[tauri::command]
@@ -235,167 +1176,771 @@ fn get_tasks(state: tauri::State<AppState>) -> TaskState {
     state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone()
 }
 
+/// Open tasks filtered and sorted as `options` say, e.g. `{"status": "overdue", "sort": "priority"}`
+#[tauri::command]
+fn get_tasks_view(options: view::ViewOptions, state: tauri::State<AppState>) -> Result<Vec<query::QueryMatch>, String> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    view::tasks_view(&tasks, &options, Local::now().naive_local())
+}
+
+/// Tasks in current, the shelf and the done log whose text or notes fuzzily match `query`,
+/// best first, or match it as a pattern with `regex`
 #[tauri::command]
-fn save_state(new_state: TaskState, state: tauri::State<AppState>) -> Result<(), String> {
-    let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
-    *tasks = new_state.clone();
-    save_tasks(&tasks)
+fn search(
+    query: String,
+    regex: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<search::SearchResult>, String> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    search::search(&tasks, &query, regex.unwrap_or(false))
 }
 
+/// Tasks in current and on the shelf matching a filter expression, e.g.
+/// `tag:work due:<friday priority:high -shelved`
 #[tauri::command]
-fn complete_task(task: Task, _state: tauri::State<AppState>) -> Result<(), String> {
-    append_done(&task)?;
+fn query_tasks(expr: String, state: tauri::State<AppState>) -> Result<Vec<query::QueryMatch>, String> {
+    let now = Local::now().naive_local();
+    let query = query::Query::parse(&expr, now.date())?;
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    Ok(query.run(&tasks, now))
+}
+
+/// Notes of the task at `index` in `list` matching `query`, best first; for "done",
+/// `index` is the entry's line id as `search` returns it
+#[tauri::command]
+fn search_notes(
+    list: search::SearchList,
+    index: usize,
+    query: String,
+    regex: Option<bool>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<search::NoteMatch>, String> {
+    let task = {
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        match list {
+            search::SearchList::Current => tasks.current.get(index).cloned(),
+            search::SearchList::Shelf => tasks.shelf.get(index).cloned(),
+            search::SearchList::Done => done::entries()?.into_iter().find(|e| e.line == index).map(|e| e.task),
+        }
+    };
+    let task = task.ok_or_else(|| format!("No task at position {}", index))?;
+    search::search_notes(&task, &query, regex.unwrap_or(false))
+}
+
+/// Task texts, tags and projects from the done history that start with `prefix`, for quick-add
+#[tauri::command]
+fn suggest(
+    prefix: String,
+    limit: Option<usize>,
+    state: tauri::State<AppState>,
+) -> Result<Vec<suggest::Suggestion>, String> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    suggest::suggest(&tasks, &prefix, limit.unwrap_or(10))
+}
+
+#[tauri::command]
+fn get_saved_filters(state: tauri::State<AppState>) -> Vec<SavedFilter> {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).saved_filters.clone()
+}
+
+/// Add a saved filter, or change the query of the one with this name
+#[tauri::command]
+fn save_filter(name: String, query: String, state: tauri::State<AppState>) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Filter name is empty".to_string());
+    }
+    query::Query::parse(&query, Local::now().date_naive())?;
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    match config.saved_filters.iter_mut().find(|f| f.name == name) {
+        Some(filter) => filter.query = query,
+        None => config.saved_filters.push(SavedFilter { name, query }),
+    }
+    save_config(&config)
+}
+
+#[tauri::command]
+fn delete_filter(name: String, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    let before = config.saved_filters.len();
+    config.saved_filters.retain(|f| f.name != name);
+    if config.saved_filters.len() == before {
+        return Err(format!("No saved filter named {}", name));
+    }
+    save_config(&config)
+}
+
+/// Every saved filter with the tasks it currently matches
+#[tauri::command]
+fn get_smart_lists(state: tauri::State<AppState>) -> Vec<query::SmartList> {
+    let filters = state.config.lock().unwrap_or_else(|e| e.into_inner()).saved_filters.clone();
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    query::smart_lists(&filters, &tasks, Local::now().naive_local())
+}
+
+/// A save from the UI that went through
+#[derive(Debug, Serialize, Clone)]
+struct Saved {
+    /// Revision the lists are at now, for the next save to carry
+    revision: u64,
+    /// Tasks in the saved lists that duplicate each other
+    duplicates: Vec<duplicates::DuplicateGroup>,
+}
+
+/// Why a save from the UI didn't go through
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum SaveError {
+    /// The lists changed since the revision the save carries, e.g. from the tray or the
+    /// scheduler; `current` is what they are now. See `merge_tasks`
+    Conflict { current: TaskState },
+    Failed { message: String },
+}
+
+/// Save the UI's task lists, unless they changed since the revision it carries
+#[tauri::command]
+fn save_state(new_state: TaskState, app: AppHandle, state: tauri::State<AppState>) -> Result<Saved, SaveError> {
+    let mut new_state = new_state;
+    let duplicates = duplicates::find_duplicates(&new_state);
+    let revision = {
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if new_state.revision != tasks.revision {
+            return Err(SaveError::Conflict { current: tasks.clone() });
+        }
+        stamp_created(&mut new_state, &tasks, Local::now().naive_local());
+        let dropped = deleted::dropped(&tasks, &new_state);
+        save_tasks(&mut new_state).map_err(|message| SaveError::Failed { message })?;
+        deleted::keep(dropped);
+        *tasks = new_state;
+        undo::record(&app, audit::Source::Ui, undo::EDIT, &tasks);
+        tasks.revision
+    };
+    tray::refresh(&app);
+    focus_bar::refresh(&app);
+    Ok(Saved { revision, duplicates })
+}
+
+/// The UI's changes since `base` (the lists as it loaded them) made to the lists as they
+/// are now, for saving after a conflict. Where both changed a task, the UI's edit wins
+#[tauri::command]
+fn merge_tasks(base: TaskState, ours: TaskState, state: tauri::State<AppState>) -> TaskState {
+    let theirs = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    merge::merge(&base, &ours, &theirs)
+}
+
+/// Open tasks that duplicate each other, ignoring case, spacing and punctuation
+#[tauri::command]
+fn find_duplicates(state: tauri::State<AppState>) -> Vec<duplicates::DuplicateGroup> {
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    duplicates::find_duplicates(&tasks)
+}
+
+/// Log `task` as completed; the UI takes it out of the lists with its next save
+#[tauri::command]
+fn complete_task(mut task: Task, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    if let Some(id) = task.id {
+        timer::stop_for(&app, id)?;
+        // Stopping the timer added its time to the lists' copy of the task
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(timed) = tasks.current.iter().chain(&tasks.shelf).find(|t| t.id == Some(id)) {
+            task.time_spent = timed.time_spent;
+        }
+    }
+    let config = state.config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let completion = append_done(&task, &config)?;
+    undo::completed(&app, completion);
     Ok(())
 }
 
+/// Add a task; returns the open tasks it duplicates, if any, so the UI can point them out
+#[tauri::command]
+fn quick_add(
+    text: String,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Vec<duplicates::Duplicate>, String> {
+    let existing = duplicates::matching(&state.tasks.lock().unwrap_or_else(|e| e.into_inner()), &text);
+    add_task(&app, audit::Source::Capture, &text)?;
+    Ok(existing)
+}
+
+/// Add a task that reappears in `current` on `rule`, at `at` o'clock
+#[tauri::command]
+fn add_recurring(text: String, rule: Recurrence, at: Option<NaiveTime>, app: AppHandle) -> Result<(), String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Task text is empty".to_string());
+    }
+    if let Recurrence::Weekly(days) = &rule {
+        if days.is_empty() {
+            return Err("Pick at least one day for a weekly task".to_string());
+        }
+    }
+
+    {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut recurring = RecurringTask {
+            task: Task {
+                text: text.to_string(),
+                ..Default::default()
+            },
+            rule,
+            at: at.unwrap_or_default(),
+            next: None,
+        };
+        recurring.next = recurring.next_after(Local::now().naive_local());
+        tasks.recurring.push(recurring);
+        save_tasks(&mut tasks)?;
+        undo::record(&app, audit::Source::Ui, "Add Recurring Task", &tasks);
+    }
+    notify_tasks_changed(&app);
+    Ok(())
+}
+
+/// Move a current task to the shelf until `until`, when the scheduler brings it back
+#[tauri::command]
+fn snooze_task(index: usize, until: NaiveDateTime, app: AppHandle) -> Result<(), String> {
+    {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        if index >= tasks.current.len() {
+            return Err(format!("No current task at position {}", index + 1));
+        }
+        let mut task = tasks.current.remove(index);
+        task.snoozed_until = Some(until);
+        tasks.shelf.push(task);
+        save_tasks(&mut tasks)?;
+        undo::record(&app, audit::Source::Ui, "Snooze", &tasks);
+    }
+    notify_tasks_changed(&app);
+    Ok(())
+}
+
+/// Put the tasks that just came back off the shelf away again for `snooze_again_minutes`
+fn snooze_woken(app: &AppHandle, source: audit::Source) -> Result<usize, String> {
+    let count = {
+        let state = app.state::<AppState>();
+        let minutes = state.config.lock().unwrap_or_else(|e| e.into_inner()).snooze_again_minutes;
+        let until = Local::now().naive_local() + chrono::TimeDelta::minutes(minutes as i64);
+        let woken = std::mem::take(&mut *state.woken.lock().unwrap_or_else(|e| e.into_inner()));
+
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut count = 0;
+        for text in woken {
+            // Ones already completed or moved by hand stay where they are
+            let Some(index) = tasks.current.iter().position(|t| t.text == text) else {
+                continue;
+            };
+            let mut task = tasks.current.remove(index);
+            task.snoozed_until = Some(until);
+            tasks.shelf.push(task);
+            count += 1;
+        }
+        save_tasks(&mut tasks)?;
+        undo::record(app, source, "Snooze Again", &tasks);
+        count
+    };
+    notify_tasks_changed(app);
+    Ok(count)
+}
+
+#[tauri::command]
+fn snooze_again(app: AppHandle) -> Result<usize, String> {
+    snooze_woken(&app, audit::Source::Ui)
+}
+
 #[tauri::command]
 fn hide_window(app: AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
-        let _ = window.hide();
+        if window.is_visible().unwrap_or(false) {
+            let _ = window.hide();
+            // Hand focus back to whatever was in front before the window opened
+            #[cfg(target_os = "macos")]
+            panel::restore_frontmost();
+        }
     }
 }
 
 #[tauri::command]
-fn archive_done() -> Result<String, String> {
+fn archive_done(app: AppHandle) -> Result<String, String> {
+    archive_done_from(&app, audit::Source::Ui)
+}
+
+/// Archive done.md and the project logs, from `source`; returns the archive's file name
+fn archive_done_from(app: &AppHandle, source: audit::Source) -> Result<String, String> {
     let done_file = get_done_file()?;
     if !done_file.exists() {
         return Err("No completed tasks to archive".to_string());
     }
 
     let date = Local::now().format("%Y-%m-%d_%H%M%S").to_string();
-    let archive_name = format!("done_{}.md", date);
-    let archive_path = get_tasks_dir()?.join(&archive_name);
+    let archive_name = done::archive_name(&date);
+    let archive_path = done::archive_dir()?.join(&archive_name);
 
-    fs::copy(&done_file, &archive_path).map_err(|e| e.to_string())?;
-    fs::write(&done_file, "").map_err(|e| e.to_string())?;
+    let len = done::archive_log(&done_file, &archive_path)?;
+    if let Err(e) = report::append_tally(&archive_path) {
+        eprintln!("Warning: Failed to add the summary to {}: {}", archive_name, e);
+    }
+    let mut archived = vec![done::ArchivedLog {
+        log: done_file,
+        archive: archive_path,
+        len,
+    }];
+    // Project logs are archived as a set with the main one
+    match done::archive_project_logs(&date) {
+        Ok(logs) => archived.extend(logs),
+        Err(e) => eprintln!("Warning: Failed to archive project done logs: {}", e),
+    }
+    undo::record_archive(app, source, archived);
+    search_index::update_in_background();
+    tray::refresh(app);
 
     Ok(archive_name)
 }
 
 #[tauri::command]
-fn get_hotkey(state: tauri::State<AppState>) -> String {
-    state.config.lock().unwrap_or_else(|e| e.into_inner()).hotkey.clone()
+fn get_tray_icon_style(state: tauri::State<AppState>) -> TrayIconStyle {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).tray_icon_style
+}
+
+#[tauri::command]
+fn set_tray_icon_style(style: TrayIconStyle, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.tray_icon_style = style;
+        save_config(&config)?;
+    }
+    tray::refresh_icon(&app);
+    Ok(())
 }
 
 #[tauri::command]
-fn set_hotkey(hotkey: String, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
-    // Validate the hotkey can be parsed
-    let new_shortcut = parse_hotkey(&hotkey)?;
+fn get_tray_menu(state: tauri::State<AppState>) -> Vec<TrayMenuEntry> {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).tray_menu.clone()
+}
 
-    // Unregister the old shortcut
+#[tauri::command]
+fn set_tray_menu(entries: Vec<TrayMenuEntry>, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
     {
-        let current = state.current_shortcut.lock().unwrap_or_else(|e| e.into_inner());
-        if let Some(old_shortcut) = current.as_ref() {
-            let _ = app.global_shortcut().unregister(*old_shortcut);
-        }
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.tray_menu = entries.into_iter().filter(|e| *e != TrayMenuEntry::Unknown).collect();
+        save_config(&config)?;
     }
+    tray::refresh(&app);
+    Ok(())
+}
 
-    // Register the new shortcut
-    let app_handle = app.clone();
-    app.global_shortcut().on_shortcut(new_shortcut, move |_app, _shortcut, event| {
-        if event.state == ShortcutState::Pressed {
-            toggle_window(&app_handle);
-        }
-    }).map_err(|e| e.to_string())?;
+#[tauri::command]
+fn get_tray_click(state: tauri::State<AppState>) -> TrayClickConfig {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).tray_click
+}
+
+#[tauri::command]
+fn set_tray_click(actions: TrayClickConfig, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    {
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.tray_click = actions;
+        save_config(&config)?;
+    }
+    tray::apply_click_config(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_window_anchor(state: tauri::State<AppState>) -> WindowAnchor {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).window_anchor
+}
 
-    // Update the stored shortcut
+#[tauri::command]
+fn set_window_anchor(
+    anchor: WindowAnchor,
+    margin: Option<WindowMargin>,
+    state: tauri::State<AppState>,
+) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.window_anchor = anchor;
+    if let Some(margin) = margin {
+        config.window_margins.insert(anchor, margin);
+    }
+    // A freshly chosen anchor should take effect on every screen
+    config.screen_positions.clear();
+    save_config(&config)
+}
+
+/// Forget where the window was dragged on each screen and go back to the anchor
+#[tauri::command]
+fn clear_screen_positions(state: tauri::State<AppState>) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.screen_positions.clear();
+    save_config(&config)
+}
+
+#[tauri::command]
+fn get_hide_on_blur(state: tauri::State<AppState>) -> bool {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).hide_on_blur
+}
+
+#[tauri::command]
+fn set_hide_on_blur(enabled: bool, state: tauri::State<AppState>) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.hide_on_blur = enabled;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn get_pinned(state: tauri::State<AppState>) -> bool {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).pinned
+}
+
+#[tauri::command]
+fn set_pinned(pinned: bool, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
     {
-        let mut current = state.current_shortcut.lock().unwrap_or_else(|e| e.into_inner());
-        *current = Some(new_shortcut);
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.pinned = pinned;
+        save_config(&config)?;
+    }
+    window::apply_pinned(&app);
+    let _ = app.emit("pinned-changed", pinned);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_panel_mode(state: tauri::State<AppState>) -> bool {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).panel_mode
+}
+
+#[tauri::command]
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+fn set_panel_mode(enabled: bool, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    if enabled && cfg!(not(target_os = "macos")) {
+        return Err("Panel mode is only available on macOS".to_string());
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(window) = app.get_webview_window("main") {
+        panel::set_enabled(&window, enabled)?;
     }
 
-    // Save to config
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    config.panel_mode = enabled;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn get_window_appearance(state: tauri::State<AppState>) -> WindowAppearance {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).window_appearance
+}
+
+#[tauri::command]
+fn set_window_appearance(
+    appearance: WindowAppearance,
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<(), String> {
     {
         let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
-        config.hotkey = hotkey;
+        config.window_appearance = WindowAppearance {
+            opacity: appearance.opacity.clamp(0.0, 1.0),
+            ..appearance
+        };
         save_config(&config)?;
     }
+    window::apply_appearance(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_theme(state: tauri::State<AppState>) -> ThemeConfig {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).theme.clone()
+}
 
+#[tauri::command]
+fn set_theme(theme: ThemeConfig, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    theme.validate()?;
+    {
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.theme = theme;
+        save_config(&config)?;
+    }
+    window::apply_theme(&app);
     Ok(())
 }
 
+#[tauri::command]
+fn get_window_margins(state: tauri::State<AppState>) -> HashMap<WindowAnchor, WindowMargin> {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).window_margins.clone()
+}
+
+/// Register or remove the login item to match `launch_at_login`. The window starts
+/// hidden either way, so a login launch only puts the app in the menu bar
+fn apply_autostart(app: &AppHandle) -> Result<(), String> {
+    let wanted = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).launch_at_login;
+    let autostart = app.autolaunch();
+    if autostart.is_enabled().map_err(|e| e.to_string())? == wanted {
+        return Ok(());
+    }
+    let result = if wanted { autostart.enable() } else { autostart.disable() };
+    result.map_err(|e| format!("Failed to update the login item: {}", e))
+}
+
+/// Whether the app is registered to start at login
+#[tauri::command]
+fn get_autostart(app: AppHandle) -> Result<bool, String> {
+    app.autolaunch().is_enabled().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_autostart(enabled: bool, app: AppHandle, state: tauri::State<AppState>) -> Result<(), String> {
+    let previous = {
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut config.launch_at_login, enabled)
+    };
+    if let Err(e) = apply_autostart(&app) {
+        state.config.lock().unwrap_or_else(|e| e.into_inner()).launch_at_login = previous;
+        return Err(e);
+    }
+    save_config(&state.config.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+#[tauri::command]
+fn get_dnd(state: tauri::State<AppState>) -> bool {
+    *state.dnd.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[tauri::command]
+fn set_dnd(enabled: bool, app: AppHandle) {
+    apply_dnd(&app, enabled);
+}
+
+/// Flip do-not-disturb and let the tray checkbox and UI catch up
+fn apply_dnd(app: &AppHandle, enabled: bool) {
+    {
+        let state = app.state::<AppState>();
+        *state.dnd.lock().unwrap_or_else(|e| e.into_inner()) = enabled;
+    }
+    let _ = app.emit("dnd-changed", enabled);
+    tray::refresh(app);
+}
+
 fn toggle_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
         if window.is_visible().unwrap_or(false) {
-            let _ = window.hide();
-        } else {
-            #[cfg(target_os = "macos")]
-            {
-                let mtm = MainThreadMarker::new().unwrap();
+            hide_window(app.clone());
+        } else if !window::just_hidden_by_blur() {
+            show_window(app);
+        }
+    }
+}
 
-                // Get mouse position (in screen coordinates, origin bottom-left)
-                let mouse_pos = NSEvent::mouseLocation();
-                let screens = NSScreen::screens(mtm);
-
-                // Find which screen contains the mouse cursor
-                for screen in screens.iter() {
-                    let frame = screen.frame();
-
-                    // Check if mouse is within this screen's bounds
-                    if mouse_pos.x >= frame.origin.x
-                        && mouse_pos.x < frame.origin.x + frame.size.width
-                        && mouse_pos.y >= frame.origin.y
-                        && mouse_pos.y < frame.origin.y + frame.size.height
-                    {
-                        let window_width = 400.0; // Fixed window width from tauri.conf.json
-
-                        // Position at top-right of this screen
-                        let x = frame.origin.x + frame.size.width - window_width;
-                        let y = frame.origin.y + frame.size.height; // Top of screen in Cocoa coords
-
-                        // Convert to Tauri coordinates (top-left origin)
-                        if let Some(main_screen) = NSScreen::mainScreen(mtm) {
-                            let main_height = main_screen.frame().size.height;
-                            let flipped_y = main_height - y;
-                            let _ = window.set_position(tauri::LogicalPosition::new(x, flipped_y));
-                        }
-                        break;
-                    }
-                }
+/// Position the main window on the screen under the cursor and focus it
+fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        window::place(app, &window);
+        #[cfg(target_os = "macos")]
+        {
+            if panel::is_enabled() {
+                panel::show(&window);
+                return;
             }
+            panel::remember_frontmost();
+        }
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
 
-            #[cfg(not(target_os = "macos"))]
-            {
-                // Fallback for non-macOS: use primary monitor
-                if let Ok(Some(monitor)) = window.primary_monitor() {
-                    let screen_size = monitor.size();
-                    let screen_position = monitor.position();
-                    if let Ok(window_size) = window.outer_size() {
-                        let x = screen_position.x + (screen_size.width as i32) - (window_size.width as i32);
-                        let y = screen_position.y;
-                        let _ = window.set_position(tauri::PhysicalPosition::new(x, y));
-                    }
-                }
-            }
+/// Show the window with the settings panel open
+fn show_settings(app: &AppHandle) {
+    show_window(app);
+    let _ = app.emit("open-settings", ());
+}
 
-            let _ = window.show();
-            let _ = window.set_focus();
-        }
+/// Show the window with today's summary open
+fn show_summary(app: &AppHandle) {
+    show_window(app);
+    let _ = app.emit("open-summary", ());
+}
+
+/// Open the small capture window rather than the full task list
+fn show_quick_add(app: &AppHandle) {
+    if let Some(capture) = app.get_webview_window("capture") {
+        window::center_on_cursor_screen(&capture);
+        let _ = capture.show();
+        let _ = capture.set_focus();
+        let _ = app.emit_to("capture", "capture-opened", ());
+    }
+}
+
+#[tauri::command]
+fn show_capture(app: AppHandle) {
+    show_quick_add(&app);
+}
+
+#[tauri::command]
+fn hide_capture(app: AppHandle) {
+    if let Some(capture) = app.get_webview_window("capture") {
+        let _ = capture.hide();
+    }
+}
+
+/// Add the captured text as a task and close the capture window
+#[tauri::command]
+fn submit_capture(text: String, app: AppHandle) -> Result<(), String> {
+    hide_capture(app.clone());
+    if text.trim().is_empty() {
+        return Ok(());
     }
+    add_task(&app, audit::Source::Capture, &text)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    profiles::init();
     let initial_state = load_tasks();
     let initial_config = load_config();
+    done::configure(&initial_config.done_log);
+    locale::configure(&initial_config.date_format, initial_config.locale.as_deref());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_autostart::init(MacosLauncher::LaunchAgent, None))
         .manage(AppState {
-            tasks: Mutex::new(initial_state),
+            tasks: Mutex::new(initial_state.clone()),
             config: Mutex::new(initial_config.clone()),
-            current_shortcut: Mutex::new(None),
+            shortcuts: Mutex::new(HashMap::new()),
+            hotkey_status: Mutex::new(HashMap::new()),
+            dnd: Mutex::new(false),
+            hotkeys_suspended: Mutex::new(false),
+            tray_clicks: Mutex::new(tray::ClickDebounce::default()),
+            woken: Mutex::new(Vec::new()),
+            goal_progress: Mutex::new(GoalProgress::default()),
+            history: Mutex::new(undo::History::load(&initial_state)),
+            timer: Mutex::new(timer::load()),
+            pomodoro: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             get_tasks,
+            get_tasks_view,
+            search,
+            query_tasks,
+            search_notes,
+            suggest,
+            get_saved_filters,
+            save_filter,
+            delete_filter,
+            get_smart_lists,
+            get_day_summary,
+            get_daily_goal_progress,
+            get_done_records,
+            get_recent_done,
+            get_done_log,
+            set_done_log,
+            uncomplete_task,
+            list_archives,
+            read_archive,
+            prune_archives,
+            dedupe_done,
+            search_done,
+            get_stats,
+            get_heatmap,
+            get_flow_metrics,
+            get_completion_patterns,
+            weekly_review,
+            year_in_review,
+            estimate_completion,
+            get_breakdown,
+            get_burndown,
+            export_stats,
+            generate_weekly_rollup,
+            export_history,
+            get_overdue_tasks,
             save_state,
+            merge_tasks,
+            audit::get_audit_log,
+            deleted::list_recently_deleted,
+            deleted::recover_deleted,
             complete_task,
+            quick_add,
+            find_duplicates,
+            add_recurring,
+            snooze_task,
+            snooze_again,
             hide_window,
+            show_capture,
+            hide_capture,
+            submit_capture,
             archive_done,
-            get_hotkey,
-            set_hotkey,
+            hotkeys::get_hotkey,
+            hotkeys::set_hotkey,
+            hotkeys::get_hotkeys,
+            hotkeys::set_action_hotkey,
+            hotkeys::set_hotkey_raw,
+            hotkeys::clear_action_hotkey,
+            hotkeys::validate_hotkey,
+            hotkeys::get_hotkey_status,
+            hotkeys::get_toggle_mode,
+            hotkeys::set_toggle_mode,
+            hotkeys::suspend_hotkeys,
+            hotkeys::resume_hotkeys,
+            hotkeys::get_hotkeys_suspended,
+            hotkeys::get_double_tap,
+            hotkeys::set_double_tap,
+            get_tray_icon_style,
+            set_tray_icon_style,
+            get_tray_menu,
+            set_tray_menu,
+            get_tray_click,
+            set_tray_click,
+            get_window_anchor,
+            set_window_anchor,
+            get_window_margins,
+            clear_screen_positions,
+            get_window_appearance,
+            focus_bar::get_focus_task,
+            focus_bar::get_focus_bar,
+            focus_bar::set_focus_bar,
+            set_window_appearance,
+            get_theme,
+            set_theme,
+            get_hide_on_blur,
+            get_pinned,
+            get_panel_mode,
+            set_panel_mode,
+            set_pinned,
+            set_hide_on_blur,
+            get_dnd,
+            set_dnd,
+            get_autostart,
+            set_autostart,
+            settings::get_config,
+            settings::update_config,
+            undo::undo,
+            undo::redo,
+            undo::get_undo_status,
+            events::get_task_history,
+            events::get_state_at,
+            snapshots::create_snapshot,
+            snapshots::list_snapshots,
+            snapshots::restore_snapshot,
+            snapshots::delete_snapshot,
+            timer::start_timer,
+            timer::stop_timer,
+            timer::get_active_timer,
+            pomodoro::start_pomodoro,
+            pomodoro::pause_pomodoro,
+            pomodoro::skip_pomodoro,
+            pomodoro::stop_pomodoro,
+            pomodoro::get_pomodoro,
+            settings::export_settings,
+            settings::import_settings,
+            profiles::get_profiles,
+            profiles::save_profile,
+            profiles::delete_profile,
+            profiles::switch_profile,
         ])
         .setup(move |app| {
             // Hide from dock on macOS
@@ -406,64 +1951,84 @@ pub fn run() {
                 ns_app.setActivationPolicy(NSApplicationActivationPolicy::Accessory);
             }
 
-            // Create tray menu
-            let archive_item = MenuItem::with_id(app, "archive", "Archive Completed", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&archive_item, &quit_item])?;
-
-            // Calculate initial task count for tooltip
-            let task_count = {
-                let state = app.state::<AppState>();
-                let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
-                tasks.current.len() + tasks.shelf.len()
-            };
-            let tooltip = format!("Task Log ({} tasks)", task_count);
-
-            // Build tray icon
-            let _tray = TrayIconBuilder::new()
+            // Build tray icon with the current tasks listed in its menu
+            let menu = tray::build_menu(app.handle())?;
+            let tooltip = tray::tooltip(app.handle());
+            let _tray = TrayIconBuilder::with_id(tray::TRAY_ID)
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .tooltip(&tooltip)
-                .show_menu_on_left_click(false)
+                .show_menu_on_left_click(initial_config.tray_click.left == TrayClickAction::ShowMenu)
                 .on_menu_event(|app, event| {
-                    match event.id.as_ref() {
-                        "archive" => {
-                            let _ = archive_done();
-                        }
-                        "quit" => {
-                            app.exit(0);
-                        }
-                        _ => {}
-                    }
+                    tray::handle_menu_event(app, event.id.as_ref());
                 })
                 .on_tray_icon_event(|tray, event| {
-                    if let tauri::tray::TrayIconEvent::Click { .. } = event {
-                        toggle_window(tray.app_handle());
-                    }
+                    tray::handle_tray_event(tray.app_handle(), event);
                 })
                 .build(app)?;
+            tray::refresh_icon(app.handle());
 
-            // Register global shortcut from config
-            let shortcut = parse_hotkey(&initial_config.hotkey)
-                .unwrap_or_else(|_| Shortcut::new(
-                    Some(Modifiers::SUPER | Modifiers::CONTROL | Modifiers::ALT | Modifiers::SHIFT),
-                    Code::Equal,
-                ));
+            // Accept links and text dropped onto the menu bar icon
+            #[cfg(target_os = "macos")]
+            tray_drop::install(app.handle());
 
-            // Store the current shortcut
-            {
-                let state = app.state::<AppState>();
-                let mut current = state.current_shortcut.lock().unwrap_or_else(|e| e.into_inner());
-                *current = Some(shortcut);
+            // Open over fullscreen apps on the current Space instead of jumping to the desktop
+            #[cfg(target_os = "macos")]
+            for window in app.webview_windows().values() {
+                panel::join_all_spaces(window);
             }
 
-            let app_handle = app.handle().clone();
-            app.global_shortcut().on_shortcut(shortcut, move |_app, _shortcut, event| {
-                // Only toggle on key press, not release
-                if event.state == ShortcutState::Pressed {
-                    toggle_window(&app_handle);
+            if let Some(window) = app.get_webview_window("main") {
+                window::restore_size(app.handle(), &window);
+                window::apply_pinned(app.handle());
+                window::apply_appearance(app.handle());
+                window::apply_theme(app.handle());
+                #[cfg(target_os = "macos")]
+                if initial_config.panel_mode {
+                    if let Err(e) = panel::set_enabled(&window, true) {
+                        eprintln!("Warning: Failed to enable panel mode: {}", e);
+                    }
                 }
-            })?;
+
+                let app_handle = app.handle().clone();
+                window.on_window_event(move |event| match event {
+                    // Redraw monochrome tray icons when the menu bar switches light/dark
+                    tauri::WindowEvent::ThemeChanged(_) => tray::refresh_icon(&app_handle),
+                    tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                        window::remember_bounds(&app_handle)
+                    }
+                    tauri::WindowEvent::Focused(false) => window::on_focus_lost(&app_handle),
+                    _ => {}
+                });
+            }
+
+            focus_bar::apply(app.handle());
+
+            // The capture window is a transient prompt; clicking away dismisses it
+            if let Some(capture) = app.get_webview_window("capture") {
+                let app_handle = app.handle().clone();
+                capture.on_window_event(move |event| {
+                    if let tauri::WindowEvent::Focused(false) = event {
+                        hide_capture(app_handle.clone());
+                    }
+                });
+            }
+
+            // Register global shortcuts from config
+            hotkeys::register_all(app.handle(), &initial_config.hotkeys);
+            #[cfg(target_os = "macos")]
+            if let Err(e) = modifier_tap::set_key(app.handle(), initial_config.double_tap) {
+                eprintln!("Warning: Double-tap activation unavailable: {}", e);
+            }
+
+            if let Err(e) = apply_autostart(app.handle()) {
+                eprintln!("Warning: {}", e);
+            }
+
+            scheduler::start(app.handle().clone());
+            timer::run(app.handle().clone());
+            pomodoro::run(app.handle().clone());
+            settings::watch(app.handle().clone());
 
             Ok(())
         })
@@ -0,0 +1,53 @@
+//! How dates are written for people to read, per `date_format` and `locale`: in
+//! reports, reminders, stats exports and next to the done log's own ISO dates
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Locale, NaiveDate};
+use std::sync::Mutex;
+
+/// The done log's own dates, and the default for everything else
+pub const ISO_DATE: &str = "%Y-%m-%d";
+
+struct DateDisplay {
+    /// Empty until configured, meaning `ISO_DATE`
+    date_format: String,
+    locale: Locale,
+}
+
+static DISPLAY: Mutex<DateDisplay> = Mutex::new(DateDisplay {
+    date_format: String::new(),
+    locale: Locale::POSIX,
+});
+
+/// A POSIX locale name such as "de_DE" or "pt_BR"; "de-DE" works too
+pub fn parse_locale(name: &str) -> Result<Locale, String> {
+    Locale::try_from(name.trim().replace('-', "_").as_str()).map_err(|_| format!("Unknown locale: {}", name))
+}
+
+/// Check a chrono strftime format before it's used
+pub fn validate_format(format: &str) -> Result<(), String> {
+    if format.trim().is_empty() || StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format: {}", format));
+    }
+    Ok(())
+}
+
+/// Use `date_format` and `locale` for every date shown from now on
+pub fn configure(date_format: &str, locale: Option<&str>) {
+    let mut display = DISPLAY.lock().unwrap_or_else(|e| e.into_inner());
+    display.date_format = date_format.to_string();
+    display.locale = locale.and_then(|name| parse_locale(name).ok()).unwrap_or(Locale::POSIX);
+}
+
+/// `day` in the configured date format
+pub fn date(day: NaiveDate) -> String {
+    let display = DISPLAY.lock().unwrap_or_else(|e| e.into_inner());
+    let format = if display.date_format.is_empty() { ISO_DATE } else { &display.date_format };
+    day.format_localized(format, display.locale).to_string()
+}
+
+/// `day` in `format`, with month and weekday names in the configured locale
+pub fn format(day: NaiveDate, format: &str) -> String {
+    let locale = DISPLAY.lock().unwrap_or_else(|e| e.into_inner()).locale;
+    day.format_localized(format, locale).to_string()
+}
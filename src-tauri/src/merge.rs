@@ -0,0 +1,249 @@
+//! Three-way merge of the task lists, for a save from the UI that lost a race with a
+//! change made elsewhere (tray, scheduler, capture window). The UI's changes since the
+//! lists it loaded are made again to the lists as they are now; tasks go by their id
+
+use std::collections::HashMap;
+
+use crate::search::SearchList;
+use crate::{Task, TaskState, MAX_CURRENT};
+
+const LISTS: [SearchList; 2] = [SearchList::Current, SearchList::Shelf];
+
+fn list(state: &TaskState, list: SearchList) -> &Vec<Task> {
+    match list {
+        SearchList::Current => &state.current,
+        _ => &state.shelf,
+    }
+}
+
+fn list_mut(state: &mut TaskState, list: SearchList) -> &mut Vec<Task> {
+    match list {
+        SearchList::Current => &mut state.current,
+        _ => &mut state.shelf,
+    }
+}
+
+/// Where each task with an id is: its list and position in it
+fn positions(state: &TaskState) -> HashMap<u64, (SearchList, usize)> {
+    let mut found = HashMap::new();
+    for l in LISTS {
+        for (index, task) in list(state, l).iter().enumerate() {
+            if let Some(id) = task.id {
+                found.insert(id, (l, index));
+            }
+        }
+    }
+    found
+}
+
+fn take(state: &mut TaskState, id: u64) -> Option<Task> {
+    let (l, index) = positions(state).remove(&id)?;
+    Some(list_mut(state, l).remove(index))
+}
+
+fn put(state: &mut TaskState, l: SearchList, index: usize, task: Task) {
+    let tasks = list_mut(state, l);
+    tasks.insert(index.min(tasks.len()), task);
+}
+
+/// A task added in the UI, at `index` in `l`; like `insert_task`, it goes on the shelf
+/// instead once `current` is full
+fn add(state: &mut TaskState, l: SearchList, index: usize, task: Task) {
+    if l == SearchList::Current && state.current.len() >= MAX_CURRENT {
+        state.shelf.push(task);
+    } else {
+        put(state, l, index, task);
+    }
+}
+
+/// The UI's edit of a task, keeping time the timer added to it meanwhile
+fn edited(ours: &Task, theirs: Option<&Task>) -> Task {
+    let time_spent = ours.time_spent.max(theirs.and_then(|t| t.time_spent));
+    Task {
+        time_spent,
+        ..ours.clone()
+    }
+}
+
+/// Ids in list `l` of `state` that are also in list `l` of `other`, in `state`'s order
+fn shared_order(state: &TaskState, other: &TaskState, l: SearchList) -> Vec<u64> {
+    let in_other: Vec<u64> = list(other, l).iter().filter_map(|t| t.id).collect();
+    list(state, l).iter().filter_map(|t| t.id).filter(|id| in_other.contains(id)).collect()
+}
+
+/// `theirs` with the changes `ours` made to `base`: tasks it added, edited, moved, reordered
+/// or removed, and its recurring templates if it changed them. Where both sides changed a
+/// task, `ours` wins, but for the timer's time; a task `theirs` removed (e.g. completed from
+/// the tray) stays removed. Added tasks overflow to the shelf once `current` is full.
+/// The result carries `theirs`' revision, so it can be saved
+pub fn merge(base: &TaskState, ours: &TaskState, theirs: &TaskState) -> TaskState {
+    let in_base = positions(base);
+    let in_ours = positions(ours);
+    let mut merged = theirs.clone();
+
+    // Deleted or completed in the UI
+    for id in in_base.keys().filter(|id| !in_ours.contains_key(id)) {
+        take(&mut merged, *id);
+    }
+
+    for l in LISTS {
+        for (index, task) in list(ours, l).iter().enumerate() {
+            let known = task.id.and_then(|id| Some((id, *in_base.get(&id)?)));
+            let Some((id, (base_list, base_index))) = known else {
+                // Added in the UI, unless it's there already: saved once before the UI
+                // had its id, say
+                let added_since = |t: &&Task| t.id.is_none_or(|id| !in_base.contains_key(&id));
+                let there = LISTS
+                    .iter()
+                    .flat_map(|l| list(&merged, *l).iter().filter(added_since))
+                    .any(|t| t.text == task.text);
+                if !there && !task.id.is_some_and(|id| positions(&merged).contains_key(&id)) {
+                    add(&mut merged, l, index, task.clone());
+                }
+                continue;
+            };
+            let Some((now_list, now_index)) = positions(&merged).get(&id).copied() else {
+                continue;
+            };
+            let changed = *task != list(base, base_list)[base_index];
+            if l != base_list {
+                let now = take(&mut merged, id);
+                let moved = match now {
+                    Some(now) if !changed => now,
+                    now => edited(task, now.as_ref()),
+                };
+                put(&mut merged, l, index, moved);
+            } else if changed {
+                let now = &mut list_mut(&mut merged, now_list)[now_index];
+                *now = edited(task, Some(now));
+            }
+        }
+
+        // Reordered in the UI: the tasks it knows about take its order, in the places they
+        // hold now, and tasks added elsewhere stay where they are
+        let order = shared_order(ours, base, l);
+        if order != shared_order(base, ours, l) {
+            let tasks = list_mut(&mut merged, l);
+            let slots: Vec<usize> = (0..tasks.len())
+                .filter(|i| tasks[*i].id.is_some_and(|id| order.contains(&id)))
+                .collect();
+            let mut sorted: Vec<Task> = slots.iter().map(|i| tasks[*i].clone()).collect();
+            sorted.sort_by_key(|t| t.id.and_then(|id| order.iter().position(|o| *o == id)));
+            for (slot, task) in slots.into_iter().zip(sorted) {
+                tasks[slot] = task;
+            }
+        }
+    }
+
+    if ours.recurring != base.recurring {
+        merged.recurring = ours.recurring.clone();
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u64, text: &str) -> Task {
+        Task {
+            id: Some(id),
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn lists(current: Vec<Task>, shelf: Vec<Task>) -> TaskState {
+        TaskState {
+            current,
+            shelf,
+            ..Default::default()
+        }
+    }
+
+    fn texts(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.text.as_str()).collect()
+    }
+
+    #[test]
+    fn adds_on_both_sides_are_kept() {
+        let base = lists(vec![task(1, "a")], vec![]);
+        let ours = lists(vec![task(1, "a"), task(2, "ours")], vec![]);
+        let theirs = lists(vec![task(1, "a"), task(3, "theirs")], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(texts(&merged.current), ["a", "ours", "theirs"]);
+    }
+
+    #[test]
+    fn the_same_add_on_both_sides_is_kept_once() {
+        let base = lists(vec![], vec![]);
+        let ours = lists(
+            vec![Task {
+                text: "same".to_string(),
+                ..Default::default()
+            }],
+            vec![],
+        );
+        let theirs = lists(vec![task(1, "same")], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(texts(&merged.current), ["same"]);
+    }
+
+    #[test]
+    fn adds_overflow_to_the_shelf_once_current_is_full() {
+        let base = lists(vec![], vec![]);
+        let ours = lists(vec![task(100, "ours")], vec![]);
+        let full: Vec<Task> = (0..MAX_CURRENT as u64).map(|id| task(id, "theirs")).collect();
+        let theirs = lists(full, vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(merged.current.len(), MAX_CURRENT);
+        assert_eq!(texts(&merged.shelf), ["ours"]);
+    }
+
+    #[test]
+    fn a_task_edited_in_the_ui_stays_deleted_elsewhere() {
+        let base = lists(vec![task(1, "a"), task(2, "b")], vec![]);
+        let ours = lists(vec![task(1, "a edited"), task(2, "b")], vec![]);
+        let theirs = lists(vec![task(2, "b")], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(texts(&merged.current), ["b"]);
+    }
+
+    #[test]
+    fn a_task_deleted_in_the_ui_stays_deleted_after_an_edit_elsewhere() {
+        let base = lists(vec![task(1, "a"), task(2, "b")], vec![]);
+        let ours = lists(vec![task(2, "b")], vec![]);
+        let theirs = lists(vec![task(1, "a edited"), task(2, "b")], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(texts(&merged.current), ["b"]);
+    }
+
+    #[test]
+    fn an_edit_in_the_ui_keeps_time_the_timer_added() {
+        let base = lists(vec![task(1, "a")], vec![]);
+        let ours = lists(vec![task(1, "a edited")], vec![]);
+        let timed = Task {
+            time_spent: Some(60),
+            ..task(1, "a")
+        };
+        let theirs = lists(vec![timed], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(texts(&merged.current), ["a edited"]);
+        assert_eq!(merged.current[0].time_spent, Some(60));
+    }
+
+    #[test]
+    fn a_move_in_the_ui_keeps_time_the_timer_added() {
+        let base = lists(vec![task(1, "a")], vec![]);
+        let ours = lists(vec![], vec![task(1, "a edited")]);
+        let timed = Task {
+            time_spent: Some(60),
+            ..task(1, "a")
+        };
+        let theirs = lists(vec![timed], vec![]);
+        let merged = merge(&base, &ours, &theirs);
+        assert!(merged.current.is_empty());
+        assert_eq!(texts(&merged.shelf), ["a edited"]);
+        assert_eq!(merged.shelf[0].time_spent, Some(60));
+    }
+}
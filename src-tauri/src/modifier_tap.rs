@@ -0,0 +1,191 @@
+//! Double-tapping a lone modifier key toggles the window (macOS only).
+//!
+//! Global shortcuts always need a non-modifier key, so modifier presses are read
+//! from a listen-only Quartz event tap instead. Creating the tap requires the
+//! Accessibility permission, which macOS prompts for the first time it's enabled.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use objc2::MainThreadMarker;
+use objc2_core_foundation::{
+    kCFRunLoopCommonModes, CFBoolean, CFDictionary, CFMachPort, CFRetained, CFRunLoop, CFString,
+};
+use objc2_core_graphics::{
+    CGEvent, CGEventField, CGEventFlags, CGEventMask, CGEventTapLocation, CGEventTapOptions,
+    CGEventTapPlacement, CGEventTapProxy, CGEventType,
+};
+use tauri::AppHandle;
+
+use crate::{hotkeys::ModifierKey, toggle_window};
+
+/// Maximum gap between the first release and the second press
+const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+/// Virtual keycode of the watched modifier; 0 while disabled
+static TARGET: AtomicU16 = AtomicU16::new(0);
+/// Set while global shortcuts are suspended
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+// The tap delivers events on the main run loop, so its state lives on the main thread
+thread_local! {
+    static TAP: RefCell<Option<CFRetained<CFMachPort>>> = const { RefCell::new(None) };
+    /// The watched key is down and nothing else has been pressed since
+    static CLEAN_PRESS: Cell<bool> = const { Cell::new(false) };
+    /// When the watched key was last tapped on its own
+    static LAST_TAP: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    static kAXTrustedCheckOptionPrompt: &'static CFString;
+    fn AXIsProcessTrustedWithOptions(options: &CFDictionary) -> u8;
+}
+
+fn keycode(key: ModifierKey) -> u16 {
+    match key {
+        ModifierKey::LeftCmd => 0x37,
+        ModifierKey::RightCmd => 0x36,
+        ModifierKey::LeftShift => 0x38,
+        ModifierKey::RightShift => 0x3C,
+        ModifierKey::LeftOption => 0x3A,
+        ModifierKey::RightOption => 0x3D,
+        ModifierKey::LeftControl => 0x3B,
+        ModifierKey::RightControl => 0x3E,
+    }
+}
+
+/// The event flag that is set while the modifier with `keycode` is held
+fn modifier_flag(keycode: u16) -> CGEventFlags {
+    match keycode {
+        0x36 | 0x37 => CGEventFlags::MaskCommand,
+        0x38 | 0x3C => CGEventFlags::MaskShift,
+        0x3A | 0x3D => CGEventFlags::MaskAlternate,
+        _ => CGEventFlags::MaskControl,
+    }
+}
+
+/// Whether the app may read global key events, asking the user if it may not
+fn accessibility_trusted() -> bool {
+    let key = unsafe { kAXTrustedCheckOptionPrompt };
+    let options = CFDictionary::<CFString, CFBoolean>::from_slices(&[key], &[CFBoolean::new(true)]);
+    unsafe { AXIsProcessTrustedWithOptions(options.as_opaque()) != 0 }
+}
+
+fn reset() {
+    CLEAN_PRESS.set(false);
+    LAST_TAP.set(None);
+}
+
+fn on_event(target: u16, event_type: CGEventType, event: &CGEvent) {
+    if event_type != CGEventType::FlagsChanged {
+        // Any other key breaks up the sequence
+        reset();
+        return;
+    }
+
+    let code = CGEvent::integer_value_field(Some(event), CGEventField::KeyboardEventKeycode) as u16;
+    if code != target {
+        reset();
+        return;
+    }
+
+    if CGEvent::flags(Some(event)).contains(modifier_flag(target)) {
+        CLEAN_PRESS.set(true);
+        return;
+    }
+    if !CLEAN_PRESS.replace(false) {
+        return;
+    }
+
+    let now = Instant::now();
+    match LAST_TAP.get() {
+        Some(last) if now.duration_since(last) <= DOUBLE_TAP_WINDOW => {
+            LAST_TAP.set(None);
+            if let Some(app) = APP.get() {
+                toggle_window(app);
+            }
+        }
+        _ => LAST_TAP.set(Some(now)),
+    }
+}
+
+unsafe extern "C-unwind" fn handle_event(
+    _proxy: CGEventTapProxy,
+    event_type: CGEventType,
+    event: NonNull<CGEvent>,
+    _user_info: *mut c_void,
+) -> *mut CGEvent {
+    // macOS switches off taps that are slow or interrupted; turn it back on
+    if event_type == CGEventType::TapDisabledByTimeout || event_type == CGEventType::TapDisabledByUserInput {
+        TAP.with_borrow(|tap| {
+            if let Some(tap) = tap {
+                CGEvent::tap_enable(tap, true);
+            }
+        });
+        return event.as_ptr();
+    }
+
+    let target = TARGET.load(Ordering::Relaxed);
+    if target != 0 && !PAUSED.load(Ordering::Relaxed) {
+        on_event(target, event_type, unsafe { event.as_ref() });
+    }
+    event.as_ptr()
+}
+
+fn install_tap() -> Result<(), String> {
+    if TAP.with_borrow(Option::is_some) {
+        return Ok(());
+    }
+    if !accessibility_trusted() {
+        return Err("Task Log needs Accessibility access (System Settings → Privacy & Security) for double-tap activation".to_string());
+    }
+
+    let mask: CGEventMask = (1 << CGEventType::FlagsChanged.0) | (1 << CGEventType::KeyDown.0);
+    let tap = unsafe {
+        CGEvent::tap_create(
+            CGEventTapLocation::SessionEventTap,
+            CGEventTapPlacement::HeadInsertEventTap,
+            CGEventTapOptions::ListenOnly,
+            mask,
+            Some(handle_event),
+            std::ptr::null_mut(),
+        )
+    }
+    .ok_or("Failed to create keyboard event tap")?;
+
+    let source = CFMachPort::new_run_loop_source(None, Some(&tap), 0).ok_or("Failed to create run loop source")?;
+    let run_loop = CFRunLoop::main().ok_or("No main run loop")?;
+    run_loop.add_source(Some(&source), unsafe { kCFRunLoopCommonModes });
+    CGEvent::tap_enable(&tap, true);
+
+    TAP.set(Some(tap));
+    Ok(())
+}
+
+/// Watch `key` for double taps, or stop watching with `None`
+pub fn set_key(app: &AppHandle, key: Option<ModifierKey>) -> Result<(), String> {
+    reset();
+    let Some(key) = key else {
+        TARGET.store(0, Ordering::Relaxed);
+        return Ok(());
+    };
+
+    if MainThreadMarker::new().is_none() {
+        return Err("Double-tap activation must be set up on the main thread".to_string());
+    }
+    let _ = APP.set(app.clone());
+    install_tap()?;
+    TARGET.store(keycode(key), Ordering::Relaxed);
+    Ok(())
+}
+
+/// Ignore double taps while global shortcuts are suspended
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::Relaxed);
+}
@@ -0,0 +1,116 @@
+//! Native window behavior on macOS: the non-activating panel mode for the main
+//! window, and showing windows on the active Space.
+//!
+//! In panel mode the window's class is swapped for an NSPanel subclass with the
+//! non-activating style, so showing it, typing into it and closing it all happen
+//! without taking the app in front out of focus. Outside panel mode the window
+//! activates the app as usual, and the app that was in front gets focus back
+//! when the window is dismissed.
+
+use std::cell::{Cell, RefCell};
+
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject, NSObjectProtocol};
+use objc2::{define_class, ClassType, MainThreadMarker, MainThreadOnly};
+use objc2_app_kit::{
+    NSApplicationActivationOptions, NSPanel, NSResponder, NSRunningApplication, NSWindow, NSWindowCollectionBehavior,
+    NSWindowStyleMask, NSWorkspace,
+};
+use objc2_foundation::NSObject;
+use tauri::WebviewWindow;
+
+define_class!(
+    // Borderless panels refuse key status by default, which would block typing
+    #[unsafe(super(NSPanel, NSWindow, NSResponder, NSObject))]
+    #[thread_kind = MainThreadOnly]
+    #[name = "TaskLogPanel"]
+    struct TaskLogPanel;
+
+    unsafe impl NSObjectProtocol for TaskLogPanel {}
+
+    impl TaskLogPanel {
+        #[unsafe(method(canBecomeKeyWindow))]
+        fn can_become_key_window(&self) -> bool {
+            true
+        }
+    }
+);
+
+// AppKit objects are main-thread only, so this state lives there too
+thread_local! {
+    /// The window's class before it became a panel; set while panel mode is on
+    static ORIGINAL_CLASS: Cell<Option<&'static AnyClass>> = const { Cell::new(None) };
+    /// The app that was in front when the window was last shown
+    static PREVIOUS_APP: RefCell<Option<Retained<NSRunningApplication>>> = const { RefCell::new(None) };
+}
+
+fn ns_window(window: &WebviewWindow) -> Option<Retained<NSWindow>> {
+    let ptr = window.ns_window().ok()?;
+    unsafe { Retained::retain(ptr.cast::<NSWindow>()) }
+}
+
+/// Turn panel mode on or off for `window`
+pub fn set_enabled(window: &WebviewWindow, enabled: bool) -> Result<(), String> {
+    if MainThreadMarker::new().is_none() {
+        return Err("Panel mode must be changed on the main thread".to_string());
+    }
+    let ns_window = ns_window(window).ok_or("Failed to get the native window")?;
+    let object: &AnyObject = &ns_window;
+
+    if enabled {
+        if ORIGINAL_CLASS.get().is_none() {
+            let original = unsafe { AnyObject::set_class(object, TaskLogPanel::class()) };
+            ORIGINAL_CLASS.set(Some(original));
+        }
+        ns_window.setStyleMask(ns_window.styleMask() | NSWindowStyleMask::NonactivatingPanel);
+        // Panels hide when the app deactivates, and this one never activates it
+        ns_window.setHidesOnDeactivate(false);
+    } else if let Some(original) = ORIGINAL_CLASS.take() {
+        ns_window.setStyleMask(ns_window.styleMask() & !NSWindowStyleMask::NonactivatingPanel);
+        unsafe { AnyObject::set_class(object, original) };
+    }
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    ORIGINAL_CLASS.get().is_some()
+}
+
+/// Bring the panel to the front without making it key; a click gives it focus
+pub fn show(window: &WebviewWindow) {
+    if let Some(ns_window) = ns_window(window) {
+        ns_window.orderFrontRegardless();
+    }
+}
+
+/// Note which app is in front before the window takes focus
+pub fn remember_frontmost() {
+    if MainThreadMarker::new().is_none() {
+        return;
+    }
+    let current = NSRunningApplication::currentApplication();
+    let front = NSWorkspace::sharedWorkspace().frontmostApplication();
+    PREVIOUS_APP.set(front.filter(|app| !app.isEqual(Some(&current))));
+}
+
+/// Give focus back to the app that was in front when the window was shown
+pub fn restore_frontmost() {
+    if MainThreadMarker::new().is_none() {
+        return;
+    }
+    if let Some(app) = PREVIOUS_APP.take() {
+        app.activateWithOptions(NSApplicationActivationOptions::empty());
+    }
+}
+
+/// Show `window` on whichever Space is active, including over fullscreen apps,
+/// rather than switching back to the Space it was first opened on
+pub fn join_all_spaces(window: &WebviewWindow) {
+    if let Some(ns_window) = ns_window(window) {
+        ns_window.setCollectionBehavior(
+            ns_window.collectionBehavior()
+                | NSWindowCollectionBehavior::CanJoinAllSpaces
+                | NSWindowCollectionBehavior::FullScreenAuxiliary,
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! The pomodoro clock: work and break phases of the configured lengths follow one
+//! another in the backend, each announced with a notification and its sound, so it
+//! keeps going with the window closed. The tray shows the minutes left in its title
+//! and tooltip
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{defaults, notify_with_sound, tray, AppState};
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Phase lengths and what the notification at the start of each phase plays
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Work phases before each long break
+    pub long_break_every: u32,
+    pub sounds: PomodoroSounds,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+            sounds: PomodoroSounds::default(),
+        }
+    }
+}
+
+/// Notification sound names by the phase starting, e.g. "Glass" on macOS; "" for the
+/// platform's usual notification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct PomodoroSounds {
+    pub work: String,
+    pub short_break: String,
+    pub long_break: String,
+}
+
+impl Default for PomodoroSounds {
+    fn default() -> Self {
+        defaults::pomodoro_sounds()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "Focus",
+            Phase::ShortBreak => "Break",
+            Phase::LongBreak => "Long Break",
+        }
+    }
+
+    fn minutes(self, config: &PomodoroConfig) -> u32 {
+        let minutes = match self {
+            Phase::Work => config.work_minutes,
+            Phase::ShortBreak => config.short_break_minutes,
+            Phase::LongBreak => config.long_break_minutes,
+        };
+        minutes.max(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    /// Counting down to this local time
+    Running(NaiveDateTime),
+    /// Stopped with this much of the phase left
+    Paused(TimeDelta),
+}
+
+/// The pomodoro under way
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    phase: Phase,
+    /// Work phases finished since it started
+    sessions: u32,
+    clock: Clock,
+}
+
+impl Session {
+    fn starting(phase: Phase, sessions: u32, config: &PomodoroConfig, now: NaiveDateTime) -> Self {
+        let length = TimeDelta::minutes(phase.minutes(config) as i64);
+        Self {
+            phase,
+            sessions,
+            clock: Clock::Running(now + length),
+        }
+    }
+
+    /// The phase after this one, starting at `now`: a break after work, a long one after
+    /// every `long_break_every` work phases, and work after a break
+    fn next(&self, config: &PomodoroConfig, now: NaiveDateTime) -> Self {
+        match self.phase {
+            Phase::Work => {
+                let sessions = self.sessions + 1;
+                let long = config.long_break_every > 0 && sessions.is_multiple_of(config.long_break_every);
+                let phase = if long { Phase::LongBreak } else { Phase::ShortBreak };
+                Self::starting(phase, sessions, config, now)
+            }
+            Phase::ShortBreak | Phase::LongBreak => Self::starting(Phase::Work, self.sessions, config, now),
+        }
+    }
+
+    fn left(&self, now: NaiveDateTime) -> TimeDelta {
+        match self.clock {
+            Clock::Running(ends) => (ends - now).max(TimeDelta::zero()),
+            Clock::Paused(left) => left,
+        }
+    }
+
+    fn status(&self, now: NaiveDateTime) -> PomodoroStatus {
+        PomodoroStatus {
+            phase: self.phase,
+            remaining: self.left(now).num_seconds() as u64,
+            paused: matches!(self.clock, Clock::Paused(_)),
+            sessions: self.sessions,
+        }
+    }
+}
+
+/// The pomodoro as the UI shows it
+#[derive(Debug, Serialize, Clone)]
+pub struct PomodoroStatus {
+    pub phase: Phase,
+    /// Seconds left in the phase
+    pub remaining: u64,
+    pub paused: bool,
+    /// Work phases finished since it started
+    pub sessions: u32,
+}
+
+fn config(app: &AppHandle) -> PomodoroConfig {
+    app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).pomodoro.clone()
+}
+
+/// The pomodoro under way, if any
+pub fn status(app: &AppHandle) -> Option<PomodoroStatus> {
+    let session = *app.state::<AppState>().pomodoro.lock().unwrap_or_else(|e| e.into_inner());
+    session.map(|s| s.status(Local::now().naive_local()))
+}
+
+/// "Focus 24m", "Break 5m (paused)"; none when no pomodoro is under way
+pub fn countdown(app: &AppHandle) -> Option<String> {
+    let status = status(app)?;
+    let minutes = status.remaining.div_ceil(60);
+    let paused = if status.paused { " (paused)" } else { "" };
+    Some(format!("{} {}m{}", status.phase.label(), minutes, paused))
+}
+
+/// Tell the windows and the tray the pomodoro started, changed phase, paused, resumed or stopped
+fn changed(app: &AppHandle) {
+    let _ = app.emit("pomodoro-changed", status(app));
+    tray::refresh(app);
+}
+
+fn announce(app: &AppHandle, session: &Session, config: &PomodoroConfig) {
+    let minutes = session.phase.minutes(config);
+    let (title, body, sound) = match session.phase {
+        Phase::Work => ("Time to focus", format!("{} minutes of work", minutes), &config.sounds.work),
+        Phase::ShortBreak => ("Take a break", format!("{} minutes", minutes), &config.sounds.short_break),
+        Phase::LongBreak => (
+            "Take a long break",
+            format!("{} minutes, after {} pomodoros", minutes, session.sessions),
+            &config.sounds.long_break,
+        ),
+    };
+    notify_with_sound(app, title, &body, sound);
+}
+
+/// Move on to the next phase when the running one is up, and keep the tray's countdown
+/// current. After the computer sleeps, the phase that ran out moves on to the next one
+/// from the time it wakes rather than catching up
+pub fn run(app: AppHandle) {
+    thread::spawn(move || {
+        let mut shown = None;
+        loop {
+            thread::sleep(TICK);
+            let config = config(&app);
+            let now = Local::now().naive_local();
+            let started = {
+                let state = app.state::<AppState>();
+                let mut pomodoro = state.pomodoro.lock().unwrap_or_else(|e| e.into_inner());
+                match *pomodoro {
+                    Some(session) if matches!(session.clock, Clock::Running(ends) if ends <= now) => {
+                        let next = session.next(&config, now);
+                        *pomodoro = Some(next);
+                        Some(next)
+                    }
+                    _ => None,
+                }
+            };
+            if let Some(session) = started {
+                announce(&app, &session, &config);
+                changed(&app);
+            }
+            let countdown = countdown(&app);
+            if countdown != shown {
+                tray::refresh_title(&app);
+                shown = countdown;
+            }
+        }
+    });
+}
+
+/// Start a pomodoro with a work phase, or resume the paused one
+#[tauri::command]
+pub fn start_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    let config = config(&app);
+    let now = Local::now().naive_local();
+    let started = {
+        let state = app.state::<AppState>();
+        let mut pomodoro = state.pomodoro.lock().unwrap_or_else(|e| e.into_inner());
+        match pomodoro.as_mut() {
+            None => {
+                let session = Session::starting(Phase::Work, 0, &config, now);
+                *pomodoro = Some(session);
+                Some(session)
+            }
+            Some(session) => {
+                if let Clock::Paused(left) = session.clock {
+                    session.clock = Clock::Running(now + left);
+                }
+                None
+            }
+        }
+    };
+    // Resuming carries on the phase already announced
+    if let Some(session) = started {
+        announce(&app, &session, &config);
+    }
+    changed(&app);
+    status(&app).ok_or_else(|| "No pomodoro is running".to_string())
+}
+
+/// Hold the clock where it is until `start_pomodoro`
+#[tauri::command]
+pub fn pause_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    {
+        let state = app.state::<AppState>();
+        let mut pomodoro = state.pomodoro.lock().unwrap_or_else(|e| e.into_inner());
+        let session = pomodoro.as_mut().ok_or_else(|| "No pomodoro is running".to_string())?;
+        session.clock = Clock::Paused(session.left(Local::now().naive_local()));
+    }
+    changed(&app);
+    status(&app).ok_or_else(|| "No pomodoro is running".to_string())
+}
+
+/// End the phase now and start the next one
+#[tauri::command]
+pub fn skip_pomodoro(app: AppHandle) -> Result<PomodoroStatus, String> {
+    let config = config(&app);
+    let session = {
+        let state = app.state::<AppState>();
+        let mut pomodoro = state.pomodoro.lock().unwrap_or_else(|e| e.into_inner());
+        let session = pomodoro.as_mut().ok_or_else(|| "No pomodoro is running".to_string())?;
+        *session = session.next(&config, Local::now().naive_local());
+        *session
+    };
+    announce(&app, &session, &config);
+    changed(&app);
+    status(&app).ok_or_else(|| "No pomodoro is running".to_string())
+}
+
+#[tauri::command]
+pub fn stop_pomodoro(app: AppHandle) {
+    *app.state::<AppState>().pomodoro.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    changed(&app);
+}
+
+#[tauri::command]
+pub fn get_pomodoro(app: AppHandle) -> Option<PomodoroStatus> {
+    status(&app)
+}
@@ -0,0 +1,204 @@
+//! Named profiles, e.g. "work" and "personal". Each keeps its tasks, done log and
+//! config.json (and so its hotkeys, theme, done log location and every other setting)
+//! in a data directory of its own. The list of profiles is ~/.tasks/profiles.json;
+//! the default profile is ~/.tasks itself
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    done, get_home_tasks_dir, load_config, load_tasks, notify_tasks_changed, save_tasks, search_index, settings, timer,
+    undo, AppState, GoalProgress,
+};
+
+/// The profile that lives in ~/.tasks; it can't be renamed or deleted
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named data directory
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Folder for the profile's state.json, config.json and done log, `~/` allowed
+    pub data_dir: String,
+}
+
+/// Contents of profiles.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Profiles {
+    /// Name of the profile in use
+    pub active: String,
+    /// Every profile but the default one
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl Profiles {
+    /// Data directory of the profile called `name`
+    fn data_dir(&self, name: &str) -> Result<PathBuf, String> {
+        if name == DEFAULT_PROFILE {
+            return get_home_tasks_dir();
+        }
+        let profile = self
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| format!("No profile named {}", name))?;
+        done::expand_path(profile.data_dir.trim())
+    }
+}
+
+/// Data directory of the active profile; `None` for the default profile
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Where the active profile keeps its files, if it isn't the default profile
+pub fn data_dir() -> Option<PathBuf> {
+    DATA_DIR.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn set_data_dir(name: &str, dir: PathBuf) {
+    *DATA_DIR.lock().unwrap_or_else(|e| e.into_inner()) = (name != DEFAULT_PROFILE).then_some(dir);
+}
+
+fn profiles_file() -> Result<PathBuf, String> {
+    Ok(get_home_tasks_dir()?.join("profiles.json"))
+}
+
+fn load() -> Profiles {
+    let Ok(path) = profiles_file() else {
+        return Profiles::default();
+    };
+    if !path.exists() {
+        return Profiles::default();
+    }
+    let parsed = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+    match parsed {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            eprintln!("Warning: Failed to read profiles.json: {}. Using the default profile.", e);
+            Profiles::default()
+        }
+    }
+}
+
+fn save(profiles: &Profiles) -> Result<(), String> {
+    let dir = get_home_tasks_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(profiles).map_err(|e| e.to_string())?;
+    fs::write(dir.join("profiles.json"), content).map_err(|e| e.to_string())
+}
+
+/// Point the data directory at the profile that was active when the app last ran;
+/// called before anything else is loaded
+pub fn init() {
+    let profiles = load();
+    match profiles.data_dir(&profiles.active) {
+        Ok(dir) => set_data_dir(&profiles.active, dir),
+        Err(e) => eprintln!("Warning: {}. Using the default profile.", e),
+    }
+}
+
+/// Every profile and which one is in use
+#[tauri::command]
+pub fn get_profiles() -> Profiles {
+    load()
+}
+
+/// Add a profile, or move the one with this name to another data directory. Its
+/// settings and tasks are whatever that directory holds; a new one starts out empty
+/// with default settings
+#[tauri::command]
+pub fn save_profile(name: String, data_dir: String) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name is empty".to_string());
+    }
+    if name == DEFAULT_PROFILE {
+        return Err("The default profile always uses ~/.tasks".to_string());
+    }
+    let mut profiles = load();
+    if profiles.active == name {
+        return Err(format!("{} is in use; switch to another profile first", name));
+    }
+    let dir = done::expand_path(data_dir.trim())?;
+    if !dir.is_absolute() {
+        return Err(format!("Data directory must be absolute or start with ~/: {}", dir.display()));
+    }
+    match profiles.profiles.iter_mut().find(|p| p.name == name) {
+        Some(profile) => profile.data_dir = data_dir,
+        None => profiles.profiles.push(Profile { name, data_dir }),
+    }
+    save(&profiles)
+}
+
+/// Forget a profile; its data directory is left as it is
+#[tauri::command]
+pub fn delete_profile(name: String) -> Result<(), String> {
+    let mut profiles = load();
+    if profiles.active == name {
+        return Err(format!("{} is in use; switch to another profile first", name));
+    }
+    let before = profiles.profiles.len();
+    profiles.profiles.retain(|p| p.name != name);
+    if profiles.profiles.len() == before {
+        return Err(format!("No profile named {}", name));
+    }
+    save(&profiles)
+}
+
+/// Leave the current profile for `name`: its tasks and settings replace the ones in
+/// use, and shortcuts, theme, tray, done log and everything else that depends on a
+/// setting follow. The UI gets "tasks-changed", "config-changed" and "profile-changed"
+#[tauri::command]
+pub fn switch_profile(name: String, app: AppHandle) -> Result<(), String> {
+    let mut profiles = load();
+    if profiles.active == name {
+        return Ok(());
+    }
+    let dir = profiles.data_dir(&name)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    // The timer's task is in this profile's lists
+    timer::stop(&app)?;
+    let state = app.state::<AppState>();
+    // Every change is saved as it's made; this covers one still in flight
+    save_tasks(&mut state.tasks.lock().unwrap_or_else(|e| e.into_inner()))?;
+
+    set_data_dir(&name, dir);
+    let config = load_config();
+    let tasks = load_tasks();
+    // Both depend on the data directory even when the profiles' settings match
+    done::configure(&config.done_log);
+    search_index::reset();
+
+    undo::reload(&app, &tasks);
+    *state.tasks.lock().unwrap_or_else(|e| e.into_inner()) = tasks;
+    let old = std::mem::replace(&mut *state.config.lock().unwrap_or_else(|e| e.into_inner()), config.clone());
+    state.woken.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    *state.goal_progress.lock().unwrap_or_else(|e| e.into_inner()) = GoalProgress::default();
+    timer::reload(&app);
+
+    profiles.active = name.clone();
+    if let Err(e) = save(&profiles) {
+        eprintln!("Warning: Failed to save profiles.json: {}", e);
+    }
+
+    settings::watch(app.clone());
+    settings::apply_changes(&app, &old, &config);
+    notify_tasks_changed(&app);
+    let _ = app.emit("profile-changed", &name);
+    Ok(())
+}
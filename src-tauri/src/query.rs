@@ -0,0 +1,289 @@
+//! Filter expressions over tasks, e.g. `tag:work due:<friday priority:high -shelved`
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::search::SearchList;
+use crate::{done, Priority, Task, TaskState};
+
+/// How a `due:` or `priority:` term compares against its value
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compare {
+    Before,
+    AtMost,
+    Equal,
+    AtLeast,
+    After,
+}
+
+impl Compare {
+    /// Split a leading `<`, `<=`, `>` or `>=` off `value`
+    fn split(value: &str) -> (Self, &str) {
+        for (prefix, compare) in [
+            ("<=", Compare::AtMost),
+            (">=", Compare::AtLeast),
+            ("<", Compare::Before),
+            (">", Compare::After),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (compare, rest);
+            }
+        }
+        (Compare::Equal, value)
+    }
+
+    fn holds<T: Ord>(self, left: T, right: T) -> bool {
+        match self {
+            Compare::Before => left < right,
+            Compare::AtMost => left <= right,
+            Compare::Equal => left == right,
+            Compare::AtLeast => left >= right,
+            Compare::After => left > right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// `tag:work` or `#work`
+    Tag(String),
+    /// `project:acme`
+    Project(String),
+    /// `due:<friday`, `due:2026-03-02`, `due:+3d`; compares the due date only
+    Due(Compare, NaiveDate),
+    /// `due:any` or `due:none`
+    HasDue(bool),
+    /// `due:overdue` or `overdue`: due before now
+    Overdue,
+    /// `priority:high`, `priority:>=medium`
+    Priority(Compare, Priority),
+    /// `priority:any` or `priority:none`
+    HasPriority(bool),
+    /// `shelved`: on the shelf rather than in `current`
+    Shelved,
+    /// `snoozed`: shelved until a set time
+    Snoozed,
+    /// `notes`: has at least one note
+    HasNotes,
+    /// Any other word, or a `"quoted phrase"`: in the text, ignoring case
+    Text(String),
+}
+
+/// One filter, possibly negated with a leading `-`
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+/// A parsed filter expression. Terms are separated by spaces and all have to match
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+/// A task matched by a query, and where it is
+#[derive(Debug, Serialize, Clone)]
+pub struct QueryMatch {
+    pub list: SearchList,
+    /// Position in `current` or `shelf`
+    pub index: usize,
+    pub task: Task,
+}
+
+/// A named query kept in config, shown by the UI as a list of its own
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedFilter {
+    pub name: String,
+    pub query: String,
+}
+
+/// A saved filter evaluated against the current tasks
+#[derive(Debug, Serialize, Clone)]
+pub struct SmartList {
+    pub name: String,
+    pub query: String,
+    pub matches: Vec<QueryMatch>,
+    /// Set, with no matches, when the query no longer parses (e.g. edited by hand)
+    pub error: Option<String>,
+}
+
+/// Split `expr` on spaces outside of double quotes
+fn tokens(expr: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in expr.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unclosed quote in query".to_string());
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A date as written in a query, relative to `today`: `today`, `tomorrow`,
+/// `yesterday`, a weekday (the next one, or today), `+3d`/`+2w`, or `YYYY-MM-DD`
+fn parse_date(value: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let value = value.to_lowercase();
+    let date = match value.as_str() {
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        "yesterday" => today.pred_opt(),
+        _ => None,
+    };
+    if let Some(date) = date {
+        return Ok(date);
+    }
+    if let Ok(weekday) = value.parse::<Weekday>() {
+        let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        return Ok(today + Days::new(ahead as u64));
+    }
+    if let Some(offset) = value.strip_prefix('+') {
+        let days = if let Some(count) = offset.strip_suffix('d') {
+            count.parse::<u64>().ok()
+        } else if let Some(count) = offset.strip_suffix('w') {
+            count.parse::<u64>().ok().and_then(|n| n.checked_mul(7))
+        } else {
+            None
+        };
+        return days
+            .and_then(|n| today.checked_add_days(Days::new(n)))
+            .ok_or_else(|| format!("Unknown date in query: {} (+3d or +2w)", value));
+    }
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| format!("Unknown date in query: {}", value))
+}
+
+fn parse_priority(value: &str) -> Result<Priority, String> {
+    match value.to_lowercase().as_str() {
+        "low" => Ok(Priority::Low),
+        "medium" | "med" => Ok(Priority::Medium),
+        "high" => Ok(Priority::High),
+        _ => Err(format!("Unknown priority in query: {} (low, medium or high)", value)),
+    }
+}
+
+fn parse_filter(token: &str, today: NaiveDate) -> Result<Filter, String> {
+    if let Some(tag) = token.strip_prefix('#').filter(|t| !t.is_empty()) {
+        return Ok(Filter::Tag(tag.to_lowercase()));
+    }
+    match token.to_lowercase().as_str() {
+        "shelved" => return Ok(Filter::Shelved),
+        "snoozed" => return Ok(Filter::Snoozed),
+        "overdue" => return Ok(Filter::Overdue),
+        "notes" => return Ok(Filter::HasNotes),
+        _ => {}
+    }
+    let Some((key, value)) = token.split_once(':').filter(|(_, value)| !value.is_empty()) else {
+        return Ok(Filter::Text(token.to_lowercase()));
+    };
+    match key.to_lowercase().as_str() {
+        "tag" => Ok(Filter::Tag(value.trim_start_matches('#').to_lowercase())),
+        "project" => Ok(Filter::Project(value.to_lowercase())),
+        "due" => match value.to_lowercase().as_str() {
+            "any" => Ok(Filter::HasDue(true)),
+            "none" => Ok(Filter::HasDue(false)),
+            "overdue" => Ok(Filter::Overdue),
+            _ => {
+                let (compare, value) = Compare::split(value);
+                Ok(Filter::Due(compare, parse_date(value, today)?))
+            }
+        },
+        "priority" => match value.to_lowercase().as_str() {
+            "any" => Ok(Filter::HasPriority(true)),
+            "none" => Ok(Filter::HasPriority(false)),
+            _ => {
+                let (compare, value) = Compare::split(value);
+                Ok(Filter::Priority(compare, parse_priority(value)?))
+            }
+        },
+        _ => Ok(Filter::Text(token.to_lowercase())),
+    }
+}
+
+impl Query {
+    /// Parse `expr`, resolving relative dates against `today`
+    pub fn parse(expr: &str, today: NaiveDate) -> Result<Self, String> {
+        let terms = tokens(expr)?
+            .iter()
+            .map(|token| {
+                let (negated, token) = match token.strip_prefix('-') {
+                    Some(rest) if !rest.is_empty() => (true, rest),
+                    _ => (false, token.as_str()),
+                };
+                parse_filter(token, today).map(|filter| Term { negated, filter })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+
+    /// Whether `task`, shelved or not, passes every term at `now`
+    pub fn matches(&self, task: &Task, shelved: bool, now: NaiveDateTime) -> bool {
+        self.terms.iter().all(|term| {
+            let hit = match &term.filter {
+                Filter::Tag(tag) => done::tags(&task.text).contains(tag),
+                Filter::Project(project) => task.project.as_ref().is_some_and(|p| p.to_lowercase() == *project),
+                Filter::Due(compare, date) => task.due.is_some_and(|due| compare.holds(due.date(), *date)),
+                Filter::HasDue(set) => task.due.is_some() == *set,
+                Filter::Overdue => task.due.is_some_and(|due| due < now),
+                Filter::Priority(compare, priority) => task.priority.is_some_and(|p| compare.holds(p, *priority)),
+                Filter::HasPriority(set) => task.priority.is_some() == *set,
+                Filter::Shelved => shelved,
+                Filter::Snoozed => shelved && task.snoozed_until.is_some(),
+                Filter::HasNotes => !task.notes.is_empty(),
+                Filter::Text(text) => task.text.to_lowercase().contains(text.as_str()),
+            };
+            hit != term.negated
+        })
+    }
+
+    /// Tasks in `current` and on the shelf that match, in that order
+    pub fn run(&self, state: &TaskState, now: NaiveDateTime) -> Vec<QueryMatch> {
+        let lists = [(SearchList::Current, &state.current), (SearchList::Shelf, &state.shelf)];
+        lists
+            .into_iter()
+            .flat_map(|(list, tasks)| {
+                let shelved = list == SearchList::Shelf;
+                tasks
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, task)| self.matches(task, shelved, now))
+                    .map(move |(index, task)| QueryMatch {
+                        list,
+                        index,
+                        task: task.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Evaluate every saved filter, in order
+pub fn smart_lists(filters: &[SavedFilter], state: &TaskState, now: NaiveDateTime) -> Vec<SmartList> {
+    filters
+        .iter()
+        .map(|filter| {
+            let (matches, error) = match Query::parse(&filter.query, now.date()) {
+                Ok(query) => (query.run(state, now), None),
+                Err(e) => (Vec::new(), Some(e)),
+            };
+            SmartList {
+                name: filter.name.clone(),
+                query: filter.query.clone(),
+                matches,
+                error,
+            }
+        })
+        .collect()
+}
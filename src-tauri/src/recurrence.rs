@@ -0,0 +1,75 @@
+//! Recurring tasks: templates in `TaskState::recurring` that the scheduler copies
+//! into `current` each time their rule comes around
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::Task;
+
+/// How often a recurring task comes back, e.g. `"weekdays"`, `{"weekly": ["Mon", "Thu"]}`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    /// Monday to Friday
+    Weekdays,
+    Weekly(Vec<Weekday>),
+    /// On this day of the month, or the last day in shorter months
+    Monthly(u32),
+    /// Every N days counting from the last occurrence
+    EveryDays(u32),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecurringTask {
+    /// Copied into `current` on every occurrence
+    pub task: Task,
+    pub rule: Recurrence,
+    /// Time of day the task appears
+    #[serde(default)]
+    pub at: NaiveTime,
+    /// When it next appears; filled in by the scheduler
+    #[serde(default)]
+    pub next: Option<NaiveDateTime>,
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).unwrap_or(date);
+    first
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(31)
+}
+
+impl Recurrence {
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            Recurrence::Daily | Recurrence::EveryDays(_) => true,
+            Recurrence::Weekdays => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+            Recurrence::Weekly(days) => days.contains(&date.weekday()),
+            Recurrence::Monthly(day) => date.day() == (*day).clamp(1, last_day_of_month(date)),
+        }
+    }
+}
+
+impl RecurringTask {
+    /// The first occurrence strictly after `after`
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        if let Recurrence::EveryDays(days) = self.rule {
+            let from = self.next.filter(|next| *next <= after).unwrap_or(after);
+            let mut at = from.date().and_time(self.at);
+            while at <= after {
+                at = at.checked_add_days(Days::new(days.max(1) as u64))?;
+            }
+            return Some(at);
+        }
+
+        // Any rule matches at least once in a year and a month
+        (0..400)
+            .filter_map(|offset| after.date().checked_add_days(Days::new(offset)))
+            .filter(|day| self.rule.matches(*day))
+            .map(|day| day.and_time(self.at))
+            .find(|at| *at > after)
+    }
+}
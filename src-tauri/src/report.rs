@@ -0,0 +1,473 @@
+//! Summaries written from the done history, such as the weekly rollup
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, Weekday};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::done::{self, DateRange, DoneFormat, DoneMatch};
+use crate::query::QueryMatch;
+use crate::scheduler::WorkWeek;
+use crate::search::SearchList;
+use crate::stats::{self, Streak};
+use crate::{locale, search_index, Task, TaskState};
+
+/// Group name for completions without a `#tag`
+pub const UNTAGGED: &str = "untagged";
+
+/// Days on the shelf, unsnoozed, after which the weekly review calls a task stale
+const STALE_DAYS: i64 = 14;
+
+/// One week of completions, counted per day and per tag
+#[derive(Debug, Serialize, Clone)]
+pub struct WeeklyRollup {
+    /// The day the week starts on, per `week_start` in the config
+    pub week_start: NaiveDate,
+    pub total: usize,
+    /// The seven days from `week_start`
+    pub per_day: Vec<(NaiveDate, usize)>,
+    /// Most used first; a task with several tags counts toward each
+    pub per_tag: Vec<(String, usize)>,
+    /// Name of the markdown file the rollup was written to, next to the done log
+    pub file: String,
+}
+
+/// The first day of the week containing `date`, for weeks starting on `first_day`
+pub fn week_start(date: NaiveDate, first_day: Weekday) -> NaiveDate {
+    date - Days::new(date.weekday().days_since(first_day) as u64)
+}
+
+/// ISO year and week number of the week starting on `start`, like "2025-W10", for file
+/// names. Weeks starting on another day than Monday go by their middle day
+fn week_label(start: NaiveDate) -> String {
+    let iso = (start + Days::new(3)).iso_week();
+    format!("{}-W{:02}", iso.year(), iso.week())
+}
+
+/// Tag counts over `completed`, most used first
+pub fn count_tags(completed: &[DoneMatch]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for m in completed {
+        let tags = done::tags(&m.task.text);
+        if tags.is_empty() {
+            *counts.entry(UNTAGGED.to_string()).or_default() += 1;
+        }
+        for tag in tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+}
+
+/// Summarize the week containing `date` and write it to `rollup_<year>-W<week>.md`
+/// next to the done log, replacing an earlier rollup of the same week
+pub fn weekly_rollup(date: NaiveDate, first_day: Weekday) -> Result<WeeklyRollup, String> {
+    let start = week_start(date, first_day);
+    let end = start + Days::new(6);
+    let completed = done::history(DateRange {
+        from: Some(start),
+        to: Some(end),
+    })?;
+
+    let per_day: Vec<(NaiveDate, usize)> = start
+        .iter_days()
+        .take(7)
+        .map(|day| {
+            let key = day.format("%Y-%m-%d").to_string();
+            (day, completed.iter().filter(|m| m.date == key).count())
+        })
+        .collect();
+    let per_tag = count_tags(&completed);
+
+    let mut markdown = format!("# Week of {}\n\n{} completed.\n\n## By day\n\n", locale::date(start), completed.len());
+    for (day, count) in &per_day {
+        markdown.push_str(&format!("- {} {}: {}\n", locale::format(*day, "%a"), locale::date(*day), count));
+    }
+    markdown.push_str("\n## By tag\n\n");
+    for (tag, count) in &per_tag {
+        let label = if tag == UNTAGGED { tag.clone() } else { format!("#{}", tag) };
+        markdown.push_str(&format!("- {}: {}\n", label, count));
+    }
+    markdown.push_str("\n## Completed\n");
+    for (day, count) in &per_day {
+        if *count == 0 {
+            continue;
+        }
+        let key = day.format("%Y-%m-%d").to_string();
+        markdown.push_str(&format!("\n### {} {}\n\n", locale::format(*day, "%a"), locale::date(*day)));
+        for m in completed.iter().filter(|m| m.date == key) {
+            markdown.push_str(&format!("- {}\n", m.task.text));
+        }
+    }
+
+    let file = format!("rollup_{}.md", week_label(start));
+    fs::write(done::archive_dir()?.join(&file), markdown).map_err(|e| e.to_string())?;
+
+    Ok(WeeklyRollup {
+        week_start: start,
+        total: completed.len(),
+        per_day,
+        per_tag,
+        file,
+    })
+}
+
+/// Write every completion in `range`, from the done log and all archives, to `path`
+/// as one done log in the format its extension names (markdown otherwise); returns
+/// how many were written
+pub fn export_history(range: DateRange, path: &str) -> Result<usize, String> {
+    let path = done::expand_path(path)?;
+    if !path.is_absolute() {
+        return Err(format!("Export path must be absolute or start with ~/: {}", path.display()));
+    }
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let format = DoneFormat::of_file(name).unwrap_or_default();
+
+    let completed = done::history(range)?;
+    let mut content = String::new();
+    for m in &completed {
+        let entry = done::format_entry(&content, &m.date, m.time.as_deref(), &m.task, format);
+        content.push_str(&entry);
+    }
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(completed.len())
+}
+
+/// Add a summary to the end of the archive at `path`: the total, a table of
+/// completions per day and the busiest day. Written as a heading and table, which
+/// the entry parser skips; CSV archives are left alone
+pub fn append_tally(path: &Path) -> Result<(), String> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let format = DoneFormat::of_file(name).unwrap_or_default();
+    if format == DoneFormat::Csv {
+        return Ok(());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let entries = done::parse_entries(&content, format);
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let mut per_day: BTreeMap<&str, usize> = BTreeMap::new();
+    for entry in &entries {
+        *per_day.entry(entry.date.as_str()).or_default() += 1;
+    }
+    let (busiest, most) = per_day
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(day, count)| (*day, *count))
+        .unwrap_or_default();
+
+    let heading = if format == DoneFormat::Org { "* Summary" } else { "## Summary" };
+    let mut tally = format!(
+        "\n{}\n\n{} tasks completed over {} days. Busiest day: {} ({})\n\n| Day | Completed |\n",
+        heading,
+        entries.len(),
+        per_day.len(),
+        busiest,
+        most
+    );
+    if format == DoneFormat::Markdown {
+        tally.push_str("|---|---|\n");
+    } else {
+        tally.push_str("|---+---|\n");
+    }
+    for (day, count) in &per_day {
+        tally.push_str(&format!("| {} | {} |\n", day, count));
+    }
+
+    let mut file = OpenOptions::new().append(true).open(path).map_err(|e| e.to_string())?;
+    file.write_all(tally.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Completions of one project in a weekly review
+#[derive(Debug, Serialize, Clone)]
+pub struct ProjectTasks {
+    /// `None` for tasks without a project
+    pub project: Option<String>,
+    pub tasks: Vec<String>,
+}
+
+/// Everything an end-of-week review goes through
+#[derive(Debug, Serialize, Clone)]
+pub struct WeeklyReview {
+    /// The day the week starts on, per `week_start` in the config
+    pub week_start: NaiveDate,
+    pub completed: usize,
+    /// Busiest project first; tasks without one come last
+    pub by_project: Vec<ProjectTasks>,
+    /// Added this week and still open
+    pub added_open: Vec<QueryMatch>,
+    /// On the shelf, not snoozed, for over two weeks
+    pub stale_shelf: Vec<QueryMatch>,
+    pub streak: Streak,
+    /// Name of the markdown file written next to the done log, if asked for
+    pub file: Option<String>,
+}
+
+/// The project of a completion: its own, or the one its text is prefixed with
+pub fn project_of(task: &Task) -> Option<String> {
+    if let Some(project) = &task.project {
+        return Some(project.clone());
+    }
+    let rest = task.text.strip_prefix('(')?;
+    let (project, _) = rest.split_once(") ")?;
+    Some(project.to_string()).filter(|p| !p.is_empty())
+}
+
+fn matches_in(state: &TaskState, keep: impl Fn(SearchList, &Task) -> bool) -> Vec<QueryMatch> {
+    let lists = [(SearchList::Current, &state.current), (SearchList::Shelf, &state.shelf)];
+    let mut found = Vec::new();
+    for (list, tasks) in lists {
+        for (index, task) in tasks.iter().enumerate().filter(|(_, task)| keep(list, task)) {
+            found.push(QueryMatch {
+                list,
+                index,
+                task: task.clone(),
+            });
+        }
+    }
+    found
+}
+
+/// Review of the week containing `now`: what got done per project, what was added
+/// and not finished, what's gone stale on the shelf and the streak. With `write`,
+/// also saved as `review_<year>-W<week>.md` next to the done log
+pub fn weekly_review(
+    state: &TaskState,
+    work_week: &WorkWeek,
+    first_day: Weekday,
+    now: NaiveDateTime,
+    write: bool,
+) -> Result<WeeklyReview, String> {
+    let today = now.date();
+    let start = week_start(today, first_day);
+    let history = search_index::history(DateRange::default())?;
+    let week: Vec<&DoneMatch> = history
+        .iter()
+        .filter(|m| stats::day_of(m).is_some_and(|d| d >= start && d <= today))
+        .collect();
+
+    let mut groups: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    for m in &week {
+        groups.entry(project_of(&m.task)).or_default().push(m.task.text.clone());
+    }
+    let mut by_project: Vec<ProjectTasks> = groups
+        .into_iter()
+        .map(|(project, tasks)| ProjectTasks { project, tasks })
+        .collect();
+    by_project.sort_by_key(|g| (g.project.is_none(), std::cmp::Reverse(g.tasks.len())));
+
+    let started = start.and_hms_opt(0, 0, 0).unwrap_or(now);
+    let added_open = matches_in(state, |_, task| task.created.is_some_and(|c| c >= started));
+    let stale_shelf = matches_in(state, |list, task| {
+        list == SearchList::Shelf
+            && task.snoozed_until.is_none()
+            && task.created.is_some_and(|c| (now - c).num_days() > STALE_DAYS)
+    });
+    let streak = stats::streak(&stats::active_days(&history), work_week, today);
+
+    let mut review = WeeklyReview {
+        week_start: start,
+        completed: week.len(),
+        by_project,
+        added_open,
+        stale_shelf,
+        streak,
+        file: None,
+    };
+    if write {
+        let file = format!("review_{}.md", week_label(start));
+        fs::write(done::archive_dir()?.join(&file), review_markdown(&review)).map_err(|e| e.to_string())?;
+        review.file = Some(file);
+    }
+    Ok(review)
+}
+
+fn review_markdown(review: &WeeklyReview) -> String {
+    let mut markdown = format!(
+        "# Review of the week of {}\n\n{} completed. ",
+        locale::date(review.week_start),
+        review.completed
+    );
+    markdown.push_str(&format!(
+        "Streak: {} days (longest {}){}.\n\n## Done\n",
+        review.streak.current,
+        review.streak.longest,
+        if review.streak.today_done { "" } else { ", nothing yet today" }
+    ));
+    for group in &review.by_project {
+        markdown.push_str(&format!("\n### {}\n\n", group.project.as_deref().unwrap_or("No project")));
+        for task in &group.tasks {
+            markdown.push_str(&format!("- {}\n", task));
+        }
+    }
+    for (heading, tasks) in [
+        ("Added and still open", &review.added_open),
+        ("Stale on the shelf", &review.stale_shelf),
+    ] {
+        markdown.push_str(&format!("\n## {}\n\n", heading));
+        if tasks.is_empty() {
+            markdown.push_str("Nothing.\n");
+        }
+        for m in tasks {
+            markdown.push_str(&format!("- {}\n", m.task.text));
+        }
+    }
+    markdown
+}
+
+/// How many to list under the busiest weeks and top projects and tags
+const YEAR_TOP: usize = 5;
+
+/// Completions in the week starting on `week_start`
+#[derive(Debug, Serialize, Clone)]
+pub struct WeekCount {
+    pub week_start: NaiveDate,
+    pub completed: usize,
+}
+
+/// A look back over one year of completions
+#[derive(Debug, Serialize, Clone)]
+pub struct YearInReview {
+    pub year: i32,
+    pub total: usize,
+    /// Days with at least one completion
+    pub active_days: usize,
+    /// January to December
+    pub per_month: Vec<usize>,
+    /// Busiest first
+    pub busiest_weeks: Vec<WeekCount>,
+    /// Longest run of days with completions; days off don't break it
+    pub longest_streak: usize,
+    /// Most completions first
+    pub top_projects: Vec<(String, usize)>,
+    /// Most completions first, leaving out untagged ones
+    pub top_tags: Vec<(String, usize)>,
+    /// The above written up as a markdown page
+    pub markdown: String,
+    /// Name of the file the markdown was written to next to the done log, if asked for
+    pub file: Option<String>,
+}
+
+/// Review of `year` from the done log, its archives and done.jsonl. With `write`,
+/// also saved as `review_<year>.md` next to the done log
+pub fn year_in_review(
+    year: i32,
+    work_week: &WorkWeek,
+    first_day: Weekday,
+    today: NaiveDate,
+    write: bool,
+) -> Result<YearInReview, String> {
+    let first = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| format!("Invalid year: {}", year))?;
+    let last = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| format!("Invalid year: {}", year))?;
+    let completed = search_index::history(DateRange {
+        from: Some(first),
+        to: Some(last),
+    })?;
+
+    let mut per_month = vec![0; 12];
+    let mut per_week: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for day in completed.iter().filter_map(stats::day_of) {
+        per_month[day.month0() as usize] += 1;
+        *per_week.entry(week_start(day, first_day)).or_default() += 1;
+    }
+    let mut busiest_weeks: Vec<WeekCount> = per_week
+        .into_iter()
+        .map(|(week_start, completed)| WeekCount { week_start, completed })
+        .collect();
+    busiest_weeks.sort_by_key(|w| std::cmp::Reverse(w.completed));
+    busiest_weeks.truncate(YEAR_TOP);
+
+    let days = stats::active_days(&completed);
+    let longest_streak = stats::streak(&days, work_week, today.min(last)).longest;
+
+    let mut projects: BTreeMap<String, usize> = BTreeMap::new();
+    for project in completed.iter().filter_map(|m| project_of(&m.task)) {
+        *projects.entry(project).or_default() += 1;
+    }
+    let mut top_projects: Vec<(String, usize)> = projects.into_iter().collect();
+    top_projects.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    top_projects.truncate(YEAR_TOP);
+    let mut top_tags: Vec<(String, usize)> =
+        count_tags(&completed).into_iter().filter(|(tag, _)| tag != UNTAGGED).collect();
+    top_tags.truncate(YEAR_TOP);
+
+    let mut review = YearInReview {
+        year,
+        total: completed.len(),
+        active_days: days.len(),
+        per_month,
+        busiest_weeks,
+        longest_streak,
+        top_projects,
+        top_tags,
+        markdown: String::new(),
+        file: None,
+    };
+    review.markdown = year_markdown(&review);
+    if write {
+        let file = format!("review_{}.md", year);
+        fs::write(done::archive_dir()?.join(&file), &review.markdown).map_err(|e| e.to_string())?;
+        review.file = Some(file);
+    }
+    Ok(review)
+}
+
+fn year_markdown(review: &YearInReview) -> String {
+    let mut markdown = format!("# {} in review\n\n", review.year);
+    if review.total == 0 {
+        markdown.push_str("Nothing was completed this year.\n");
+        return markdown;
+    }
+    markdown.push_str(&format!(
+        "{} tasks completed over {} days, about {:.1} on each of them. ",
+        review.total,
+        review.active_days,
+        review.total as f64 / review.active_days.max(1) as f64
+    ));
+    if let Some(week) = review.busiest_weeks.first() {
+        markdown.push_str(&format!(
+            "The busiest week started {} with {} done",
+            locale::date(week.week_start),
+            week.completed
+        ));
+    }
+    markdown.push_str(&format!(", and the longest streak ran {} days.\n", review.longest_streak));
+    if let Some((project, count)) = review.top_projects.first() {
+        markdown.push_str(&format!("\nMost of the work went into {} ({} tasks)", project, count));
+        match review.top_tags.first() {
+            Some((tag, count)) => markdown.push_str(&format!(", and #{} was the most used tag ({}).\n", tag, count)),
+            None => markdown.push_str(".\n"),
+        }
+    } else if let Some((tag, count)) = review.top_tags.first() {
+        markdown.push_str(&format!("\n#{} was the most used tag ({}).\n", tag, count));
+    }
+
+    markdown.push_str("\n## By month\n\n");
+    for (month, count) in review.per_month.iter().enumerate() {
+        let name = NaiveDate::from_ymd_opt(review.year, month as u32 + 1, 1).map(|d| locale::format(d, "%B"));
+        markdown.push_str(&format!("- {}: {}\n", name.unwrap_or_default(), count));
+    }
+    markdown.push_str("\n## Busiest weeks\n\n");
+    for week in &review.busiest_weeks {
+        markdown.push_str(&format!("- Week of {}: {}\n", locale::date(week.week_start), week.completed));
+    }
+    for (heading, counts, prefix) in [
+        ("Top projects", &review.top_projects, ""),
+        ("Top tags", &review.top_tags, "#"),
+    ] {
+        if counts.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("\n## {}\n\n", heading));
+        for (name, count) in counts {
+            markdown.push_str(&format!("- {}{}: {}\n", prefix, name, count));
+        }
+    }
+    markdown
+}
@@ -0,0 +1,439 @@
+//! Background thread for everything that happens at a time of day rather than
+//! in response to the user: recurring and snoozed tasks, due-date reminders and
+//! overdue escalation, the morning planning prompt, the end-of-day summary and
+//! automatic archiving.
+//!
+//! Nothing about pending reminders is stored. Each tick fires whatever fell due
+//! since the previous tick, computed from the tasks as they are now, so edits to
+//! due dates apply right away and a restart simply picks up from the current state.
+//! Ticks compare wall-clock times, so a tick that arrives long after the previous
+//! one means the machine slept: tasks are brought up to date, but reminders from
+//! the gap are dropped rather than fired in a burst. While the user is idle,
+//! reminders are held and delivered together when they're back.
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::Source;
+use crate::{
+    archive_done_from, day_summary, defaults, done, idle, insert_task, locale, notify, notify_tasks_changed, report,
+    save_tasks, show_summary, show_window, tray, undo, AppState, Task, TaskState,
+};
+
+/// How often the scheduler wakes up; reminders fire at most this late
+const TICK: Duration = Duration::from_secs(15);
+
+/// A gap between ticks this long means the machine was asleep
+const SLEEP_GAP: TimeDelta = TimeDelta::minutes(1);
+
+/// Whether the user was idle at the last tick
+static AWAY: AtomicBool = AtomicBool::new(false);
+
+/// Notifications (title, body) held back while the user is idle
+static HELD: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// How often done.md is rolled into a `done_<timestamp>.md` archive automatically
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoArchive {
+    #[default]
+    Off,
+    Daily,
+    /// Weeks start on the configured `week_start`
+    Weekly,
+    Monthly,
+}
+
+impl AutoArchive {
+    /// First day of the period containing `today`, with weeks starting on `first_day`, or `None`
+    /// when turned off
+    fn period_start(self, today: NaiveDate, first_day: Weekday) -> Option<NaiveDate> {
+        match self {
+            AutoArchive::Off => None,
+            AutoArchive::Daily => Some(today),
+            AutoArchive::Weekly => Some(report::week_start(today, first_day)),
+            AutoArchive::Monthly => today.with_day(1),
+        }
+    }
+}
+
+/// Days on which reminders, overdue nags and the daily prompts may fire
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WorkWeek {
+    pub days: Vec<Weekday>,
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for WorkWeek {
+    fn default() -> Self {
+        Self {
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl WorkWeek {
+    pub fn is_work_day(&self, date: NaiveDate) -> bool {
+        self.days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+}
+
+fn is_work_day(app: &AppHandle, date: NaiveDate) -> bool {
+    app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).work_week.is_work_day(date)
+}
+
+/// Nagging about tasks that are past due
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct OverdueConfig {
+    pub enabled: bool,
+    /// Minutes between repeat notifications, growing; the last gap then repeats
+    pub renotify_minutes: Vec<u32>,
+    /// Show the overdue count as a red badge on the tray icon
+    pub badge: bool,
+}
+
+impl Default for OverdueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            renotify_minutes: defaults::overdue_renotify_minutes(),
+            badge: true,
+        }
+    }
+}
+
+pub fn start(app: AppHandle) {
+    thread::spawn(move || {
+        // Reminders that came due while the app wasn't running aren't replayed
+        let mut last = Local::now().naive_local();
+        let mut overdue_count = 0;
+        auto_archive(&app, last.date());
+        loop {
+            thread::sleep(TICK);
+            let now = Local::now().naive_local();
+            // After sleep, start the reminder window at `now` so nothing from the gap fires
+            let from = if now - last > SLEEP_GAP { now } else { last };
+            check_idle(&app);
+            wake_tasks(&app, now);
+            due_reminders(&app, from, now);
+            overdue_count = escalate_overdue(&app, from, now, overdue_count);
+            planning_prompt(&app, from, now);
+            end_of_day_summary(&app, from, now);
+            if now.date() != last.date() {
+                auto_archive(&app, now.date());
+            }
+            last = now;
+        }
+    });
+}
+
+/// Show a notification now, or hold it until the user is back if they're idle
+fn remind(app: &AppHandle, title: &str, body: &str) {
+    if AWAY.load(Ordering::Relaxed) {
+        HELD.lock().unwrap_or_else(|e| e.into_inner()).push((title.to_string(), body.to_string()));
+    } else {
+        notify(app, title, body);
+    }
+}
+
+/// Note whether the user has gone idle; on their return, deliver everything held
+/// meanwhile as a single notification
+fn check_idle(app: &AppHandle) {
+    let minutes = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).idle_minutes;
+    let away = minutes > 0 && idle::idle_time().is_some_and(|idle| idle.as_secs() >= minutes as u64 * 60);
+    let was_away = AWAY.swap(away, Ordering::Relaxed);
+    if away || !was_away {
+        return;
+    }
+
+    let held = std::mem::take(&mut *HELD.lock().unwrap_or_else(|e| e.into_inner()));
+    match held.as_slice() {
+        [] => {}
+        [(title, body)] => notify(app, title, body),
+        [(title, body), rest @ ..] => notify(
+            app,
+            "While you were away",
+            &format!("{}: {} and {} more", title, body, rest.len()),
+        ),
+    }
+}
+
+/// Whether the daily `time` occurred in `(from, to]`
+fn passed(time: NaiveTime, from: NaiveDateTime, to: NaiveDateTime) -> bool {
+    [from.date(), to.date()]
+        .iter()
+        .map(|day| day.and_time(time))
+        .any(|at| at > from && at <= to)
+}
+
+/// Return snoozed tasks whose time has come to `current` and add recurring
+/// tasks that are due, skipping any whose previous copy is still open
+fn wake_tasks(app: &AppHandle, now: NaiveDateTime) {
+    let (changed, woken) = {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = false;
+        let mut woken_texts = Vec::new();
+
+        let (woken, shelf): (Vec<Task>, Vec<Task>) = std::mem::take(&mut tasks.shelf)
+            .into_iter()
+            .partition(|t| t.snoozed_until.is_some_and(|at| at <= now));
+        tasks.shelf = shelf;
+        for mut task in woken {
+            task.snoozed_until = None;
+            woken_texts.push(task.text.clone());
+            insert_task(&mut tasks, task);
+            changed = true;
+        }
+
+        let mut due = Vec::new();
+        for recurring in &mut tasks.recurring {
+            match recurring.next {
+                Some(next) if next > now => continue,
+                // Each copy is a task of its own, with its own id
+                Some(_) => due.push(Task {
+                    id: None,
+                    ..recurring.task.clone()
+                }),
+                // Newly added by hand in state.json
+                None => {}
+            }
+            // Occurrences missed while the app was closed collapse into this one
+            recurring.next = recurring.next_after(now);
+            changed = true;
+        }
+        for task in due {
+            if !tasks.current.iter().chain(&tasks.shelf).any(|t| t.text == task.text) {
+                insert_task(&mut tasks, task);
+            }
+        }
+
+        if changed {
+            if let Err(e) = save_tasks(&mut tasks) {
+                eprintln!("Warning: Failed to save scheduled tasks: {}", e);
+            }
+            undo::record(app, Source::Scheduler, "Scheduled Tasks", &tasks);
+        }
+        if !woken_texts.is_empty() {
+            *state.woken.lock().unwrap_or_else(|e| e.into_inner()) = woken_texts.clone();
+        }
+        (changed, woken_texts)
+    };
+    if changed {
+        notify_tasks_changed(app);
+    }
+
+    // Desktop notifications can't carry buttons, so "Snooze Again" lives in the tray
+    match woken.as_slice() {
+        [] => {}
+        [text] => remind(app, "Back from the shelf", text),
+        texts => remind(app, "Back from the shelf", &format!("{} and {} more", texts[0], texts.len() - 1)),
+    }
+}
+
+/// "15 min", "2 h", "1 day"
+fn format_lead(minutes: u32) -> String {
+    match minutes {
+        m if m % 1440 == 0 => format!("{} day{}", m / 1440, if m == 1440 { "" } else { "s" }),
+        m if m % 60 == 0 => format!("{} h", m / 60),
+        m => format!("{} min", m),
+    }
+}
+
+/// Notify about tasks whose due time, or one of the lead times before it, is in `(from, to]`
+fn due_reminders(app: &AppHandle, from: NaiveDateTime, to: NaiveDateTime) {
+    let (leads, due): (Vec<u32>, Vec<(String, NaiveDateTime)>) = {
+        let state = app.state::<AppState>();
+        let leads = state.config.lock().unwrap_or_else(|e| e.into_inner()).reminder_lead_minutes.clone();
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let due = tasks
+            .current
+            .iter()
+            .chain(&tasks.shelf)
+            .filter_map(|t| Some((t.text.clone(), t.due?)))
+            .collect();
+        (leads, due)
+    };
+
+    for (text, due_at) in due {
+        // A due time was set on purpose, so it fires even on a day off
+        if due_at > from && due_at <= to {
+            remind(app, "Task due", &text);
+            continue;
+        }
+        if !is_work_day(app, to.date()) {
+            continue;
+        }
+        for &lead in &leads {
+            let remind_at = due_at - TimeDelta::minutes(lead as i64);
+            if remind_at > from && remind_at <= to {
+                remind(app, &format!("Due in {}", format_lead(lead)), &text);
+            }
+        }
+    }
+}
+
+/// Tasks in `current` or on the shelf whose due time has passed, longest overdue first
+pub fn overdue_tasks(tasks: &TaskState, now: NaiveDateTime) -> Vec<Task> {
+    let mut overdue: Vec<Task> = tasks
+        .current
+        .iter()
+        .chain(&tasks.shelf)
+        .filter(|t| t.due.is_some_and(|due| due <= now))
+        .cloned()
+        .collect();
+    overdue.sort_by_key(|t| t.due);
+    overdue
+}
+
+/// Whether a repeat notification for a task due at `due` falls in `(from, to]`
+fn renotifies(due: NaiveDateTime, intervals: &[u32], from: NaiveDateTime, to: NaiveDateTime) -> bool {
+    let Some(&last) = intervals.last() else {
+        return false;
+    };
+    let mut at = due;
+    for &minutes in intervals {
+        at += TimeDelta::minutes(minutes.max(1) as i64);
+        if at > from && at <= to {
+            return true;
+        }
+    }
+
+    // Past the listed gaps the last one repeats; find the latest repeat up to `to`
+    if at >= to {
+        return false;
+    }
+    let period = last.max(1) as i64;
+    let repeats = (to - at).num_minutes() / period;
+    repeats > 0 && at + TimeDelta::minutes(repeats * period) > from
+}
+
+/// Re-notify about overdue tasks at growing intervals, and redraw the tray badge
+/// when the number of overdue tasks changes; returns that number
+fn escalate_overdue(app: &AppHandle, from: NaiveDateTime, to: NaiveDateTime, previous: usize) -> usize {
+    let (config, overdue) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner()).overdue.clone();
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        (config, overdue_tasks(&tasks, to))
+    };
+    if !config.enabled {
+        return 0;
+    }
+
+    // Days off only skip the repeats; the badge still counts
+    let renotify = if is_work_day(app, to.date()) { config.renotify_minutes.as_slice() } else { &[] };
+    for task in &overdue {
+        let Some(due) = task.due else {
+            continue;
+        };
+        if renotifies(due, renotify, from, to) {
+            let late = (to - due).num_minutes().max(0) as u32;
+            let late = if late >= 60 { late / 60 * 60 } else { late };
+            let body = if due.date() < to.date() {
+                format!("{} (due {})", task.text, locale::date(due.date()))
+            } else {
+                task.text.clone()
+            };
+            remind(app, &format!("Overdue by {}", format_lead(late)), &body);
+        }
+    }
+
+    if overdue.len() != previous {
+        tray::refresh_icon(app);
+    }
+    overdue.len()
+}
+
+/// Open the window on the planning view at the configured time of day
+fn planning_prompt(app: &AppHandle, from: NaiveDateTime, to: NaiveDateTime) {
+    let state = app.state::<AppState>();
+    let Some(time) = state.config.lock().unwrap_or_else(|e| e.into_inner()).planning_time else {
+        return;
+    };
+    if !passed(time, from, to) || !is_work_day(app, to.date()) || *state.dnd.lock().unwrap_or_else(|e| e.into_inner()) {
+        return;
+    }
+
+    // Placing the window needs the main thread on macOS
+    let handle = app.clone();
+    let _ = app.run_on_main_thread(move || {
+        show_window(&handle);
+        let _ = handle.emit("open-planning", ());
+    });
+}
+
+/// Notify with today's completed and open counts at the configured time of day, and open
+/// the window on the summary if `summary_opens_window` is set and the user is around
+fn end_of_day_summary(app: &AppHandle, from: NaiveDateTime, to: NaiveDateTime) {
+    let time = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).summary_time;
+    if !time.is_some_and(|time| passed(time, from, to)) || !is_work_day(app, to.date()) {
+        return;
+    }
+    let goal = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).daily_goal as usize;
+    match day_summary(app) {
+        Ok(summary) => {
+            let done = summary.completed.len();
+            let completed = if goal == 0 {
+                format!("{} completed today", done)
+            } else if done >= goal {
+                format!("{} completed today, goal of {} reached", done, goal)
+            } else {
+                format!("{} of {} completed today", done, goal)
+            };
+            remind(app, "Today", &format!("{}, {} still open", completed, summary.open));
+            let state = app.state::<AppState>();
+            let opens = state.config.lock().unwrap_or_else(|e| e.into_inner()).summary_opens_window;
+            let dnd = *state.dnd.lock().unwrap_or_else(|e| e.into_inner());
+            if !opens || dnd || AWAY.load(Ordering::Relaxed) {
+                return;
+            }
+            // Placing the window needs the main thread on macOS
+            let handle = app.clone();
+            let _ = app.run_on_main_thread(move || show_summary(&handle));
+        }
+        Err(e) => eprintln!("Warning: Failed to build the day summary: {}", e),
+    }
+}
+
+/// Archive done.md once it holds entries from before the current archive period,
+/// which also catches up on periods that ended while the app wasn't running
+fn auto_archive(app: &AppHandle, today: NaiveDate) {
+    let (setting, first_day) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        (config.auto_archive, config.week_start)
+    };
+    let Some(start) = setting.period_start(today, first_day) else {
+        return;
+    };
+    match done::oldest_date() {
+        Ok(Some(oldest)) if oldest < start => {}
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("Warning: Failed to read done log: {}", e);
+            return;
+        }
+    }
+
+    match archive_done_from(app, Source::Scheduler) {
+        Ok(name) => remind(app, "Archived completed tasks", &name),
+        Err(e) => eprintln!("Warning: Automatic archive failed: {}", e),
+    }
+}
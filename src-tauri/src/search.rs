@@ -0,0 +1,272 @@
+//! Searching task text and notes in `current`, on the shelf and in the done log
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{done, Task, TaskState};
+
+/// Characters of context kept on each side of a match in a snippet
+const CONTEXT: usize = 30;
+
+/// Longest pattern accepted in regex mode
+const MAX_PATTERN_LEN: usize = 256;
+/// Cap on a compiled pattern's size. The regex crate matches in time linear in the
+/// text, so a pattern can't run away once compiled; this stops one blowing up before
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+// Fuzzy scoring, after fzf: every matched character scores, more so at the start
+// of a word or right after the previous match, and skipped characters cost a little
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 8;
+const GAP_START: i64 = -3;
+const GAP_EXTENSION: i64 = -1;
+
+/// Where a search result was found
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchList {
+    Current,
+    Shelf,
+    Done,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResult {
+    pub list: SearchList,
+    /// Position in `current` or `shelf`; for done entries the line id `uncomplete_task` takes
+    pub index: usize,
+    /// Completion date of a done entry
+    pub date: Option<String>,
+    pub text: String,
+    /// The note that matched, when the task's text didn't
+    pub note: Option<usize>,
+    /// The matching text or note, cut down to the part around the match
+    pub snippet: String,
+    /// How well it matched; higher is better, and results come sorted by it.
+    /// Always 0 in regex mode
+    pub score: i64,
+}
+
+/// A note of one task that matched `search_notes`
+#[derive(Debug, Serialize, Clone)]
+pub struct NoteMatch {
+    /// Position in the task's notes
+    pub note: usize,
+    pub snippet: String,
+    pub score: i64,
+}
+
+/// Bonus for matching the character at `j`, by what comes before it
+fn position_bonus(chars: &[char], j: usize) -> i64 {
+    let Some(&prev) = j.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return BONUS_BOUNDARY;
+    };
+    if !prev.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && chars[j].is_uppercase() {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+/// Best score for `word`'s characters appearing in order in `text`, ignoring case,
+/// or `None` if they don't. Smith-Waterman style: row by row over the word, the best
+/// way to end a match on each text character, with affine gap costs
+fn score_word(word: &[char], text: &[char]) -> Option<i64> {
+    let lower: Vec<char> = text.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let mut prev_row: Vec<Option<i64>> = Vec::new();
+    for (i, &wc) in word.iter().enumerate() {
+        let mut row = vec![None; text.len()];
+        // Best score of the previous character ending before j - 1, less the gap to j
+        let mut gapped: Option<i64> = None;
+        for j in 0..text.len() {
+            if i > 0 && j >= 2 {
+                let opened = prev_row[j - 2].map(|s| s + GAP_START);
+                gapped = gapped.map(|g| g + GAP_EXTENSION).max(opened);
+            }
+            if lower[j] != wc {
+                continue;
+            }
+            let here = SCORE_MATCH + position_bonus(text, j);
+            row[j] = if i == 0 {
+                Some(here)
+            } else {
+                let adjacent = j.checked_sub(1).and_then(|p| prev_row[p]).map(|s| s + BONUS_CONSECUTIVE);
+                adjacent.max(gapped).map(|s| s + here)
+            };
+        }
+        prev_row = row;
+    }
+    prev_row.into_iter().flatten().max()
+}
+
+/// Fuzzy score of `query` against `text`: each whitespace-separated word of the
+/// query has to match in order somewhere in the text, so "dply web" finds
+/// "Deploy website". `None` when some word doesn't match
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut total = 0;
+    let mut words = 0;
+    for word in query.split_whitespace() {
+        let word: Vec<char> = word.to_lowercase().chars().collect();
+        total += score_word(&word, &text)?;
+        words += 1;
+    }
+    (words > 0).then_some(total)
+}
+
+/// `text` around the byte range `start..end`, with "…" where it was cut
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let before: Vec<(usize, char)> = text[..start].char_indices().collect();
+    let from = before.len().checked_sub(CONTEXT).map_or(0, |i| before[i].0);
+    let to = text[end..].char_indices().nth(CONTEXT).map_or(text.len(), |(i, _)| end + i);
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(text[from..to].trim());
+    if to < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Byte range of `query` (already lowercase) in `text`, ignoring case
+fn find_in(text: &str, query: &str) -> Option<(usize, usize)> {
+    let lower = text.to_lowercase();
+    let start = lower.find(query)?;
+    // Lowercasing can change byte lengths outside ASCII; then match positions can't
+    // be mapped back, so the snippet starts at the beginning instead
+    if lower.len() == text.len() {
+        Some((start, start + query.len()))
+    } else {
+        Some((0, 0))
+    }
+}
+
+/// Snippet of `text` around where `query` (or, failing that, its first word) occurs
+fn snippet_for(text: &str, query: &str) -> String {
+    let first_word = query.split_whitespace().next().unwrap_or_default();
+    let (start, end) = find_in(text, query).or_else(|| find_in(text, first_word)).unwrap_or((0, 0));
+    snippet(text, start, end)
+}
+
+/// How well `task` matches, and if a note matched better than its text, which
+/// one, with the snippet from wherever matched best
+fn match_task(task: &Task, query: &str) -> Option<(i64, Option<usize>, String)> {
+    let text = fuzzy_score(query, &task.text).map(|score| (score, None, &task.text));
+    let note = task
+        .notes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, note)| fuzzy_score(query, &note.text).map(|score| (score, Some(i), &note.text)))
+        .max_by_key(|(score, _, _)| *score);
+    let (score, note, matched) = match (text, note) {
+        (Some(text), Some(note)) if note.0 > text.0 => note,
+        (Some(text), _) => text,
+        (None, note) => note?,
+    };
+    Some((score, note, snippet_for(matched, query)))
+}
+
+/// Compile `pattern` for a regex search. Case is ignored unless the pattern turns
+/// that off with `(?-i)`
+pub fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    if pattern.len() > MAX_PATTERN_LEN {
+        return Err(format!("Pattern is longer than {} characters", MAX_PATTERN_LEN));
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+/// Whether `regex` matches the text of `task` or else one of its notes, like `match_task`
+fn match_regex(task: &Task, regex: &Regex) -> Option<(i64, Option<usize>, String)> {
+    if let Some(m) = regex.find(&task.text) {
+        return Some((0, None, snippet(&task.text, m.start(), m.end())));
+    }
+    task.notes.iter().enumerate().find_map(|(i, note)| {
+        regex
+            .find(&note.text)
+            .map(|m| (0, Some(i), snippet(&note.text, m.start(), m.end())))
+    })
+}
+
+/// Tasks in `current`, on the shelf and in the done log whose text or one of whose
+/// notes fuzzily matches `query`, best match first; ties put open tasks first, then
+/// the newest completions. With `regex`, `query` is a pattern instead and results
+/// come in that list order
+pub fn search(tasks: &TaskState, query: &str, regex: bool) -> Result<Vec<SearchResult>, String> {
+    let pattern = if regex { Some(compile_regex(query.trim())?) } else { None };
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let match_task = |task: &Task| match &pattern {
+        Some(pattern) => match_regex(task, pattern),
+        None => match_task(task, &query),
+    };
+
+    let mut results = Vec::new();
+    for (list, items) in [(SearchList::Current, &tasks.current), (SearchList::Shelf, &tasks.shelf)] {
+        for (index, task) in items.iter().enumerate() {
+            if let Some((score, note, snippet)) = match_task(task) {
+                results.push(SearchResult {
+                    list,
+                    index,
+                    date: None,
+                    text: task.text.clone(),
+                    note,
+                    snippet,
+                    score,
+                });
+            }
+        }
+    }
+    for entry in done::entries()?.into_iter().rev() {
+        if let Some((score, note, snippet)) = match_task(&entry.task) {
+            results.push(SearchResult {
+                list: SearchList::Done,
+                index: entry.line,
+                date: Some(entry.date),
+                text: entry.task.text,
+                note,
+                snippet,
+                score,
+            });
+        }
+    }
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    Ok(results)
+}
+
+/// Notes of `task` that fuzzily match `query` (or `regex` when given), best first
+/// and in note order among equals
+pub fn search_notes(task: &Task, query: &str, regex: bool) -> Result<Vec<NoteMatch>, String> {
+    let pattern = if regex { Some(compile_regex(query.trim())?) } else { None };
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut found: Vec<NoteMatch> = task
+        .notes
+        .iter()
+        .enumerate()
+        .filter_map(|(note, n)| {
+            let (score, snippet) = match &pattern {
+                Some(pattern) => pattern.find(&n.text).map(|m| (0, snippet(&n.text, m.start(), m.end())))?,
+                None => (fuzzy_score(&query, &n.text)?, snippet_for(&n.text, &query)),
+            };
+            Some(NoteMatch { note, snippet, score })
+        })
+        .collect();
+    found.sort_by_key(|m| std::cmp::Reverse(m.score));
+    Ok(found)
+}
@@ -0,0 +1,434 @@
+//! On-disk word index over the done log, its archives and done.jsonl, so `search_done`
+//! doesn't have to re-read and re-parse years of archives on every query
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::done::{self, DateRange, DoneFormat, DoneMatch};
+use crate::search::compile_regex;
+use crate::{get_done_records_file, get_tasks_dir, Task};
+
+/// Bumped whenever the layout changes, so an old index is rebuilt rather than misread
+const VERSION: u32 = 4;
+
+/// One completion in a segment
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct IndexedEntry {
+    date: String,
+    time: Option<String>,
+    task: Task,
+    /// done.jsonl only: whether a done log or archive has this completion too, so it's
+    /// left out of results
+    #[serde(default)]
+    shadowed: bool,
+}
+
+/// The entries of one file and which of them contain each word
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Segment {
+    /// Shown as the `source` of matches
+    name: String,
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    entries: Vec<IndexedEntry>,
+    /// Lowercased word to the entries whose text or notes have it
+    words: BTreeMap<String, Vec<u32>>,
+    /// done.jsonl only: the last line indexed, which must still end at `len` for lines
+    /// added since to be indexed on their own
+    #[serde(default)]
+    tail: String,
+    /// Every suffix of every word, with the word, so the words containing a query word
+    /// are a range of it; built after loading
+    #[serde(skip)]
+    suffixes: BTreeSet<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct SearchIndex {
+    version: u32,
+    /// The live log first, then archives by name, then done.jsonl
+    segments: Vec<Segment>,
+}
+
+/// Kept in memory after the first search; `None` until then
+static INDEX: Mutex<Option<SearchIndex>> = Mutex::new(None);
+
+fn index_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("search_index.json"))
+}
+
+/// Lowercased runs of letters and digits; queries are split the same way
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Size and modification time, which say whether a segment is still current
+fn stamp(path: &Path) -> Option<(u64, Option<SystemTime>)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()))
+}
+
+impl Segment {
+    /// `len` and `modified` are taken before reading the file, so an append while it's
+    /// read leaves the segment looking stale rather than current
+    fn new(
+        name: String,
+        path: PathBuf,
+        (len, modified): (u64, Option<SystemTime>),
+        entries: Vec<IndexedEntry>,
+    ) -> Self {
+        let mut segment = Self {
+            name,
+            path,
+            len,
+            modified,
+            entries: Vec::new(),
+            words: BTreeMap::new(),
+            tail: String::new(),
+            suffixes: BTreeSet::new(),
+        };
+        segment.add(entries);
+        segment
+    }
+
+    /// Append `entries` and index their words
+    fn add(&mut self, entries: Vec<IndexedEntry>) {
+        for entry in entries {
+            let i = self.entries.len() as u32;
+            let texts = std::iter::once(&entry.task.text).chain(entry.task.notes.iter().map(|n| &n.text));
+            let unique: HashSet<String> = texts.flat_map(|t| words(t)).collect();
+            for word in unique {
+                if !self.words.contains_key(&word) {
+                    add_suffixes(&mut self.suffixes, &word);
+                }
+                self.words.entry(word).or_default().push(i);
+            }
+            self.entries.push(entry);
+        }
+    }
+
+    fn is_current(&self) -> bool {
+        stamp(&self.path) == Some((self.len, self.modified))
+    }
+
+    /// Entries that could contain `query`: each of its words must be part of a word
+    /// in the entry. `None` when the query has no words to narrow by
+    fn candidates(&self, query: &str) -> Option<HashSet<u32>> {
+        let mut candidates: Option<HashSet<u32>> = None;
+        for word in words(query) {
+            let having: HashSet<u32> = self
+                .suffixes
+                .range((word.clone(), String::new())..)
+                .take_while(|(suffix, _)| suffix.starts_with(&word))
+                .filter_map(|(_, w)| self.words.get(w))
+                .flat_map(|ids| ids.iter().copied())
+                .collect();
+            candidates = Some(match candidates {
+                Some(c) => c.intersection(&having).copied().collect(),
+                None => having,
+            });
+        }
+        candidates
+    }
+}
+
+fn add_suffixes(suffixes: &mut BTreeSet<(String, String)>, word: &str) {
+    for (i, _) in word.char_indices() {
+        suffixes.insert((word[i..].to_string(), word.to_string()));
+    }
+}
+
+/// A segment for the done log or an archive at `path`
+fn log_segment(name: String, path: PathBuf, format: DoneFormat) -> Result<Segment, String> {
+    let stamp = stamp(&path).unwrap_or_default();
+    let content = done::read_archive_file(&path)?;
+    let entries = done::parse_entries(&content, format)
+        .into_iter()
+        .map(|entry| IndexedEntry {
+            date: entry.date,
+            time: entry.time,
+            task: entry.task,
+            shadowed: false,
+        })
+        .collect();
+    Ok(Segment::new(name, path, stamp, entries))
+}
+
+/// A segment for done.jsonl at `path`
+fn records_segment(path: PathBuf) -> Result<Segment, String> {
+    let mut segment = Segment::new("done.jsonl".to_string(), path, (0, None), Vec::new());
+    if !segment.append_records()? {
+        return Err("done.jsonl changed while it was read".to_string());
+    }
+    Ok(segment)
+}
+
+impl Segment {
+    /// Index the lines added to done.jsonl since it was last indexed, which is all of
+    /// it for a new segment. `false` when it was rewritten rather than added to, so the
+    /// segment has to be built again
+    fn append_records(&mut self) -> Result<bool, String> {
+        let Some((len, modified)) = stamp(&self.path) else {
+            return Ok(false);
+        };
+        let grown = len > self.len || self.len == 0;
+        let Some(start) = self.len.checked_sub(self.tail.len() as u64).filter(|_| grown) else {
+            return Ok(false);
+        };
+        let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(start)).map_err(|e| e.to_string())?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).map_err(|e| e.to_string())?;
+        let Some(added) = content.strip_prefix(self.tail.as_str()) else {
+            return Ok(false);
+        };
+        // A line still being written is left for next time
+        let complete = added.rfind('\n').map_or(0, |i| i + 1);
+        let added = &added[..complete];
+        let entries = done::parse_records(added)
+            .into_iter()
+            .map(|record| {
+                let m = record.into_match();
+                IndexedEntry {
+                    date: m.date,
+                    time: m.time,
+                    task: m.task,
+                    shadowed: false,
+                }
+            })
+            .collect();
+        self.add(entries);
+        if let Some(body) = added.strip_suffix('\n') {
+            self.tail = added[body.rfind('\n').map_or(0, |i| i + 1)..].to_string();
+        }
+        self.len += added.len() as u64;
+        self.modified = modified;
+        Ok(true)
+    }
+}
+
+/// The files the index covers: name, path and format (`None` for done.jsonl)
+fn sources() -> Result<Vec<(String, PathBuf, Option<DoneFormat>)>, String> {
+    let live = done::log_file()?;
+    let live_name = live.file_name().and_then(|n| n.to_str()).unwrap_or("done.md").to_string();
+    let mut sources = vec![(live_name, live, Some(done::log_format()))];
+    let mut archives = done::archive_paths()?;
+    archives.sort();
+    // Project archives only repeat entries of the main archive
+    for (name, path) in archives.into_iter().filter(|(name, _)| !done::is_project_archive(name)) {
+        let format = DoneFormat::of_file(&name).unwrap_or_default();
+        sources.push((name, path, Some(format)));
+    }
+    sources.push(("done.jsonl".to_string(), get_done_records_file()?, None));
+    Ok(sources)
+}
+
+impl SearchIndex {
+    fn load() -> Self {
+        let Ok(content) = index_file().and_then(|path| fs::read_to_string(path).map_err(|e| e.to_string())) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<SearchIndex>(&content) {
+            Ok(mut index) if index.version == VERSION => {
+                for segment in &mut index.segments {
+                    for word in segment.words.keys() {
+                        add_suffixes(&mut segment.suffixes, word);
+                    }
+                }
+                index
+            }
+            Ok(_) => Self::default(),
+            Err(e) => {
+                eprintln!("Warning: Rebuilding unreadable search index: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(index_file()?, content).map_err(|e| e.to_string())
+    }
+
+    /// Re-index whichever files changed since they were indexed, drop files that
+    /// are gone, and save if anything changed
+    fn refresh(&mut self) -> Result<(), String> {
+        let mut changed = self.version != VERSION;
+        self.version = VERSION;
+        let mut old: Vec<Segment> = std::mem::take(&mut self.segments);
+        for (name, path, format) in sources()? {
+            if !path.exists() {
+                continue;
+            }
+            if let Some(i) = old.iter().position(|s| s.path == path && s.is_current()) {
+                let mut segment = old.swap_remove(i);
+                segment.name = name;
+                self.segments.push(segment);
+                continue;
+            }
+            changed = true;
+            // done.jsonl is only ever added to, but for a completion taken back out
+            if let Some(i) = old.iter().position(|s| s.path == path && format.is_none()) {
+                let mut segment = old.swap_remove(i);
+                match segment.append_records() {
+                    Ok(true) => {
+                        self.segments.push(segment);
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Warning: Failed to index the end of {}: {}", name, e),
+                }
+            }
+            let segment = match format {
+                Some(format) => log_segment(name.clone(), path, format),
+                None => records_segment(path),
+            };
+            match segment {
+                Ok(segment) => self.segments.push(segment),
+                Err(e) => eprintln!("Warning: Failed to index {}: {}", name, e),
+            }
+        }
+        if !old.is_empty() {
+            changed = true;
+        }
+        if !changed {
+            return Ok(());
+        }
+
+        // Like `history`, done.jsonl only adds what no log has any more. It also knows what
+        // the logs can't hold, like the project, which their entries take from it
+        let (mut logs, mut records): (Vec<&mut Segment>, Vec<&mut Segment>) =
+            self.segments.iter_mut().partition(|s| s.name != "done.jsonl");
+        let mut by_text: HashMap<(String, String), usize> = HashMap::new();
+        if let Some(records) = records.first_mut() {
+            for (i, entry) in records.entries.iter_mut().enumerate() {
+                entry.shadowed = false;
+                for text in done::logged_texts(&entry.task) {
+                    by_text.insert((entry.date.clone(), text), i);
+                }
+            }
+        }
+        for entry in logs.iter_mut().flat_map(|s| s.entries.iter_mut()) {
+            let Some(&i) = by_text.get(&(entry.date.clone(), entry.task.text.clone())) else {
+                continue;
+            };
+            let Some(record) = records.first_mut().and_then(|r| r.entries.get_mut(i)) else {
+                continue;
+            };
+            record.shadowed = true;
+            let task = &mut entry.task;
+            task.project = task.project.take().or_else(|| record.task.project.clone());
+            task.priority = task.priority.or(record.task.priority);
+            task.created = task.created.or(record.task.created);
+            task.due = task.due.or(record.task.due);
+        }
+        self.save()
+    }
+
+    /// Entries containing `query`, or matching `pattern` when it's set; a pattern
+    /// can't be narrowed down by word, so then every entry is checked
+    fn search(&self, query: &str, pattern: Option<&Regex>, range: DateRange) -> Vec<DoneMatch> {
+        let mut found = Vec::new();
+        for segment in &self.segments {
+            let candidates = if pattern.is_some() { None } else { segment.candidates(query) };
+            let ids: Vec<u32> = match candidates {
+                Some(candidates) => {
+                    let mut ids: Vec<u32> = candidates.into_iter().collect();
+                    ids.sort_unstable();
+                    ids
+                }
+                None => (0..segment.entries.len() as u32).collect(),
+            };
+            for entry in ids.into_iter().filter_map(|i| segment.entries.get(i as usize)) {
+                let task = &entry.task;
+                let hit = match pattern {
+                    Some(pattern) => {
+                        pattern.is_match(&task.text) || task.notes.iter().any(|n| pattern.is_match(&n.text))
+                    }
+                    None => {
+                        task.text.to_lowercase().contains(query)
+                            || task.notes.iter().any(|n| n.text.to_lowercase().contains(query))
+                    }
+                };
+                let in_range = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").is_ok_and(|d| range.contains(d));
+                if hit && in_range && !entry.shadowed {
+                    found.push(DoneMatch {
+                        source: segment.name.clone(),
+                        date: entry.date.clone(),
+                        time: entry.time.clone(),
+                        task: task.clone(),
+                    });
+                }
+            }
+        }
+        found.sort_by(|a, b| a.date.cmp(&b.date));
+        found.reverse();
+        found
+    }
+}
+
+/// Forget the index in memory, after switching to a profile with a data directory of its own
+pub fn reset() {
+    *INDEX.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Bring the index up to date with the files on disk, loading it first if needed
+pub fn update() -> Result<(), String> {
+    let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    index.get_or_insert_with(SearchIndex::load).refresh()
+}
+
+/// `update` on another thread, after a completion or archive, so the next search is quick
+pub fn update_in_background() {
+    std::thread::spawn(|| {
+        if let Err(e) = update() {
+            eprintln!("Warning: Failed to update the search index: {}", e);
+        }
+    });
+}
+
+/// Completed tasks whose text or notes contain `query` (ignoring case), or with
+/// `regex` match it as a pattern, from the done log, every archive and done.jsonl,
+/// newest first
+pub fn search(query: &str, range: DateRange, regex: bool) -> Result<Vec<DoneMatch>, String> {
+    let pattern = if regex { Some(compile_regex(query.trim())?) } else { None };
+    let query = query.trim().to_lowercase();
+    let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    let index = index.get_or_insert_with(SearchIndex::load);
+    index.refresh()?;
+    Ok(index.search(&query, pattern.as_ref(), range))
+}
+
+/// Every completion in `range`, oldest first, like `done::history` but from the index
+/// rather than re-reading every archive
+pub fn history(range: DateRange) -> Result<Vec<DoneMatch>, String> {
+    let mut index = INDEX.lock().unwrap_or_else(|e| e.into_inner());
+    let index = index.get_or_insert_with(SearchIndex::load);
+    index.refresh()?;
+    let mut found: Vec<DoneMatch> = index
+        .segments
+        .iter()
+        .flat_map(|segment| segment.entries.iter().map(move |entry| (segment, entry)))
+        .filter(|(_, entry)| {
+            !entry.shadowed && NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").is_ok_and(|d| range.contains(d))
+        })
+        .map(|(segment, entry)| DoneMatch {
+            source: segment.name.clone(),
+            date: entry.date.clone(),
+            time: entry.time.clone(),
+            task: entry.task.clone(),
+        })
+        .collect();
+    found.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(found)
+}
@@ -0,0 +1,466 @@
+//! One typed surface over every setting: the whole config out, and partial updates
+//! in that are checked field by field before anything is saved or applied. Edits
+//! made to config.json (or config.toml) by hand are picked up while the app runs
+
+use chrono::Local;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use toml_edit::{DocumentMut, Item, Table};
+use std::fs;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    apply_autostart, defaults, done, focus_bar, get_config_candidates, get_config_file, hotkeys, is_toml, locale,
+    parse_config, query, save_config, snapshots, tray, window, AppConfig, AppState,
+};
+
+/// Layout of config.json this version writes. Renaming or restructuring a setting bumps
+/// it and adds a step to `MIGRATIONS` that rewrites older files to match
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrades from each version to the next: the first turns a version 0 file (written
+/// before config.json had a version) into version 1, and so on
+const MIGRATIONS: [fn(&mut Map<String, Value>); CONFIG_VERSION as usize] = [fold_hotkey];
+
+/// Version 1: the single `hotkey` became the toggle entry of `hotkeys`
+fn fold_hotkey(config: &mut Map<String, Value>) {
+    let Some(hotkey) = config.remove("hotkey").filter(|h| !h.is_null()) else {
+        return;
+    };
+    let hotkeys = config
+        .entry("hotkeys")
+        .or_insert_with(|| serde_json::to_value(defaults::hotkeys()).unwrap_or_default());
+    if let Value::Object(hotkeys) = hotkeys {
+        hotkeys.insert("toggle".to_string(), hotkey);
+    }
+}
+
+/// Bring a parsed config.json up to `CONFIG_VERSION`; returns whether any step ran. A
+/// file from a newer version is read as it is, without the settings this one doesn't know
+pub fn migrate(config: &mut Value) -> Result<bool, String> {
+    let Value::Object(fields) = config else {
+        return Err("config.json must hold a JSON object".to_string());
+    };
+    let version = fields.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > CONFIG_VERSION as u64 {
+        eprintln!(
+            "Warning: config.json is from a newer version (layout {}); settings this one doesn't know are ignored",
+            version
+        );
+        return Ok(false);
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        step(fields);
+    }
+    fields.insert("version".to_string(), Value::from(CONFIG_VERSION));
+    Ok(version < CONFIG_VERSION as u64)
+}
+
+/// Bring `doc` in line with `fresh`, leaving the keys whose value didn't change as they
+/// are, comments and all. `old` and `new` are the same two tables as plain values
+fn update_table(doc: &mut Table, fresh: &Table, old: &Map<String, Value>, new: &Map<String, Value>) {
+    let gone: Vec<String> = old.keys().filter(|key| !new.contains_key(*key)).cloned().collect();
+    for key in gone {
+        doc.remove(&key);
+    }
+    for (key, item) in fresh.iter() {
+        if old.get(key) == new.get(key) {
+            continue;
+        }
+        match (doc.get_mut(key), item, old.get(key), new.get(key)) {
+            (Some(Item::Table(table)), Item::Table(item), Some(Value::Object(old)), Some(Value::Object(new))) => {
+                update_table(table, item, old, new)
+            }
+            (Some(existing), ..) => {
+                // The comment after a value or above a table goes with the new one
+                let value_decor = existing.as_value().map(|v| v.decor().clone());
+                let table_decor = existing.as_table().map(|t| t.decor().clone());
+                *existing = item.clone();
+                if let (Some(decor), Some(value)) = (value_decor, existing.as_value_mut()) {
+                    *value.decor_mut() = decor;
+                }
+                if let (Some(decor), Some(table)) = (table_decor, existing.as_table_mut()) {
+                    *table.decor_mut() = decor;
+                }
+            }
+            (None, ..) => {
+                doc.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+/// `config` as TOML. Given the file's current contents, settings that didn't change keep
+/// their place and comments
+pub fn to_toml(config: &AppConfig, current: Option<&str>) -> Result<String, String> {
+    let fresh = toml::to_string_pretty(config).map_err(|e| e.to_string())?;
+    let Some(mut doc) = current.and_then(|c| c.parse::<DocumentMut>().ok()) else {
+        return Ok(fresh);
+    };
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (toml::from_str::<Value>(&doc.to_string()), toml::from_str::<Value>(&fresh))
+    else {
+        return Ok(fresh);
+    };
+    let fresh: DocumentMut = fresh.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+    update_table(&mut doc, &fresh, &old, &new);
+    Ok(doc.to_string())
+}
+
+/// Settings that stay on this machine when settings are exported or imported: where
+/// windows sat on its screens. Anything secret, like an access token, belongs here too
+const NOT_EXPORTED: &[&str] = &["window_bounds", "screen_positions"];
+
+/// Settings only macOS can use, skipped when importing elsewhere
+const MACOS_ONLY: &[&str] = &["panel_mode", "double_tap"];
+
+/// Editors save in several steps (truncate, write, rename); wait this long after the
+/// last change before reading the file
+const SETTLE: Duration = Duration::from_millis(250);
+
+/// Watch on the active profile's data directory. Replacing it stops the old watch,
+/// whose thread ends once its events stop coming
+static WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+
+/// Apply `patch` to `target` as a JSON merge patch: objects merge key by key and
+/// `null` removes a key, which puts a top-level setting back to its default
+fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Checks serde can't make: values that parse but can't be used
+fn validate(config: &AppConfig) -> Result<(), String> {
+    hotkeys::validate_all(&config.hotkeys).map_err(|e| format!("hotkeys: {}", e))?;
+    config.done_log.validate().map_err(|e| format!("done_log: {}", e))?;
+    config.theme.validate().map_err(|e| format!("theme: {}", e))?;
+    locale::validate_format(&config.date_format).map_err(|e| format!("date_format: {}", e))?;
+    if let Some(name) = &config.locale {
+        locale::parse_locale(name).map_err(|e| format!("locale: {}", e))?;
+    }
+    let today = Local::now().date_naive();
+    for filter in &config.saved_filters {
+        query::Query::parse(&filter.query, today)
+            .map_err(|e| format!("saved_filters: \"{}\": {}", filter.name, e))?;
+    }
+    if cfg!(not(target_os = "macos")) {
+        if config.panel_mode {
+            return Err("panel_mode: Panel mode is only available on macOS".to_string());
+        }
+        if config.double_tap.is_some() {
+            return Err("double_tap: Double-tap activation is only available on macOS".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// `current` with `patch` applied. Each field is checked on its own first, so an
+/// error names the setting that's wrong
+fn patched(current: &AppConfig, patch: &Value) -> Result<AppConfig, String> {
+    let Value::Object(fields) = patch else {
+        return Err("Settings must be an object of fields to change".to_string());
+    };
+    let base = serde_json::to_value(current).map_err(|e| e.to_string())?;
+    let known = base.as_object().cloned().unwrap_or_default();
+    let mut merged = base.clone();
+    for (key, value) in fields {
+        if !known.contains_key(key) {
+            return Err(format!("Unknown setting: {}", key));
+        }
+        if key == "version" {
+            return Err("version: Set by the app when it upgrades config.json".to_string());
+        }
+        let mut single = base.clone();
+        merge(&mut single, &Value::Object(Map::from_iter([(key.clone(), value.clone())])));
+        serde_json::from_value::<AppConfig>(single).map_err(|e| format!("{}: {}", key, e))?;
+        merge(&mut merged, &Value::Object(Map::from_iter([(key.clone(), value.clone())])));
+    }
+    let mut config: AppConfig = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+    config.window_appearance.opacity = config.window_appearance.opacity.clamp(0.0, 1.0);
+    validate(&config)?;
+    Ok(config)
+}
+
+/// Top-level settings whose values differ between `old` and `new`
+fn changed_fields(old: &AppConfig, new: &AppConfig) -> Vec<String> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) = (serde_json::to_value(old), serde_json::to_value(new)) else {
+        return Vec::new();
+    };
+    new.iter().filter(|(key, value)| old.get(*key) != Some(*value)).map(|(key, _)| key.clone()).collect()
+}
+
+/// Bring everything that caches a setting up to date after the config went from
+/// `old` to `new`, and tell the UI with "config-changed". Settings read on each use,
+/// like reminders and schedules, need nothing here
+pub fn apply_changes(app: &AppHandle, old: &AppConfig, new: &AppConfig) {
+    let changed = changed_fields(old, new);
+    if changed.is_empty() {
+        return;
+    }
+    // Shortcuts, windows and the panel all want the main thread, and edits to config.json
+    // arrive on the watcher's
+    let handle = app.clone();
+    let new = new.clone();
+    let _ = app.run_on_main_thread(move || apply_fields(&handle, changed, &new));
+}
+
+/// The part of `apply_changes` that runs on the main thread
+fn apply_fields(app: &AppHandle, changed: Vec<String>, new: &AppConfig) {
+    for field in changed {
+        match field.as_str() {
+            "hotkeys" => hotkeys::rebind_all(app, &new.hotkeys),
+            "done_log" => done::configure(&new.done_log),
+            "date_format" | "locale" => locale::configure(&new.date_format, new.locale.as_deref()),
+            "tray_click" => tray::apply_click_config(app),
+            "pinned" => {
+                window::apply_pinned(app);
+                let _ = app.emit("pinned-changed", new.pinned);
+            }
+            "window_appearance" => window::apply_appearance(app),
+            "theme" => window::apply_theme(app),
+            "launch_at_login" => {
+                if let Err(e) = apply_autostart(app) {
+                    eprintln!("Warning: {}", e);
+                }
+            }
+            "focus_bar" => {
+                focus_bar::apply(app);
+                focus_bar::refresh(app);
+            }
+            #[cfg(target_os = "macos")]
+            "panel_mode" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    if let Err(e) = crate::panel::set_enabled(&window, new.panel_mode) {
+                        eprintln!("Warning: Failed to switch panel mode: {}", e);
+                    }
+                }
+            }
+            #[cfg(target_os = "macos")]
+            "double_tap" => {
+                if let Err(e) = crate::modifier_tap::set_key(app, new.double_tap) {
+                    eprintln!("Warning: Double-tap activation unavailable: {}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+    // Cheap, and covers the menu, icon style, limits, overdue badge and daily goal
+    tray::refresh(app);
+    let _ = app.emit("config-changed", new);
+}
+
+/// Re-read config.json after it changed on disk and apply whatever differs from the
+/// settings in use. Our own saves read back unchanged and do nothing. A file that
+/// doesn't parse is left for the next edit to fix
+fn reload(app: &AppHandle) {
+    let Ok(path) = get_config_file() else {
+        return;
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        // Deleted, or mid-rename; a later event brings it back
+        Err(_) => return,
+    };
+    let new = match parse_config(&content, is_toml(&path)) {
+        Ok((config, migrated)) => {
+            // Someone pasted in an old config; store it in the current layout
+            if migrated {
+                if let Err(e) = save_config(&config) {
+                    eprintln!("Warning: Failed to save the upgraded {}: {}", path.display(), e);
+                }
+            }
+            config
+        }
+        Err(e) => {
+            eprintln!("Warning: Ignoring edit to {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let old = {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *config, new.clone())
+    };
+    apply_changes(app, &old, &new);
+}
+
+/// Watch config.json and config.toml (or `TASK_LOG_CONFIG`) for edits made outside the app.
+/// The directory is watched rather than the files, since editors often replace a file when
+/// saving. Called again after switching profiles
+pub fn watch(app: AppHandle) {
+    let Ok(files) = get_config_candidates() else {
+        return;
+    };
+    let Some(dir) = files.first().and_then(|file| file.parent()).map(|dir| dir.to_path_buf()) else {
+        return;
+    };
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Warning: Failed to watch config.json: {}", e);
+            return;
+        }
+    };
+    let watched = fs::create_dir_all(&dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| watcher.watch(&dir, RecursiveMode::NonRecursive).map_err(|e| e.to_string()));
+    if let Err(e) = watched {
+        eprintln!("Warning: Failed to watch config.json: {}", e);
+        return;
+    }
+
+    *WATCHER.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+
+    thread::spawn(move || {
+        while let Ok(event) = rx.recv() {
+            let event: notify::Result<notify::Event> = event;
+            if !event.is_ok_and(|e| e.paths.iter().any(|path| files.contains(path))) {
+                continue;
+            }
+            while rx.recv_timeout(SETTLE).is_ok() {}
+            reload(&app);
+        }
+    });
+}
+
+/// Every setting, as saved in config.json or config.toml
+#[tauri::command]
+pub fn get_config(state: tauri::State<AppState>) -> AppConfig {
+    state.config.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Change the settings named in `patch`, e.g. {"daily_goal": 5, "tray_icon_style": "progress"},
+/// leaving the rest alone; `null` resets a setting to its default. Nothing is saved
+/// unless every field is valid. Returns the updated config
+#[tauri::command]
+pub fn update_config(patch: Value, app: AppHandle) -> Result<AppConfig, String> {
+    let (old, new) = {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        let new = patched(&config, &patch)?;
+        save_config(&new)?;
+        (std::mem::replace(&mut *config, new.clone()), new)
+    };
+    apply_changes(&app, &old, &new);
+    Ok(new)
+}
+
+/// What `import_settings` did with the settings in a file
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportReport {
+    /// Settings that now hold the file's value
+    pub applied: Vec<String>,
+    /// Settings this version doesn't know, that stay per machine, or that only macOS can use
+    pub skipped: Vec<String>,
+}
+
+/// Write every setting but the per-machine ones to `path`, as TOML for a .toml path and JSON
+/// otherwise, to carry a setup to another machine or share it
+#[tauri::command]
+pub fn export_settings(path: String, state: tauri::State<AppState>) -> Result<(), String> {
+    let path = done::expand_path(&path)?;
+    if !path.is_absolute() {
+        return Err(format!("Export path must be absolute or start with ~/: {}", path.display()));
+    }
+    let config = state.config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let Value::Object(mut fields) = serde_json::to_value(&config).map_err(|e| e.to_string())? else {
+        return Err("Settings didn't serialize to an object".to_string());
+    };
+    for key in NOT_EXPORTED {
+        fields.remove(*key);
+    }
+    let content = if is_toml(&path) {
+        toml::to_string_pretty(&fields).map_err(|e| e.to_string())?
+    } else {
+        serde_json::to_string_pretty(&fields).map_err(|e| e.to_string())?
+    };
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Take the settings in a file written by `export_settings` (or a config.json/config.toml
+/// from another machine) in place of the current ones. Older layouts are upgraded first, and
+/// settings the file leaves out keep their values. Nothing changes unless every setting is valid
+#[tauri::command]
+pub fn import_settings(path: String, app: AppHandle) -> Result<ImportReport, String> {
+    let path = done::expand_path(&path)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    snapshots::create(&app, "Before settings import").map_err(|e| format!("Failed to take a snapshot first: {}", e))?;
+    let mut value: Value = if is_toml(&path) {
+        toml::from_str(&content).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(&content).map_err(|e| e.to_string())?
+    };
+    migrate(&mut value)?;
+    let Value::Object(mut fields) = value else {
+        return Err("Settings file must hold an object".to_string());
+    };
+    fields.remove("version");
+
+    let (old, new, report) = {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        let known = match serde_json::to_value(&*config).map_err(|e| e.to_string())? {
+            Value::Object(known) => known,
+            _ => Map::new(),
+        };
+        let (patch, skipped): (Map<String, Value>, Map<String, Value>) = fields
+            .into_iter()
+            .partition(|(key, _)| {
+                known.contains_key(key)
+                    && !NOT_EXPORTED.contains(&key.as_str())
+                    && (cfg!(target_os = "macos") || !MACOS_ONLY.contains(&key.as_str()))
+            });
+        let report = ImportReport {
+            applied: patch.keys().cloned().collect(),
+            skipped: skipped.keys().cloned().collect(),
+        };
+        let new = patched(&config, &Value::Object(patch))?;
+        save_config(&new)?;
+        (std::mem::replace(&mut *config, new.clone()), new, report)
+    };
+    apply_changes(&app, &old, &new);
+    Ok(report)
+}
+
+/// Put back settings kept by a snapshot, upgrading an older layout first. Where the
+/// window sits on screen stays as it is
+pub fn restore(app: &AppHandle, mut value: Value) -> Result<(), String> {
+    migrate(&mut value)?;
+    let (old, new) = {
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        let current = serde_json::to_value(&*config).map_err(|e| e.to_string())?;
+        if let Value::Object(fields) = &mut value {
+            for key in NOT_EXPORTED {
+                if let Some(kept) = current.get(*key) {
+                    fields.insert(key.to_string(), kept.clone());
+                }
+            }
+        }
+        let new: AppConfig = serde_json::from_value(value).map_err(|e| format!("Invalid settings: {}", e))?;
+        save_config(&new)?;
+        (std::mem::replace(&mut *config, new.clone()), new)
+    };
+    apply_changes(app, &old, &new);
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Named restore points: the task lists and settings as they were at one moment, kept
+//! in the data directory's snapshots folder as a JSON file each. One is taken on its
+//! own before a settings import and before restoring another
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::audit::Source;
+use crate::{get_tasks_dir, notify_tasks_changed, save_tasks, settings, undo, AppState, TaskState};
+
+/// A snapshot file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Snapshot {
+    label: String,
+    created: NaiveDateTime,
+    tasks: TaskState,
+    /// The config as it was saved, upgraded on restore if its layout has changed since
+    config: Value,
+}
+
+/// A snapshot without its contents, for listing
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotInfo {
+    /// File name without `.json`, e.g. "2026-03-02_170000"
+    pub id: String,
+    pub label: String,
+    pub created: NaiveDateTime,
+    /// Tasks in current and on the shelf
+    pub tasks: usize,
+}
+
+impl SnapshotInfo {
+    fn of(id: String, snapshot: &Snapshot) -> Self {
+        Self {
+            id,
+            label: snapshot.label.clone(),
+            created: snapshot.created,
+            tasks: snapshot.tasks.current.len() + snapshot.tasks.shelf.len(),
+        }
+    }
+}
+
+fn snapshots_dir() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("snapshots"))
+}
+
+/// Path of the snapshot `id`; ids are file names of our own making, so anything else is refused
+fn snapshot_file(id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("No snapshot {}", id));
+    }
+    Ok(snapshots_dir()?.join(format!("{}.json", id)))
+}
+
+fn read(id: &str) -> Result<Snapshot, String> {
+    let path = snapshot_file(id)?;
+    let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read snapshot {}: {}", id, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse snapshot {}: {}", id, e))
+}
+
+/// Save the task lists and settings in use now as a snapshot called `label`
+pub fn create(app: &AppHandle, label: &str) -> Result<SnapshotInfo, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("Snapshot label is empty".to_string());
+    }
+    let state = app.state::<AppState>();
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let config = state.config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let created = Local::now().naive_local();
+    let snapshot = Snapshot {
+        label: label.to_string(),
+        created,
+        tasks,
+        config: serde_json::to_value(&config).map_err(|e| e.to_string())?,
+    };
+
+    let dir = snapshots_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    // Two in the same second get a counter
+    let stamp = created.format("%Y-%m-%d_%H%M%S").to_string();
+    let mut id = stamp.clone();
+    let mut n = 1;
+    while dir.join(format!("{}.json", id)).exists() {
+        n += 1;
+        id = format!("{}-{}", stamp, n);
+    }
+    let content = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", id)), content).map_err(|e| e.to_string())?;
+    Ok(SnapshotInfo::of(id, &snapshot))
+}
+
+/// When the last snapshot taken at or before `at` was taken, and its task lists
+pub fn latest_before(at: NaiveDateTime) -> Result<Option<(NaiveDateTime, TaskState)>, String> {
+    let Some(info) = list_snapshots()?.into_iter().find(|s| s.created <= at) else {
+        return Ok(None);
+    };
+    Ok(Some((info.created, read(&info.id)?.tasks)))
+}
+
+#[tauri::command]
+pub fn create_snapshot(label: String, app: AppHandle) -> Result<SnapshotInfo, String> {
+    create(&app, &label)
+}
+
+/// Every snapshot, newest first; unreadable files are skipped
+#[tauri::command]
+pub fn list_snapshots() -> Result<Vec<SnapshotInfo>, String> {
+    let dir = snapshots_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for item in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let path = item.map_err(|e| e.to_string())?.path();
+        let Some(id) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".json")) else {
+            continue;
+        };
+        match read(id) {
+            Ok(snapshot) => snapshots.push(SnapshotInfo::of(id.to_string(), &snapshot)),
+            Err(e) => eprintln!("Warning: {}", e),
+        }
+    }
+    snapshots.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.id.cmp(&a.id)));
+    Ok(snapshots)
+}
+
+/// Put the task lists and settings back as snapshot `id` has them, after taking a
+/// snapshot of the ones in use so this can be reversed too. Undo also takes back the lists
+#[tauri::command]
+pub fn restore_snapshot(id: String, app: AppHandle) -> Result<(), String> {
+    let snapshot = read(&id)?;
+    create(&app, &format!("Before restoring {}", snapshot.label))
+        .map_err(|e| format!("Failed to take a snapshot first: {}", e))?;
+    settings::restore(&app, snapshot.config)?;
+    {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut restored = TaskState {
+            revision: tasks.revision,
+            ..snapshot.tasks
+        };
+        save_tasks(&mut restored)?;
+        *tasks = restored;
+        undo::record(&app, Source::Ui, "Restore Snapshot", &tasks);
+    }
+    notify_tasks_changed(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_snapshot(id: String) -> Result<(), String> {
+    let path = snapshot_file(&id)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete snapshot {}: {}", id, e))
+}
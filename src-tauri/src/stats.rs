@@ -0,0 +1,680 @@
+//! Numbers computed from the done history for charts and reviews
+
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use crate::done::{self, DateRange, DoneMatch};
+use crate::scheduler::WorkWeek;
+use crate::{report, search_index, Task, TaskState};
+
+/// Size of the buckets completions are counted in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Day,
+    /// Weeks start on the configured `week_start`
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// First day of the bucket `date` falls in, with weeks starting on `first_day`
+    fn bucket(self, date: NaiveDate, first_day: Weekday) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => report::week_start(date, first_day),
+            Granularity::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// First day of the bucket after the one starting at `start`
+    fn next(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Granularity::Day => start.succ_opt(),
+            Granularity::Week => start.checked_add_days(chrono::Days::new(7)),
+            Granularity::Month => start.checked_add_months(chrono::Months::new(1)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Bucket {
+    /// First day of the day, week or month
+    pub start: NaiveDate,
+    pub completed: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Stats {
+    pub granularity: Granularity,
+    pub total: usize,
+    /// Every bucket from the start of the range to its end, empty ones included
+    pub buckets: Vec<Bucket>,
+}
+
+/// Date of a completion in the history
+pub fn day_of(m: &DoneMatch) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&m.date, "%Y-%m-%d").ok()
+}
+
+/// Completions in `range` counted per `granularity`, weeks starting on `first_day`.
+/// An open start begins at the oldest completion, an open end at today
+pub fn get_stats(range: DateRange, granularity: Granularity, first_day: Weekday) -> Result<Stats, String> {
+    let completed = search_index::history(range)?;
+    let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for day in completed.iter().filter_map(day_of) {
+        *counts.entry(granularity.bucket(day, first_day)).or_default() += 1;
+    }
+
+    let first = range.from.or_else(|| completed.iter().find_map(day_of));
+    let last = range.to.unwrap_or_else(|| Local::now().date_naive());
+    let mut buckets = Vec::new();
+    let mut start = first.map(|first| granularity.bucket(first, first_day));
+    while let Some(at) = start.filter(|at| *at <= last) {
+        buckets.push(Bucket {
+            start: at,
+            completed: counts.get(&at).copied().unwrap_or(0),
+        });
+        start = granularity.next(at);
+    }
+
+    Ok(Stats {
+        granularity,
+        total: completed.len(),
+        buckets,
+    })
+}
+
+/// One year of completions a day at a time, for a contribution-graph view
+#[derive(Debug, Serialize, Clone)]
+pub struct Heatmap {
+    pub year: i32,
+    /// Days Jan 1 is after the first day of the week, to line the first column up
+    pub first_weekday: u32,
+    /// Completions on each day from Jan 1, 365 or 366 of them
+    pub counts: Vec<usize>,
+    /// The highest count, to scale colors by
+    pub max: usize,
+}
+
+pub fn get_heatmap(year: i32, first_day: Weekday) -> Result<Heatmap, String> {
+    let first = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| format!("Invalid year: {}", year))?;
+    let last = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| format!("Invalid year: {}", year))?;
+    let mut counts = vec![0; last.ordinal() as usize];
+    let completed = search_index::history(DateRange {
+        from: Some(first),
+        to: Some(last),
+    })?;
+    for day in completed.iter().filter_map(day_of) {
+        counts[day.ordinal0() as usize] += 1;
+    }
+    Ok(Heatmap {
+        year,
+        first_weekday: first.weekday().days_since(first_day),
+        max: counts.iter().copied().max().unwrap_or(0),
+        counts,
+    })
+}
+
+/// Hours from creation to completion over the completions that know both
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LeadTime {
+    /// How many completions this covers
+    pub count: usize,
+    pub average_hours: f64,
+    pub median_hours: f64,
+}
+
+/// Open tasks of one age band
+#[derive(Debug, Serialize, Clone)]
+pub struct AgeBand {
+    /// e.g. "1-3 days"
+    pub label: &'static str,
+    pub open: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FlowMetrics {
+    pub lead_time: LeadTime,
+    /// Open tasks in `current` and on the shelf by age, youngest band first
+    pub wip_age: Vec<AgeBand>,
+    /// Open tasks with no creation time, added before it was kept
+    pub wip_unknown_age: usize,
+    /// Completions per week over the range
+    pub weekly_throughput: Vec<Bucket>,
+    pub average_weekly_throughput: f64,
+}
+
+/// Upper bounds in days of the WIP age bands; the last band has none
+const AGE_BANDS: [(&str, Option<i64>); 5] = [
+    ("under a day", Some(1)),
+    ("1-3 days", Some(3)),
+    ("3-7 days", Some(7)),
+    ("1-4 weeks", Some(28)),
+    ("over 4 weeks", None),
+];
+
+fn lead_time(hours: &mut [f64]) -> LeadTime {
+    if hours.is_empty() {
+        return LeadTime::default();
+    }
+    hours.sort_by(f64::total_cmp);
+    let mid = hours.len() / 2;
+    let median = if hours.len().is_multiple_of(2) {
+        (hours[mid - 1] + hours[mid]) / 2.0
+    } else {
+        hours[mid]
+    };
+    LeadTime {
+        count: hours.len(),
+        average_hours: hours.iter().sum::<f64>() / hours.len() as f64,
+        median_hours: median,
+    }
+}
+
+/// Lead time of the completions in `range` (from done.jsonl, the only log that keeps
+/// creation times), the age of what's open now, and weekly throughput (weeks starting on `first_day`)
+pub fn get_flow_metrics(
+    state: &TaskState,
+    range: DateRange,
+    first_day: Weekday,
+    now: NaiveDateTime,
+) -> Result<FlowMetrics, String> {
+    let mut hours: Vec<f64> = done::read_records()?
+        .into_iter()
+        .filter(|r| range.contains(r.completed_at.date()))
+        .filter_map(|r| Some((r.completed_at - r.created?).num_minutes() as f64 / 60.0))
+        .filter(|h| *h >= 0.0)
+        .collect();
+
+    let mut wip_age: Vec<AgeBand> = AGE_BANDS.iter().map(|(label, _)| AgeBand { label, open: 0 }).collect();
+    let mut wip_unknown_age = 0;
+    for task in state.current.iter().chain(&state.shelf) {
+        let Some(created) = task.created else {
+            wip_unknown_age += 1;
+            continue;
+        };
+        let days = (now - created).num_days();
+        let band = AGE_BANDS.iter().position(|(_, max)| max.is_none_or(|max| days < max)).unwrap_or(0);
+        wip_age[band].open += 1;
+    }
+
+    let weekly = get_stats(range, Granularity::Week, first_day)?;
+    let weeks = weekly.buckets.len().max(1) as f64;
+    Ok(FlowMetrics {
+        lead_time: lead_time(&mut hours),
+        wip_age,
+        wip_unknown_age,
+        average_weekly_throughput: weekly.total as f64 / weeks,
+        weekly_throughput: weekly.buckets,
+    })
+}
+
+/// When in the day and week things get finished
+#[derive(Debug, Serialize, Clone)]
+pub struct CompletionPatterns {
+    pub total: usize,
+    /// Completions per hour of the day, 0 to 23; only those logged with a time count
+    pub by_hour: Vec<usize>,
+    /// Completions per weekday, starting with the configured `week_start`
+    pub by_weekday: Vec<usize>,
+    /// `by_hour` split by weekday: `grid[weekday][hour]`
+    pub grid: Vec<Vec<usize>>,
+    /// Completions logged without a time (or in a `done_time_format` without hours)
+    pub without_time: usize,
+}
+
+/// Time of day of a completion, if it was logged in a format that has one
+fn time_of(m: &DoneMatch) -> Option<NaiveTime> {
+    let time = m.time.as_deref()?;
+    ["%H:%M", "%H:%M:%S", "%I:%M %p", "%I:%M%p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())
+}
+
+/// Completions in `range` counted by hour of the day and day of the week, weeks starting on `first_day`
+pub fn get_completion_patterns(range: DateRange, first_day: Weekday) -> Result<CompletionPatterns, String> {
+    let completed = search_index::history(range)?;
+    let mut patterns = CompletionPatterns {
+        total: 0,
+        by_hour: vec![0; 24],
+        by_weekday: vec![0; 7],
+        grid: vec![vec![0; 24]; 7],
+        without_time: 0,
+    };
+    for m in &completed {
+        let Some(day) = day_of(m) else {
+            continue;
+        };
+        let weekday = day.weekday().days_since(first_day) as usize;
+        patterns.total += 1;
+        patterns.by_weekday[weekday] += 1;
+        match time_of(m) {
+            Some(time) => {
+                patterns.by_hour[time.hour() as usize] += 1;
+                patterns.grid[weekday][time.hour() as usize] += 1;
+            }
+            None => patterns.without_time += 1,
+        }
+    }
+    Ok(patterns)
+}
+
+/// Runs of days with at least one completion. Days off don't break a run, and today
+/// only does once it's over
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Streak {
+    /// Days in the run that's still going
+    pub current: usize,
+    pub longest: usize,
+    /// Whether today already has a completion
+    pub today_done: bool,
+}
+
+/// The streaks in `days` (those with completions) up to `today`
+pub fn streak(days: &BTreeSet<NaiveDate>, work_week: &WorkWeek, today: NaiveDate) -> Streak {
+    let mut streak = Streak {
+        today_done: days.contains(&today),
+        ..Default::default()
+    };
+    let Some(first) = days.first().copied() else {
+        return streak;
+    };
+    for day in first.iter_days().take_while(|day| *day <= today) {
+        if days.contains(&day) {
+            streak.current += 1;
+            streak.longest = streak.longest.max(streak.current);
+        } else if day < today && work_week.is_work_day(day) {
+            streak.current = 0;
+        }
+    }
+    streak
+}
+
+/// Days with completions in `completed`
+pub fn active_days(completed: &[DoneMatch]) -> BTreeSet<NaiveDate> {
+    completed.iter().filter_map(day_of).collect()
+}
+
+/// What `get_breakdown` groups by
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Dimension {
+    Tag,
+    Project,
+}
+
+/// Group name for tasks without a project
+const NO_PROJECT: &str = "no project";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BreakdownRow {
+    /// Tag (without `#`, or "untagged") or project (or "no project")
+    pub key: String,
+    pub completed: usize,
+    /// Share of the completions in the range, 0 to 100
+    pub completed_percent: f64,
+    pub open: usize,
+    /// Share of the open tasks, 0 to 100
+    pub open_percent: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Breakdown {
+    pub dimension: Dimension,
+    pub completed_total: usize,
+    pub open_total: usize,
+    /// Most completions first. A task with several tags counts toward each, so tag
+    /// shares can add up to more than 100
+    pub rows: Vec<BreakdownRow>,
+}
+
+fn keys(task: &Task, dimension: Dimension) -> Vec<String> {
+    match dimension {
+        Dimension::Tag => {
+            let tags = done::tags(&task.text);
+            if tags.is_empty() {
+                vec![report::UNTAGGED.to_string()]
+            } else {
+                tags
+            }
+        }
+        Dimension::Project => vec![report::project_of(task).unwrap_or_else(|| NO_PROJECT.to_string())],
+    }
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Completions in `range` and the open tasks, counted per tag or project
+pub fn get_breakdown(state: &TaskState, dimension: Dimension, range: DateRange) -> Result<Breakdown, String> {
+    let completed = search_index::history(range)?;
+    let open: Vec<&Task> = state.current.iter().chain(&state.shelf).collect();
+
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for m in &completed {
+        for key in keys(&m.task, dimension) {
+            counts.entry(key).or_default().0 += 1;
+        }
+    }
+    for task in &open {
+        for key in keys(task, dimension) {
+            counts.entry(key).or_default().1 += 1;
+        }
+    }
+
+    let mut rows: Vec<BreakdownRow> = counts
+        .into_iter()
+        .map(|(key, (done, open_count))| BreakdownRow {
+            key,
+            completed: done,
+            completed_percent: percent(done, completed.len()),
+            open: open_count,
+            open_percent: percent(open_count, open.len()),
+        })
+        .collect();
+    rows.sort_by(|a, b| b.completed.cmp(&a.completed).then_with(|| b.open.cmp(&a.open)));
+    Ok(Breakdown {
+        dimension,
+        completed_total: completed.len(),
+        open_total: open.len(),
+        rows,
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BurndownDay {
+    pub date: NaiveDate,
+    /// Tasks of the project added by the end of the day and not yet done
+    pub open: usize,
+    /// Tasks of the project done by the end of the day, since the range began
+    pub done: usize,
+}
+
+/// Open and done counts of `project`'s tasks at the end of each day in `range`. Built
+/// from when each task was created and completed; tasks from before creation times
+/// were kept count as open from the start. An open range runs from the project's
+/// first known date to today
+pub fn get_burndown(
+    state: &TaskState,
+    project: &str,
+    range: DateRange,
+    today: NaiveDate,
+) -> Result<Vec<BurndownDay>, String> {
+    let wanted = project.trim().to_lowercase();
+    let in_project = |task: &Task| report::project_of(task).is_some_and(|p| p.to_lowercase() == wanted);
+
+    // (created, completed) per task
+    let mut spans: Vec<(Option<NaiveDate>, Option<NaiveDate>)> = search_index::history(DateRange::default())?
+        .iter()
+        .filter(|m| in_project(&m.task))
+        .map(|m| (m.task.created.map(|c| c.date()), day_of(m)))
+        .collect();
+    spans.extend(
+        state
+            .current
+            .iter()
+            .chain(&state.shelf)
+            .filter(|task| in_project(task))
+            .map(|task| (task.created.map(|c| c.date()), None)),
+    );
+
+    let known = spans.iter().flat_map(|(created, completed)| [*created, *completed]).flatten();
+    let Some(first) = range.from.or_else(|| known.min()) else {
+        return Ok(Vec::new());
+    };
+    let last = range.to.unwrap_or(today);
+    Ok(first
+        .iter_days()
+        .take_while(|day| *day <= last)
+        .map(|day| {
+            let added = |created: &Option<NaiveDate>| created.is_none_or(|c| c <= day);
+            let done_by = |completed: &Option<NaiveDate>| completed.is_some_and(|c| c <= day);
+            BurndownDay {
+                date: day,
+                open: spans.iter().filter(|(c, d)| added(c) && !done_by(d)).count(),
+                done: spans.iter().filter(|(_, d)| done_by(d) && d.is_some_and(|d| d >= first)).count(),
+            }
+        })
+        .collect())
+}
+
+/// Working days of history the pace in `estimate_completion` is taken from
+const PACE_WINDOW_DAYS: usize = 20;
+
+/// How far to look for working days, so a work week without any doesn't loop forever
+const SCAN_DAYS: u64 = 3660;
+
+/// How long the tasks left in a list or project will take at the recent pace
+#[derive(Debug, Serialize, Clone)]
+pub struct Forecast {
+    /// "current", "shelf" or the project's name
+    pub target: String,
+    pub remaining: usize,
+    /// Completions per working day over the last `PACE_WINDOW_DAYS` working days: the
+    /// project's own, or every completion for a list or a project with none lately
+    pub per_day: f64,
+    /// Whether `per_day` is the project's own pace
+    pub project_pace: bool,
+    /// Working days to finish, rounded up; `None` with no recent completions to go by
+    pub working_days: Option<usize>,
+    /// The working day the last task would be done on
+    pub finish: Option<NaiveDate>,
+    /// E.g. "At your current pace, the 14 remaining tasks in Website will take ~9 working days"
+    pub message: String,
+}
+
+/// The `count`th working day from `today`, today being the first if it's a working day
+fn nth_work_day(today: NaiveDate, count: usize, work_week: &WorkWeek) -> Option<NaiveDate> {
+    today.iter_days().take(SCAN_DAYS as usize).filter(|day| work_week.is_work_day(*day)).nth(count.checked_sub(1)?)
+}
+
+/// Project when the open tasks left in `list_or_project` ("current", "shelf" or a
+/// project name) will be done, going by completions over the recent working days
+pub fn estimate_completion(
+    state: &TaskState,
+    list_or_project: &str,
+    work_week: &WorkWeek,
+    today: NaiveDate,
+) -> Result<Forecast, String> {
+    let target = list_or_project.trim();
+    let wanted = target.to_lowercase();
+    let in_project = |task: &Task| report::project_of(task).is_some_and(|p| p.to_lowercase() == wanted);
+    let (remaining, is_project) = match wanted.as_str() {
+        "current" => (state.current.len(), false),
+        "shelf" => (state.shelf.len(), false),
+        _ => (state.current.iter().chain(&state.shelf).filter(|task| in_project(task)).count(), true),
+    };
+
+    // The window ends yesterday, so a slow morning doesn't drag the pace down
+    let window: Vec<NaiveDate> = (1..SCAN_DAYS)
+        .filter_map(|back| today.checked_sub_days(Days::new(back)))
+        .filter(|day| work_week.is_work_day(*day))
+        .take(PACE_WINDOW_DAYS)
+        .collect();
+    let Some(&from) = window.last() else {
+        return Err("The work week has no working days".to_string());
+    };
+    let recent = search_index::history(DateRange {
+        from: Some(from),
+        to: today.pred_opt(),
+    })?;
+    let in_window: Vec<&DoneMatch> = recent.iter().filter(|m| day_of(m).is_some_and(|d| window.contains(&d))).collect();
+    let own = in_window.iter().filter(|m| in_project(&m.task)).count();
+    let project_pace = is_project && own > 0;
+    let done = if project_pace { own } else { in_window.len() };
+    let per_day = done as f64 / window.len() as f64;
+
+    let working_days = (per_day > 0.0).then(|| (remaining as f64 / per_day).ceil() as usize);
+    let finish = working_days.and_then(|days| nth_work_day(today, days.max(1), work_week));
+    let label = match wanted.as_str() {
+        "current" => "in Current".to_string(),
+        "shelf" => "on the shelf".to_string(),
+        _ => format!("in {}", target),
+    };
+    let tasks = if remaining == 1 { "task" } else { "tasks" };
+    let message = match working_days {
+        _ if remaining == 0 => format!("Nothing left {}", label),
+        Some(days) => format!(
+            "At your current pace, the {} remaining {} {} will take ~{} working day{}",
+            remaining,
+            tasks,
+            label,
+            days,
+            if days == 1 { "" } else { "s" }
+        ),
+        None => format!("No completions in the last {} working days to estimate from", PACE_WINDOW_DAYS),
+    };
+    Ok(Forecast {
+        target: target.to_string(),
+        remaining,
+        per_day,
+        project_pace,
+        working_days,
+        finish,
+        message,
+    })
+}
+
+/// Which numbers `export_stats` writes
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsMetric {
+    /// Completions per day, week or month
+    Daily,
+    Weekly,
+    Monthly,
+    /// By hour and weekday
+    Patterns,
+    /// `get_breakdown` per tag or per project
+    Tags,
+    Projects,
+    /// Lead time, WIP age and weekly throughput
+    Flow,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// One CSV line from already formatted fields
+fn csv_row(fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| done::csv_field(f)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// Dates in exports are YYYY-MM-DD whatever the display format, so they read back the same
+fn csv_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn buckets_csv(buckets: &[Bucket]) -> String {
+    let mut csv = String::from("start,completed\n");
+    for bucket in buckets {
+        csv.push_str(&csv_row(&[csv_date(bucket.start), bucket.completed.to_string()]));
+    }
+    csv
+}
+
+/// Compute `metric` over `range` and write it to `path` as CSV (a table in long
+/// form, one row per value) or as JSON (what the matching `get_*` command returns).
+/// Returns the number of rows, counted as in the CSV form either way
+pub fn export_stats(
+    state: &TaskState,
+    metric: StatsMetric,
+    range: DateRange,
+    format: ExportFormat,
+    path: &str,
+    first_day: Weekday,
+    now: NaiveDateTime,
+) -> Result<usize, String> {
+    let path = done::expand_path(path)?;
+    if !path.is_absolute() {
+        return Err(format!("Export path must be absolute or start with ~/: {}", path.display()));
+    }
+
+    let (json, csv) = match metric {
+        StatsMetric::Daily | StatsMetric::Weekly | StatsMetric::Monthly => {
+            let granularity = match metric {
+                StatsMetric::Daily => Granularity::Day,
+                StatsMetric::Weekly => Granularity::Week,
+                _ => Granularity::Month,
+            };
+            let stats = get_stats(range, granularity, first_day)?;
+            (serde_json::to_value(&stats), buckets_csv(&stats.buckets))
+        }
+        StatsMetric::Patterns => {
+            let patterns = get_completion_patterns(range, first_day)?;
+            let mut csv = String::from("weekday,hour,completed\n");
+            let mut weekday = first_day;
+            for hours in &patterns.grid {
+                for (hour, count) in hours.iter().enumerate() {
+                    csv.push_str(&csv_row(&[weekday.to_string(), hour.to_string(), count.to_string()]));
+                }
+                weekday = weekday.succ();
+            }
+            (serde_json::to_value(&patterns), csv)
+        }
+        StatsMetric::Tags | StatsMetric::Projects => {
+            let dimension = if metric == StatsMetric::Tags { Dimension::Tag } else { Dimension::Project };
+            let breakdown = get_breakdown(state, dimension, range)?;
+            let mut csv = String::from("key,completed,completed_percent,open,open_percent\n");
+            for row in &breakdown.rows {
+                csv.push_str(&csv_row(&[
+                    row.key.clone(),
+                    row.completed.to_string(),
+                    format!("{:.1}", row.completed_percent),
+                    row.open.to_string(),
+                    format!("{:.1}", row.open_percent),
+                ]));
+            }
+            (serde_json::to_value(&breakdown), csv)
+        }
+        StatsMetric::Flow => {
+            let flow = get_flow_metrics(state, range, first_day, now)?;
+            let mut csv = String::from("section,key,value\n");
+            let lead = &flow.lead_time;
+            for (key, value) in [
+                ("count", lead.count.to_string()),
+                ("average_hours", format!("{:.2}", lead.average_hours)),
+                ("median_hours", format!("{:.2}", lead.median_hours)),
+            ] {
+                csv.push_str(&csv_row(&["lead_time".to_string(), key.to_string(), value]));
+            }
+            for band in &flow.wip_age {
+                csv.push_str(&csv_row(&["wip_age".to_string(), band.label.to_string(), band.open.to_string()]));
+            }
+            csv.push_str(&csv_row(&["wip_age".to_string(), "unknown".to_string(), flow.wip_unknown_age.to_string()]));
+            for bucket in &flow.weekly_throughput {
+                csv.push_str(&csv_row(&[
+                    "weekly_throughput".to_string(),
+                    csv_date(bucket.start),
+                    bucket.completed.to_string(),
+                ]));
+            }
+            (serde_json::to_value(&flow), csv)
+        }
+    };
+
+    let rows = csv.lines().count().saturating_sub(1);
+    let content = match format {
+        ExportFormat::Csv => csv,
+        ExportFormat::Json => {
+            let json = json.map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?
+        }
+    };
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(rows)
+}
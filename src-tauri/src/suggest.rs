@@ -0,0 +1,106 @@
+//! Quick-add suggestions: task texts, tags and projects that have come up before
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::done::{self, DateRange};
+use crate::{search_index, TaskState};
+
+/// What a suggestion would fill in
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Task,
+    Tag,
+    Project,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// Task text as last written, `#tag`, or project name
+    pub text: String,
+    /// Times it was completed, or for projects used; recurring tasks count once more
+    pub count: usize,
+}
+
+/// Counts per kind and normalized text, keeping the latest spelling
+#[derive(Default)]
+struct Tally(HashMap<(SuggestionKind, String), (String, usize)>);
+
+impl Tally {
+    fn add(&mut self, kind: SuggestionKind, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let slot = self.0.entry((kind, done::normalized(text))).or_default();
+        slot.0 = text.to_string();
+        slot.1 += 1;
+    }
+}
+
+/// How closely `text` (normalized) starts with `prefix`: 0 for the whole text,
+/// 1 for a later word, `None` otherwise
+fn prefix_rank(text: &str, prefix: &str) -> Option<u8> {
+    if text.starts_with(prefix) {
+        Some(0)
+    } else if text.split_whitespace().skip(1).any(|word| word.starts_with(prefix)) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Up to `limit` suggestions for what's been typed so far, most used first. A
+/// `#` prefix only suggests tags. Task texts are counted from the done history and
+/// recurring tasks; projects also from open tasks
+pub fn suggest(state: &TaskState, prefix: &str, limit: usize) -> Result<Vec<Suggestion>, String> {
+    let prefix = done::normalized(prefix);
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (tags_only, tag_prefix) = match prefix.strip_prefix('#') {
+        Some(rest) => (true, rest),
+        None => (false, prefix.as_str()),
+    };
+
+    let mut tally = Tally::default();
+    for m in search_index::history(DateRange::default())? {
+        tally.add(SuggestionKind::Task, &m.task.text);
+        for tag in done::tags(&m.task.text) {
+            tally.add(SuggestionKind::Tag, &format!("#{}", tag));
+        }
+        if let Some(project) = &m.task.project {
+            tally.add(SuggestionKind::Project, project);
+        }
+    }
+    for recurring in &state.recurring {
+        tally.add(SuggestionKind::Task, &recurring.task.text);
+    }
+    for task in state.current.iter().chain(&state.shelf) {
+        if let Some(project) = &task.project {
+            tally.add(SuggestionKind::Project, project);
+        }
+    }
+
+    let mut found: Vec<(u8, Suggestion)> = tally
+        .0
+        .into_iter()
+        .filter_map(|((kind, key), (text, count))| {
+            let rank = match kind {
+                SuggestionKind::Tag => prefix_rank(key.trim_start_matches('#'), tag_prefix)?,
+                _ if tags_only => return None,
+                _ => prefix_rank(&key, &prefix)?,
+            };
+            Some((rank, Suggestion { kind, text, count }))
+        })
+        .collect();
+    found.sort_by(|(rank_a, a), (rank_b, b)| {
+        rank_a
+            .cmp(rank_b)
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    Ok(found.into_iter().take(limit).map(|(_, s)| s).collect())
+}
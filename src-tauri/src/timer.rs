@@ -0,0 +1,195 @@
+//! The task timer: one task at a time is timed from `start_timer` to `stop_timer`, and
+//! the time adds up in the task's `time_spent`. The running timer is kept in timer.json
+//! next to state.json so it carries on through a restart, and ticks to the windows
+//! every second whether or not they're showing
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::Source;
+use crate::{ensure_tasks_dir, get_tasks_dir, notify_tasks_changed, save_tasks, undo, AppState, Task, TaskState};
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// The task being timed and since when
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Running {
+    pub task_id: u64,
+    pub started: NaiveDateTime,
+}
+
+/// The running timer as the windows show it
+#[derive(Debug, Serialize, Clone)]
+pub struct ActiveTimer {
+    pub task_id: u64,
+    pub text: String,
+    pub started: NaiveDateTime,
+    /// Seconds since `started`
+    pub elapsed: u64,
+    /// Seconds timed on the task in all, this run included
+    pub total: u64,
+}
+
+fn timer_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("timer.json"))
+}
+
+/// The timer left running in timer.json, if any
+pub fn load() -> Option<Running> {
+    let loaded = timer_file().and_then(|path| {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+    });
+    loaded.unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to read timer.json: {}", e);
+        None
+    })
+}
+
+/// Write the running timer to timer.json, or remove it when there's none
+fn save(running: Option<Running>) {
+    let saved = timer_file().and_then(|path| match running {
+        Some(running) => {
+            ensure_tasks_dir()?;
+            let content = serde_json::to_string_pretty(&running).map_err(|e| e.to_string())?;
+            fs::write(path, content).map_err(|e| e.to_string())
+        }
+        None if path.exists() => fs::remove_file(path).map_err(|e| e.to_string()),
+        None => Ok(()),
+    });
+    if let Err(e) = saved {
+        eprintln!("Warning: Failed to update timer.json: {}", e);
+    }
+}
+
+fn find_mut(tasks: &mut TaskState, id: u64) -> Option<&mut Task> {
+    tasks.current.iter_mut().chain(&mut tasks.shelf).find(|t| t.id == Some(id))
+}
+
+/// Whole seconds from `started` to now
+fn seconds_since(started: NaiveDateTime) -> u64 {
+    (Local::now().naive_local() - started).num_seconds().max(0) as u64
+}
+
+fn active(app: &AppHandle) -> Option<ActiveTimer> {
+    let state = app.state::<AppState>();
+    let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+    let running = (*state.timer.lock().unwrap_or_else(|e| e.into_inner()))?;
+    let task = tasks.current.iter().chain(&tasks.shelf).find(|t| t.id == Some(running.task_id))?;
+    let elapsed = seconds_since(running.started);
+    Some(ActiveTimer {
+        task_id: running.task_id,
+        text: task.text.clone(),
+        started: running.started,
+        elapsed,
+        total: task.time_spent.unwrap_or(0) + elapsed,
+    })
+}
+
+/// Tell the windows the timer started, stopped or moved to another task
+fn changed(app: &AppHandle) {
+    let _ = app.emit("timer-changed", active(app));
+}
+
+/// Stop the running timer, if any, and add its time to the task it was timing
+pub fn stop(app: &AppHandle) -> Result<(), String> {
+    {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(running) = state.timer.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return Ok(());
+        };
+        save(None);
+        // A task deleted while it was timed takes its time with it; completions stop the
+        // timer first
+        if let Some(task) = find_mut(&mut tasks, running.task_id) {
+            *task.time_spent.get_or_insert(0) += seconds_since(running.started);
+            save_tasks(&mut tasks)?;
+            undo::record(app, Source::Ui, "Stop Timer", &tasks);
+        }
+    }
+    notify_tasks_changed(app);
+    changed(app);
+    Ok(())
+}
+
+/// Stop the timer if it's timing the task with id `task_id`, which is about to be
+/// completed, so its time is on the task when it's logged
+pub fn stop_for(app: &AppHandle, task_id: u64) -> Result<(), String> {
+    let running = *app.state::<AppState>().timer.lock().unwrap_or_else(|e| e.into_inner());
+    match running {
+        Some(running) if running.task_id == task_id => stop(app),
+        _ => Ok(()),
+    }
+}
+
+/// Take up the timer left running in the data directory, after switching profiles
+pub fn reload(app: &AppHandle) {
+    *app.state::<AppState>().timer.lock().unwrap_or_else(|e| e.into_inner()) = load();
+    changed(app);
+}
+
+/// Send "timer-tick" with the running timer every second. A timed task deleted from the
+/// lists stops the timer
+pub fn run(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(TICK);
+        let state = app.state::<AppState>();
+        if state.timer.lock().unwrap_or_else(|e| e.into_inner()).is_none() {
+            continue;
+        }
+        match active(&app) {
+            Some(timer) => {
+                let _ = app.emit("timer-tick", timer);
+            }
+            None => {
+                *state.timer.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                save(None);
+                changed(&app);
+            }
+        }
+    });
+}
+
+/// Time the task with id `task_id`, stopping the timer on any other task first
+#[tauri::command]
+pub fn start_timer(task_id: u64, app: AppHandle) -> Result<ActiveTimer, String> {
+    let running = *app.state::<AppState>().timer.lock().unwrap_or_else(|e| e.into_inner());
+    match running {
+        Some(running) if running.task_id == task_id => {}
+        _ => {
+            stop(&app)?;
+            let state = app.state::<AppState>();
+            let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+            if !tasks.current.iter().chain(&tasks.shelf).any(|t| t.id == Some(task_id)) {
+                return Err(format!("No task with id {}", task_id));
+            }
+            let running = Running {
+                task_id,
+                started: Local::now().naive_local(),
+            };
+            *state.timer.lock().unwrap_or_else(|e| e.into_inner()) = Some(running);
+            save(Some(running));
+        }
+    }
+    changed(&app);
+    active(&app).ok_or_else(|| format!("No task with id {}", task_id))
+}
+
+#[tauri::command]
+pub fn stop_timer(app: AppHandle) -> Result<(), String> {
+    stop(&app)
+}
+
+#[tauri::command]
+pub fn get_active_timer(app: AppHandle) -> Option<ActiveTimer> {
+    active(&app)
+}
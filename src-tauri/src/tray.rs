@@ -0,0 +1,512 @@
+use chrono::Local;
+use std::thread;
+use std::time::Duration;
+use tauri::{
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{MouseButton, MouseButtonState, TrayIconEvent},
+    AppHandle, Manager, Theme, Wry,
+};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::audit::Source;
+use crate::icon::{self, Canvas};
+use crate::window::ThemeMode;
+use crate::{
+    add_task, apply_dnd, archive_done_from, complete_current_task, done, focus_bar, hotkeys, locale, pomodoro,
+    restore_completed, show_quick_add, scheduler, show_settings, show_summary, snooze_woken, toggle_window,
+    update_goal_progress, AppState, TrayClickAction, TrayIconStyle, TrayMenuEntry,
+};
+
+pub const TRAY_ID: &str = "main";
+
+/// Menu item ids for current tasks are "complete:<task id>"
+const COMPLETE_PREFIX: &str = "complete:";
+
+/// Undo items are "undo:<line of the entry in done.md>:<date>:<text>"
+const UNDO_PREFIX: &str = "undo:";
+
+const MAX_LABEL_LEN: usize = 40;
+
+fn truncate_label(text: &str) -> String {
+    if text.chars().count() > MAX_LABEL_LEN {
+        let truncated: String = text.chars().take(MAX_LABEL_LEN).collect();
+        format!("{}...", truncated)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Build the tray menu from the configured `tray_menu` entries, in order
+pub fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let state = app.state::<AppState>();
+    let (limit, recent_limit, mut entries, focus_bar) = {
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        (config.tray_task_limit, config.tray_recent_limit, config.tray_menu.clone(), config.focus_bar)
+    };
+    let dnd = *state.dnd.lock().unwrap_or_else(|e| e.into_inner());
+    let hotkeys_suspended = *state.hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner());
+    let current: Vec<(u64, String)> = {
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.current.iter().take(limit).filter_map(|t| Some((t.id?, t.text.clone()))).collect()
+    };
+    let woken = state.woken.lock().unwrap_or_else(|e| e.into_inner()).len();
+    let pomodoro = pomodoro::status(app);
+
+    // Never leave the user without a way to quit
+    if !entries.contains(&TrayMenuEntry::Quit) {
+        entries.push(TrayMenuEntry::Separator);
+        entries.push(TrayMenuEntry::Quit);
+    }
+
+    let mut menu = MenuWriter {
+        app,
+        menu: Menu::new(app)?,
+        has_items: false,
+        pending_separator: false,
+    };
+
+    for entry in entries {
+        match entry {
+            TrayMenuEntry::Tasks => {
+                for (id, text) in &current {
+                    let item = MenuItem::with_id(
+                        app,
+                        format!("{}{}", COMPLETE_PREFIX, id),
+                        format!("○ {}", truncate_label(text)),
+                        true,
+                        None::<&str>,
+                    )?;
+                    menu.append(&item)?;
+                }
+                // Offered until the tasks that just came off the shelf are dealt with
+                if woken > 0 {
+                    let label = if woken == 1 { "Snooze Again".to_string() } else { format!("Snooze {} Again", woken) };
+                    menu.append(&MenuItem::with_id(app, "snooze_again", label, true, None::<&str>)?)?;
+                }
+            }
+            TrayMenuEntry::Recent => menu.append(&build_recent_submenu(app, recent_limit)?)?,
+            TrayMenuEntry::Summary => {
+                menu.append(&MenuItem::with_id(app, "summary", "Today's Summary…", true, None::<&str>)?)?
+            }
+            TrayMenuEntry::QuickAdd => {
+                menu.append(&MenuItem::with_id(app, "quick_add", "New Task…", true, None::<&str>)?)?
+            }
+            TrayMenuEntry::Dnd => {
+                menu.append(&CheckMenuItem::with_id(app, "dnd", "Do Not Disturb", true, dnd, None::<&str>)?)?
+            }
+            TrayMenuEntry::PauseHotkeys => menu.append(&CheckMenuItem::with_id(
+                app,
+                "pause_hotkeys",
+                "Pause Shortcuts",
+                true,
+                hotkeys_suspended,
+                None::<&str>,
+            )?)?,
+            TrayMenuEntry::FocusBar => {
+                menu.append(&CheckMenuItem::with_id(app, "focus_bar", "Focus Bar", true, focus_bar, None::<&str>)?)?
+            }
+            TrayMenuEntry::Pomodoro => match &pomodoro {
+                None => menu.append(&MenuItem::with_id(
+                    app,
+                    "pomodoro_start",
+                    "Start Pomodoro",
+                    true,
+                    None::<&str>,
+                )?)?,
+                Some(status) => menu.append(&build_pomodoro_submenu(app, status)?)?,
+            },
+            TrayMenuEntry::Settings => {
+                menu.append(&MenuItem::with_id(app, "settings", "Settings…", true, None::<&str>)?)?
+            }
+            TrayMenuEntry::Archive => {
+                menu.append(&MenuItem::with_id(app, "archive", "Archive Completed", true, None::<&str>)?)?
+            }
+            TrayMenuEntry::Quit => menu.append(&MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?)?,
+            TrayMenuEntry::Separator => menu.separator(),
+            TrayMenuEntry::Unknown => {}
+        }
+    }
+
+    Ok(menu.menu)
+}
+
+/// Appends to the tray menu, only emitting separators between visible items
+/// so empty sections don't leave doubled or trailing lines
+struct MenuWriter<'a> {
+    app: &'a AppHandle,
+    menu: Menu<Wry>,
+    has_items: bool,
+    pending_separator: bool,
+}
+
+impl MenuWriter<'_> {
+    fn append(&mut self, item: &dyn IsMenuItem<Wry>) -> tauri::Result<()> {
+        if self.pending_separator {
+            self.menu.append(&PredefinedMenuItem::separator(self.app)?)?;
+            self.pending_separator = false;
+        }
+        self.has_items = true;
+        self.menu.append(item)
+    }
+
+    fn separator(&mut self) {
+        self.pending_separator = self.has_items;
+    }
+}
+
+/// Pause or resume, skip and stop the pomodoro under way
+fn build_pomodoro_submenu(app: &AppHandle, status: &pomodoro::PomodoroStatus) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::new(app, "Pomodoro", true)?;
+    let (toggle, label) = if status.paused {
+        ("pomodoro_start", "Resume")
+    } else {
+        ("pomodoro_pause", "Pause")
+    };
+    submenu.append(&MenuItem::with_id(app, toggle, label, true, None::<&str>)?)?;
+    let skip = if status.phase == pomodoro::Phase::Work { "Skip to Break" } else { "Skip to Focus" };
+    submenu.append(&MenuItem::with_id(app, "pomodoro_skip", skip, true, None::<&str>)?)?;
+    submenu.append(&MenuItem::with_id(app, "pomodoro_stop", "Stop", true, None::<&str>)?)?;
+    Ok(submenu)
+}
+
+fn build_recent_submenu(app: &AppHandle, limit: usize) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::new(app, "Recently Completed", true)?;
+    let entries = done::recent_entries(limit).unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to read done log: {}", e);
+        Vec::new()
+    });
+
+    if entries.is_empty() {
+        let empty = MenuItem::new(app, "Nothing completed yet", false, None::<&str>)?;
+        submenu.append(&empty)?;
+        return Ok(submenu);
+    }
+
+    for entry in entries {
+        let date = entry.day().map(locale::date).unwrap_or_else(|| entry.date.clone());
+        let item = MenuItem::with_id(
+            app,
+            format!("{}{}:{}:{}", UNDO_PREFIX, entry.line, entry.date, entry.task.text),
+            format!("Undo: {} ({})", truncate_label(&entry.task.text), date),
+            true,
+            None::<&str>,
+        )?;
+        submenu.append(&item)?;
+    }
+    Ok(submenu)
+}
+
+pub fn tooltip(app: &AppHandle) -> String {
+    let count = {
+        let state = app.state::<AppState>();
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        tasks.current.len() + tasks.shelf.len()
+    };
+    match pomodoro::countdown(app) {
+        Some(countdown) => format!("Task Log ({} tasks)\n{}", count, countdown),
+        None => format!("Task Log ({} tasks)", count),
+    }
+}
+
+/// Put the pomodoro's countdown next to the tray icon, where the platform shows a title
+/// (macOS and Linux), and in the tooltip; both lose it when no pomodoro is under way
+pub fn refresh_title(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let _ = tray.set_title(pomodoro::countdown(app));
+    let _ = tray.set_tooltip(Some(tooltip(app)));
+}
+
+/// Dark unless the theme setting says otherwise, then whatever the window reports
+fn menu_bar_is_dark(app: &AppHandle) -> bool {
+    match app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).theme.mode {
+        ThemeMode::Light => return false,
+        ThemeMode::Dark => return true,
+        ThemeMode::System => {}
+    }
+    app.get_webview_window("main")
+        .and_then(|w| w.theme().ok())
+        .is_some_and(|theme| theme == Theme::Dark)
+}
+
+/// Share of the daily goal that is done or, without a goal, of today's plan:
+/// completed today / (completed today + still current)
+fn daily_progress(app: &AppHandle, open: usize) -> f64 {
+    let goal = app.state::<AppState>().goal_progress.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if goal.goal > 0 {
+        return goal.fraction();
+    }
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let completed = done::completed_on(&today).unwrap_or(0);
+    let planned = completed + open;
+    if planned == 0 {
+        0.0
+    } else {
+        completed as f64 / planned as f64
+    }
+}
+
+/// Render the configured icon variant (redrawing the badge for the current count)
+pub fn refresh_icon(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let Some(base) = app.default_window_icon() else {
+        return;
+    };
+
+    let state = app.state::<AppState>();
+    let (style, overdue_badge, accent) = {
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        (
+            config.tray_icon_style,
+            config.overdue.enabled && config.overdue.badge,
+            config.theme.accent_rgb().unwrap_or(icon::PROGRESS_GREEN),
+        )
+    };
+    let (count, overdue) = {
+        let tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let overdue = if overdue_badge {
+            scheduler::overdue_tasks(&tasks, Local::now().naive_local()).len()
+        } else {
+            0
+        };
+        (tasks.current.len(), overdue)
+    };
+    let foreground = icon::foreground(menu_bar_is_dark(app));
+
+    // Overdue tasks take over the badge in every style
+    if overdue > 0 {
+        let mut canvas = match style {
+            TrayIconStyle::Colored => Canvas::from_image(base),
+            _ => Canvas::monochrome(base, foreground),
+        };
+        canvas.draw_badge(overdue, icon::BADGE_RED);
+        let _ = tray.set_icon(Some(canvas.into_image()));
+        let _ = tray.set_icon_as_template(false);
+        return;
+    }
+
+    let (image, template) = match style {
+        TrayIconStyle::Colored => (base.clone(), false),
+        // macOS recolors template images itself; elsewhere pick the color up front
+        TrayIconStyle::Template if cfg!(target_os = "macos") => {
+            (Canvas::monochrome(base, icon::BLACK).into_image(), true)
+        }
+        TrayIconStyle::Template => (Canvas::monochrome(base, foreground).into_image(), false),
+        TrayIconStyle::Badge => {
+            let mut canvas = Canvas::monochrome(base, foreground);
+            if count > 0 {
+                canvas.draw_badge(count, icon::BADGE_RED);
+            }
+            (canvas.into_image(), false)
+        }
+        TrayIconStyle::Progress => {
+            let mut canvas = Canvas::monochrome(base, foreground);
+            canvas.draw_ring(daily_progress(app, count), accent, foreground);
+            (canvas.into_image(), false)
+        }
+    };
+
+    let _ = tray.set_icon(Some(image));
+    let _ = tray.set_icon_as_template(template);
+}
+
+/// Rebuild the tray menu, tooltip and icon after the task state changed
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    match build_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => eprintln!("Warning: Failed to rebuild tray menu: {}", e),
+    }
+    refresh_title(app);
+    update_goal_progress(app);
+    refresh_icon(app);
+}
+
+pub fn handle_menu_event(app: &AppHandle, id: &str) {
+    if let Some(task_id) = id.strip_prefix(COMPLETE_PREFIX) {
+        if let Ok(task_id) = task_id.parse::<u64>() {
+            if let Err(e) = complete_current_task(app, Source::Tray, task_id) {
+                eprintln!("Warning: Failed to complete task from tray: {}", e);
+            }
+        }
+        return;
+    }
+
+    if let Some(entry) = id.strip_prefix(UNDO_PREFIX) {
+        // <line>:<date>:<text>; the text may have colons of its own
+        let mut parts = entry.splitn(3, ':');
+        let (line, date, text) = (parts.next().map(str::parse::<usize>), parts.next(), parts.next());
+        if let (Some(Ok(line)), Some(date), Some(text)) = (line, date, text) {
+            if let Err(e) = restore_completed(app, Source::Tray, line, date, text) {
+                eprintln!("Warning: Failed to undo completed task: {}", e);
+            }
+        }
+        return;
+    }
+
+    match id {
+        "quick_add" => {
+            show_quick_add(app);
+        }
+        "settings" => {
+            show_settings(app);
+        }
+        "summary" => {
+            show_summary(app);
+        }
+        "snooze_again" => {
+            if let Err(e) = snooze_woken(app, Source::Tray) {
+                eprintln!("Warning: Failed to snooze tasks: {}", e);
+            }
+        }
+        "dnd" => {
+            let enabled = *app.state::<AppState>().dnd.lock().unwrap_or_else(|e| e.into_inner());
+            apply_dnd(app, !enabled);
+        }
+        "pause_hotkeys" => {
+            let suspended = *app.state::<AppState>().hotkeys_suspended.lock().unwrap_or_else(|e| e.into_inner());
+            if let Err(e) = hotkeys::set_suspended(app, !suspended) {
+                eprintln!("Warning: Failed to re-register shortcuts: {}", e);
+            }
+        }
+        "focus_bar" => {
+            let enabled = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).focus_bar;
+            if let Err(e) = focus_bar::set_enabled(app, !enabled) {
+                eprintln!("Warning: Failed to toggle focus bar: {}", e);
+            }
+        }
+        "archive" => {
+            let _ = archive_done_from(app, Source::Tray);
+        }
+        "pomodoro_start" => {
+            let _ = pomodoro::start_pomodoro(app.clone());
+        }
+        "pomodoro_pause" => {
+            let _ = pomodoro::pause_pomodoro(app.clone());
+        }
+        "pomodoro_skip" => {
+            let _ = pomodoro::skip_pomodoro(app.clone());
+        }
+        "pomodoro_stop" => pomodoro::stop_pomodoro(app.clone()),
+        "quit" => {
+            app.exit(0);
+        }
+        _ => {}
+    }
+}
+
+/// Let the OS open the menu on left click only when that's the configured left action
+pub fn apply_click_config(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let left = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).tray_click.left;
+    let _ = tray.set_show_menu_on_left_click(left == TrayClickAction::ShowMenu);
+}
+
+/// A left click waits this long for a second click before running its single-click action
+const DOUBLE_CLICK_WINDOW: Duration = Duration::from_millis(250);
+
+/// Tracks a left click that may still turn into a double-click
+#[derive(Default)]
+pub struct ClickDebounce {
+    next_id: u64,
+    pending: Option<u64>,
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: TrayIconEvent) {
+    // Act once per click, on release; some platforms also report the press.
+    // Native DoubleClick events are ignored since double-clicks are detected below.
+    let TrayIconEvent::Click {
+        button,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    else {
+        return;
+    };
+
+    let actions = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).tray_click;
+    match button {
+        // Only delay single clicks when a double-click action is configured
+        MouseButton::Left if actions.double != TrayClickAction::Nothing => {
+            debounce_left_click(app, actions.left, actions.double)
+        }
+        MouseButton::Left => run_click_action(app, actions.left),
+        MouseButton::Right => run_click_action(app, actions.right),
+        MouseButton::Middle => run_click_action(app, actions.middle),
+    }
+}
+
+/// First click arms a timer; a second click inside the window cancels it and
+/// runs the double-click action instead
+fn debounce_left_click(app: &AppHandle, single: TrayClickAction, double: TrayClickAction) {
+    let state = app.state::<AppState>();
+    let id = {
+        let mut clicks = state.tray_clicks.lock().unwrap_or_else(|e| e.into_inner());
+        if clicks.pending.take().is_some() {
+            drop(clicks);
+            run_click_action(app, double);
+            return;
+        }
+        clicks.next_id += 1;
+        clicks.pending = Some(clicks.next_id);
+        clicks.next_id
+    };
+
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(DOUBLE_CLICK_WINDOW);
+        let still_pending = {
+            let state = app.state::<AppState>();
+            let mut clicks = state.tray_clicks.lock().unwrap_or_else(|e| e.into_inner());
+            if clicks.pending == Some(id) {
+                clicks.pending = None;
+                true
+            } else {
+                false
+            }
+        };
+        if still_pending {
+            // Window placement uses AppKit, which must run on the main thread
+            let handle = app.clone();
+            let _ = app.run_on_main_thread(move || run_click_action(&handle, single));
+        }
+    });
+}
+
+fn run_click_action(app: &AppHandle, action: TrayClickAction) {
+    match action {
+        TrayClickAction::ToggleWindow => toggle_window(app),
+        TrayClickAction::QuickAdd => show_quick_add(app),
+        TrayClickAction::QuickAddClipboard => {
+            if let Err(e) = add_from_clipboard(app) {
+                eprintln!("Warning: Failed to add task from clipboard: {}", e);
+            }
+        }
+        TrayClickAction::OpenSettings => show_settings(app),
+        TrayClickAction::ArchiveCompleted => {
+            let _ = archive_done_from(app, Source::Tray);
+        }
+        TrayClickAction::ShowMenu | TrayClickAction::Nothing => {}
+    }
+}
+
+/// Add the first non-empty line of the clipboard as a task
+fn add_from_clipboard(app: &AppHandle) -> Result<(), String> {
+    let text = app.clipboard().read_text().map_err(|e| e.to_string())?;
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .ok_or_else(|| "Clipboard has no text".to_string())?;
+    add_task(app, Source::Tray, line)
+}
@@ -0,0 +1,142 @@
+//! Dropping text or links onto the menu bar icon turns them into tasks (macOS only).
+//!
+//! The status item's click target is a view created by the tray-icon crate, so the
+//! NSDraggingDestination methods are added to its class at runtime and the view is
+//! registered for URL and string drags.
+
+use std::ffi::CString;
+use std::sync::OnceLock;
+
+use objc2::encode::Encode;
+use objc2::ffi::class_addMethod;
+use objc2::runtime::{AnyClass, AnyObject, Bool, Imp, ProtocolObject, Sel};
+use objc2::{sel, MainThreadMarker};
+use objc2_app_kit::{NSDragOperation, NSDraggingInfo, NSPasteboardTypeString, NSPasteboardTypeURL};
+use objc2_foundation::{NSArray, NSString};
+use tauri::AppHandle;
+
+use crate::audit::Source;
+use crate::{add_new_task, tray::TRAY_ID, Note, Task};
+
+/// Pasteboard type carrying a dragged link's title (e.g. from Safari)
+const URL_NAME_TYPE: &str = "public.url-name";
+
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+type DraggingEntered = extern "C-unwind" fn(&AnyObject, Sel, &ProtocolObject<dyn NSDraggingInfo>) -> NSDragOperation;
+type PerformDragOperation = extern "C-unwind" fn(&AnyObject, Sel, &ProtocolObject<dyn NSDraggingInfo>) -> Bool;
+
+extern "C-unwind" fn dragging_entered(
+    _this: &AnyObject,
+    _cmd: Sel,
+    _sender: &ProtocolObject<dyn NSDraggingInfo>,
+) -> NSDragOperation {
+    NSDragOperation::Copy
+}
+
+extern "C-unwind" fn perform_drag_operation(
+    _this: &AnyObject,
+    _cmd: Sel,
+    sender: &ProtocolObject<dyn NSDraggingInfo>,
+) -> Bool {
+    let Some(app) = APP.get() else {
+        return Bool::NO;
+    };
+
+    let pasteboard = sender.draggingPasteboard();
+    let url = unsafe { pasteboard.stringForType(NSPasteboardTypeURL) }.map(|s| s.to_string());
+    let title = pasteboard
+        .stringForType(&NSString::from_str(URL_NAME_TYPE))
+        .map(|s| s.to_string());
+    let text = unsafe { pasteboard.stringForType(NSPasteboardTypeString) }.map(|s| s.to_string());
+
+    let Some(task) = task_from_drop(url, title, text) else {
+        return Bool::NO;
+    };
+    match add_new_task(app, Source::Tray, task) {
+        Ok(()) => Bool::YES,
+        Err(e) => {
+            eprintln!("Warning: Failed to add dropped task: {}", e);
+            Bool::NO
+        }
+    }
+}
+
+/// Links become a task named after the page title with the URL kept as a note;
+/// plain text uses its first non-empty line
+fn task_from_drop(url: Option<String>, title: Option<String>, text: Option<String>) -> Option<Task> {
+    let first_line = |s: &str| s.lines().map(str::trim).find(|l| !l.is_empty()).map(str::to_string);
+
+    if let Some(url) = url.as_deref().and_then(first_line) {
+        let title = title.as_deref().and_then(first_line).filter(|t| *t != url);
+        return Some(match title {
+            Some(title) => Task {
+                text: title,
+                notes: vec![Note {
+                    text: url,
+                    completed: false,
+                }],
+                ..Default::default()
+            },
+            None => Task {
+                text: url,
+                ..Default::default()
+            },
+        });
+    }
+
+    text.as_deref().and_then(first_line).map(|text| Task {
+        text,
+        ..Default::default()
+    })
+}
+
+unsafe fn add_method(class: &AnyClass, name: Sel, imp: Imp, types: &str) {
+    let types = CString::new(types).expect("type encoding has no NUL bytes");
+    let added = class_addMethod(class as *const AnyClass as *mut AnyClass, name, imp, types.as_ptr());
+    if !added.as_bool() {
+        eprintln!("Warning: Tray drop target already implements {}", name.name().to_string_lossy());
+    }
+}
+
+/// Register the tray icon as a drop target for links and text
+pub fn install(app: &AppHandle) {
+    if APP.set(app.clone()).is_err() {
+        return;
+    }
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let _ = tray.with_inner_tray_icon(|inner| {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let Some(button) = inner.ns_status_item().and_then(|item| item.button(mtm)) else {
+            return;
+        };
+        // tray-icon layers its own target view over the button; that view receives the drag
+        let Some(target) = button.subviews().firstObject() else {
+            return;
+        };
+
+        let entered: DraggingEntered = dragging_entered;
+        let perform: PerformDragOperation = perform_drag_operation;
+        unsafe {
+            let class = target.class();
+            add_method(
+                class,
+                sel!(draggingEntered:),
+                std::mem::transmute::<DraggingEntered, Imp>(entered),
+                &format!("{}@:@", NSDragOperation::ENCODING),
+            );
+            add_method(
+                class,
+                sel!(performDragOperation:),
+                std::mem::transmute::<PerformDragOperation, Imp>(perform),
+                &format!("{}@:@", Bool::ENCODING),
+            );
+            target.registerForDraggedTypes(&NSArray::from_slice(&[NSPasteboardTypeURL, NSPasteboardTypeString]));
+        }
+    });
+}
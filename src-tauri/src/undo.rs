@@ -0,0 +1,424 @@
+//! Undo and redo for the task lists. Every change to `TaskState`, from the window,
+//! tray, capture prompts or scheduler, records the lists it touched as they were
+//! before and after, so Cmd+Z in the UI can step back through all of them. Archiving
+//! the done log is a step too, and a step that took completed tasks out of the lists
+//! takes their done log entries with it. The latest steps are kept in undo.json next
+//! to state.json, so they can still be undone after a restart
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audit::{self, Source};
+use crate::done::ArchivedLog;
+use crate::recurrence::RecurringTask;
+use crate::{
+    append_done, ensure_tasks_dir, get_tasks_dir, notify_tasks_changed, report, save_tasks, search_index,
+    unlog_completed, AppConfig, AppState, Task, TaskState,
+};
+
+/// Changes kept for undo; older ones drop off
+const MAX_STEPS: usize = 50;
+
+/// Changes each way written to undo.json, which is rewritten after every change
+const SAVED_STEPS: usize = 10;
+
+/// Edits from the window this close together undo as one, so a burst of saves while
+/// reordering or typing a note doesn't take as many steps to walk back
+const COALESCE: TimeDelta = TimeDelta::seconds(2);
+
+/// Label of the UI's own saves, which are the ones that coalesce
+pub const EDIT: &str = "Edit";
+
+/// Label of archiving the done log
+const ARCHIVE: &str = "Archive";
+
+/// Label of changes to state.json made while the app wasn't running
+const OUTSIDE: &str = "Outside Edit";
+
+/// The lists one change touched, as they were on one side of it; `None` for untouched lists
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct Lists {
+    current: Option<Vec<Task>>,
+    shelf: Option<Vec<Task>>,
+    recurring: Option<Vec<RecurringTask>>,
+}
+
+impl Lists {
+    /// Put these lists into `tasks`. When the next appearance of a recurring task is all that
+    /// differs, the scheduler's newer time is kept, or it would add the task straight back
+    fn apply(&self, tasks: &mut TaskState) {
+        if let Some(current) = &self.current {
+            tasks.current = current.clone();
+        }
+        if let Some(shelf) = &self.shelf {
+            tasks.shelf = shelf.clone();
+        }
+        if let Some(recurring) = &self.recurring {
+            let mut recurring = recurring.clone();
+            for r in &mut recurring {
+                if let Some(now) = tasks.recurring.iter().find(|n| n.task == r.task && n.rule == r.rule) {
+                    r.next = r.next.max(now.next);
+                }
+            }
+            tasks.recurring = recurring;
+        }
+    }
+}
+
+/// A task logged as completed, which undoing its step takes back out of the done log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Completion {
+    pub task_id: Option<u64>,
+    /// Day it was logged, YYYY-MM-DD
+    pub date: String,
+    /// Text as written to done.md, with its project prefix if any
+    pub text: String,
+}
+
+/// One change to the task lists, or to the done log
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Change {
+    /// What was done, e.g. "Snooze", for "Undo Snooze"
+    label: String,
+    at: NaiveDateTime,
+    before: Lists,
+    after: Lists,
+    /// Done logs this change archived, the main one first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archived: Vec<ArchivedLog>,
+    /// Tasks this change took out of the lists because they were completed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    completed: Vec<Completion>,
+}
+
+impl Change {
+    /// The change from `old` to `new`, or `None` if nothing differs
+    fn between(label: &str, old: &TaskState, new: &TaskState, at: NaiveDateTime) -> Option<Change> {
+        let mut change = Change {
+            label: label.to_string(),
+            at,
+            before: Lists::default(),
+            after: Lists::default(),
+            archived: Vec::new(),
+            completed: Vec::new(),
+        };
+        if old.current != new.current {
+            change.before.current = Some(old.current.clone());
+            change.after.current = Some(new.current.clone());
+        }
+        if old.shelf != new.shelf {
+            change.before.shelf = Some(old.shelf.clone());
+            change.after.shelf = Some(new.shelf.clone());
+        }
+        if old.recurring != new.recurring {
+            change.before.recurring = Some(old.recurring.clone());
+            change.after.recurring = Some(new.recurring.clone());
+        }
+        let after = &change.after;
+        let touched = after.current.is_some() || after.shelf.is_some() || after.recurring.is_some();
+        touched.then_some(change)
+    }
+
+    /// Fold `next`, which directly followed this one, into it
+    fn absorb(&mut self, next: Change) {
+        self.at = next.at;
+        self.before.current = self.before.current.take().or(next.before.current);
+        self.before.shelf = self.before.shelf.take().or(next.before.shelf);
+        self.before.recurring = self.before.recurring.take().or(next.before.recurring);
+        self.after.current = next.after.current.or(self.after.current.take());
+        self.after.shelf = next.after.shelf.or(self.after.shelf.take());
+        self.after.recurring = next.after.recurring.or(self.after.recurring.take());
+        self.completed.extend(next.completed);
+    }
+
+    /// The task with id `id` as it was before this change
+    fn task_before(&self, id: u64) -> Option<&Task> {
+        let before = &self.before;
+        let mut tasks = before.current.iter().flatten().chain(before.shelf.iter().flatten());
+        tasks.find(|t| t.id == Some(id))
+    }
+}
+
+/// What undo and redo would do next, for the UI's menu items
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct UndoStatus {
+    /// Label of the change undo would revert
+    pub undo: Option<String>,
+    /// Label of the change redo would reapply
+    pub redo: Option<String>,
+}
+
+/// The undo and redo stacks
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct History {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    /// The lists as of the last recorded change, to compare the next one with
+    last: TaskState,
+    /// Completions logged whose tasks are still to leave the lists; the UI logs one
+    /// before the save that takes the task out
+    #[serde(skip)]
+    pending: Vec<Completion>,
+}
+
+impl History {
+    pub fn new(tasks: &TaskState) -> Self {
+        Self {
+            last: tasks.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The history saved by the last run, carried on to `tasks`. Changes made to state.json
+    /// while the app wasn't running become a step of their own
+    pub fn load(tasks: &TaskState) -> Self {
+        let loaded: Result<Option<History>, String> = history_file().and_then(|path| {
+            if !path.exists() {
+                return Ok(None);
+            }
+            let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+            serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+        });
+        match loaded {
+            Ok(Some(mut history)) => {
+                history.record(OUTSIDE, tasks, Local::now().naive_local());
+                history
+            }
+            Ok(None) => History::new(tasks),
+            Err(e) => {
+                eprintln!("Warning: Failed to read undo.json: {}. Starting with no undo history.", e);
+                History::new(tasks)
+            }
+        }
+    }
+
+    /// Write the latest steps to undo.json
+    fn save(&self) {
+        let saved = History {
+            undo: self.undo.iter().skip(self.undo.len().saturating_sub(SAVED_STEPS)).cloned().collect(),
+            redo: self.redo[self.redo.len().saturating_sub(SAVED_STEPS)..].to_vec(),
+            last: self.last.clone(),
+            pending: Vec::new(),
+        };
+        let written = ensure_tasks_dir().and_then(|_| {
+            let content = serde_json::to_string(&saved).map_err(|e| e.to_string())?;
+            fs::write(history_file()?, content).map_err(|e| e.to_string())
+        });
+        if let Err(e) = written {
+            eprintln!("Warning: Failed to save undo.json: {}", e);
+        }
+    }
+
+    fn record(&mut self, label: &str, tasks: &TaskState, now: NaiveDateTime) {
+        let Some(mut change) = Change::between(label, &self.last, tasks, now) else {
+            return;
+        };
+        // Completions go with the change that takes their task out of the lists
+        let listed = |id: Option<u64>| id.is_some() && tasks.current.iter().chain(&tasks.shelf).any(|t| t.id == id);
+        let (open, done): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|c| listed(c.task_id));
+        self.pending = open;
+        change.completed = done
+            .into_iter()
+            .filter(|c| c.task_id.and_then(|id| change.task_before(id)).is_some())
+            .collect();
+        self.last = tasks.clone();
+        match self.undo.back_mut() {
+            Some(top) if label == EDIT && top.label == EDIT && now - top.at < COALESCE => {
+                self.redo.clear();
+                top.absorb(change);
+            }
+            _ => self.push(change),
+        }
+    }
+
+    fn push(&mut self, change: Change) {
+        self.redo.clear();
+        self.undo.push_back(change);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn status(&self) -> UndoStatus {
+        UndoStatus {
+            undo: self.undo.back().map(|c| c.label.clone()),
+            redo: self.redo.last().map(|c| c.label.clone()),
+        }
+    }
+}
+
+/// Remember a change to the task lists under `label` (e.g. "Snooze") so it can be undone,
+/// and log it to the audit log as coming from `source`. Called with the lists as they are
+/// after the change, while they're still locked
+pub fn record(app: &AppHandle, source: Source, label: &str, tasks: &TaskState) {
+    let status = {
+        let state = app.state::<AppState>();
+        let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+        audit::record(source, label, &history.last, tasks, Vec::new());
+        history.record(label, tasks, Local::now().naive_local());
+        history.save();
+        history.status()
+    };
+    let _ = app.emit("undo-changed", status);
+}
+
+/// Remember that `completion` was logged, so the change that takes its task out of the
+/// lists takes it back out of the done log when undone
+pub fn completed(app: &AppHandle, completion: Completion) {
+    let state = app.state::<AppState>();
+    state.history.lock().unwrap_or_else(|e| e.into_inner()).pending.push(completion);
+}
+
+/// The archive files of `archived`, for the audit log
+fn archive_names(archived: &[ArchivedLog]) -> Vec<String> {
+    archived
+        .iter()
+        .map(|log| format!("{} into {}", log.log.display(), log.archive.display()))
+        .collect()
+}
+
+/// Remember that the done logs in `archived` were archived, from `source`, so it can be undone
+pub fn record_archive(app: &AppHandle, source: Source, archived: Vec<ArchivedLog>) {
+    let status = {
+        let state = app.state::<AppState>();
+        let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+        audit::record(source, ARCHIVE, &history.last, &history.last, archive_names(&archived));
+        history.push(Change {
+            label: ARCHIVE.to_string(),
+            at: Local::now().naive_local(),
+            before: Lists::default(),
+            after: Lists::default(),
+            archived,
+            completed: Vec::new(),
+        });
+        history.save();
+        history.status()
+    };
+    let _ = app.emit("undo-changed", status);
+}
+
+/// Switch to the history saved in the data directory, e.g. of another profile
+pub fn reload(app: &AppHandle, tasks: &TaskState) {
+    let status = {
+        let state = app.state::<AppState>();
+        let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+        *history = History::load(tasks);
+        history.status()
+    };
+    let _ = app.emit("undo-changed", status);
+}
+
+fn history_file() -> Result<PathBuf, String> {
+    Ok(get_tasks_dir()?.join("undo.json"))
+}
+
+/// Undo (`back`) or redo archiving the done logs in `archived`
+fn move_archives(archived: &[ArchivedLog], back: bool) -> Result<(), String> {
+    for (i, log) in archived.iter().enumerate() {
+        if back {
+            log.unarchive()?;
+            continue;
+        }
+        log.rearchive()?;
+        // As when it was first archived, only the main log gets a summary
+        if i == 0 {
+            if let Err(e) = report::append_tally(&log.archive) {
+                eprintln!("Warning: Failed to add the summary to {}: {}", log.archive.display(), e);
+            }
+        }
+    }
+    search_index::update_in_background();
+    Ok(())
+}
+
+/// Take the tasks `change` completed back out of the done log (`back`), or log them again
+fn step_completions(change: &mut Change, back: bool, config: &AppConfig) {
+    if back {
+        for completion in &change.completed {
+            if let Err(e) = unlog_completed(None, &completion.date, &completion.text) {
+                eprintln!("Warning: Failed to take {} out of the done log: {}", completion.text, e);
+            }
+        }
+        return;
+    }
+    let mut logged = Vec::new();
+    for completion in &change.completed {
+        let Some(task) = completion.task_id.and_then(|id| change.task_before(id)) else {
+            continue;
+        };
+        match append_done(task, config) {
+            Ok(completion) => logged.push(completion),
+            Err(e) => eprintln!("Warning: Failed to log {} as completed again: {}", task.text, e),
+        }
+    }
+    change.completed = logged;
+}
+
+/// Undo (`back`) or redo one change; returns its label
+fn step(app: &AppHandle, back: bool) -> Result<String, String> {
+    let config = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let (label, status) = {
+        let state = app.state::<AppState>();
+        let mut tasks = state.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut history = state.history.lock().unwrap_or_else(|e| e.into_inner());
+        let change = if back { history.undo.pop_back() } else { history.redo.pop() };
+        let Some(mut change) = change else {
+            return Err(if back { "Nothing to undo" } else { "Nothing to redo" }.to_string());
+        };
+        let lists = if back { &change.before } else { &change.after };
+        let mut stepped = tasks.clone();
+        lists.apply(&mut stepped);
+        let stepped_files = if change.archived.is_empty() {
+            save_tasks(&mut stepped)
+        } else {
+            move_archives(&change.archived, back)
+        };
+        if let Err(e) = stepped_files {
+            // Leave both stacks as they were
+            if back {
+                history.undo.push_back(change);
+            } else {
+                history.redo.push(change);
+            }
+            return Err(e);
+        }
+        step_completions(&mut change, back, &config);
+        let action = format!("{} {}", if back { "Undo" } else { "Redo" }, change.label);
+        audit::record(Source::Ui, &action, &tasks, &stepped, archive_names(&change.archived));
+        *tasks = stepped;
+        history.last = tasks.clone();
+        let label = change.label.clone();
+        if back {
+            history.redo.push(change);
+        } else {
+            history.undo.push_back(change);
+        }
+        history.save();
+        (label, history.status())
+    };
+    notify_tasks_changed(app);
+    let _ = app.emit("undo-changed", status);
+    Ok(label)
+}
+
+/// Revert the latest change to the task lists; returns what it was, e.g. "Snooze".
+/// Completed tasks it puts back leave the done log again
+#[tauri::command]
+pub fn undo(app: AppHandle) -> Result<String, String> {
+    step(&app, true)
+}
+
+/// Reapply the change undo last reverted; returns what it was
+#[tauri::command]
+pub fn redo(app: AppHandle) -> Result<String, String> {
+    step(&app, false)
+}
+
+#[tauri::command]
+pub fn get_undo_status(state: tauri::State<AppState>) -> UndoStatus {
+    state.history.lock().unwrap_or_else(|e| e.into_inner()).status()
+}
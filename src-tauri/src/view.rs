@@ -0,0 +1,123 @@
+//! Sorted and filtered views of the open tasks, computed here so the webview doesn't
+//! have to receive and sort the whole state
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::cmp::{Ordering, Reverse};
+
+use crate::query::{Query, QueryMatch};
+use crate::search::SearchList;
+use crate::{done, Task, TaskState};
+
+/// What a view is ordered by; ties keep list order
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// `current` as arranged, then the shelf
+    #[default]
+    Manual,
+    /// Soonest due first; tasks without a due time last
+    Due,
+    /// Highest first; tasks without a priority last
+    Priority,
+    /// Oldest first; tasks without a creation time last
+    Age,
+}
+
+/// Which tasks a view keeps, by their due and snooze times
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Past its due time
+    Overdue,
+    /// Due later today
+    DueToday,
+    /// Has a due time
+    Scheduled,
+    /// Has no due time
+    Unscheduled,
+    /// On the shelf until a set time
+    Snoozed,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ViewOptions {
+    /// Only `current` or only the shelf; both when unset
+    pub list: Option<SearchList>,
+    /// Only tasks with this `#tag`
+    pub tag: Option<String>,
+    pub status: Option<TaskStatus>,
+    /// A `query_tasks` expression the tasks also have to match
+    pub query: Option<String>,
+    pub sort: SortKey,
+    /// Reverse the order, e.g. latest due first
+    pub reverse: bool,
+    /// Skip this many tasks of the result, then return at most `limit`
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl TaskStatus {
+    fn matches(self, task: &Task, shelved: bool, now: NaiveDateTime) -> bool {
+        match self {
+            TaskStatus::Overdue => task.due.is_some_and(|due| due < now),
+            TaskStatus::DueToday => task.due.is_some_and(|due| due >= now && due.date() == now.date()),
+            TaskStatus::Scheduled => task.due.is_some(),
+            TaskStatus::Unscheduled => task.due.is_none(),
+            TaskStatus::Snoozed => shelved && task.snoozed_until.is_some(),
+        }
+    }
+}
+
+/// Order of `a` and `b` when both have a value; one without goes last
+fn present_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// The tasks `options` pick out, in the order they ask for
+pub fn tasks_view(state: &TaskState, options: &ViewOptions, now: NaiveDateTime) -> Result<Vec<QueryMatch>, String> {
+    let query = options.query.as_deref().map(|q| Query::parse(q, now.date())).transpose()?;
+    let tag = options.tag.as_deref().map(|t| t.trim_start_matches('#').to_lowercase());
+
+    let lists = [(SearchList::Current, &state.current), (SearchList::Shelf, &state.shelf)];
+    let mut found: Vec<QueryMatch> = lists
+        .into_iter()
+        .filter(|(list, _)| options.list.is_none_or(|wanted| wanted == *list))
+        .flat_map(|(list, tasks)| {
+            tasks.iter().enumerate().map(move |(index, task)| QueryMatch {
+                list,
+                index,
+                task: task.clone(),
+            })
+        })
+        .filter(|m| {
+            let shelved = m.list == SearchList::Shelf;
+            tag.as_ref().is_none_or(|tag| done::tags(&m.task.text).contains(tag))
+                && options.status.is_none_or(|status| status.matches(&m.task, shelved, now))
+                && query.as_ref().is_none_or(|query| query.matches(&m.task, shelved, now))
+        })
+        .collect();
+
+    match options.sort {
+        SortKey::Manual => {}
+        SortKey::Due => found.sort_by(|a, b| present_first(a.task.due, b.task.due)),
+        SortKey::Priority => {
+            found.sort_by(|a, b| present_first(a.task.priority.map(Reverse), b.task.priority.map(Reverse)))
+        }
+        SortKey::Age => found.sort_by(|a, b| present_first(a.task.created, b.task.created)),
+    }
+    if options.reverse {
+        found.reverse();
+    }
+    Ok(found
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect())
+}
@@ -0,0 +1,528 @@
+//! Where the main window appears when shown, and remembering where it was left
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::window::{Effect, EffectState, EffectsBuilder};
+use tauri::{AppHandle, Emitter, LogicalPosition, LogicalSize, Manager, Theme, WebviewWindow};
+
+#[cfg(target_os = "macos")]
+use objc2::MainThreadMarker;
+#[cfg(target_os = "macos")]
+use objc2_app_kit::{NSEvent, NSScreen};
+
+use crate::icon::Rgb;
+use crate::{defaults, save_config, tray, tray::TRAY_ID, AppState};
+
+/// Where the window is placed each time it's shown
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowAnchor {
+    /// Top-right corner of the screen under the cursor
+    TopRight,
+    TopLeft,
+    BottomRight,
+    BottomLeft,
+    Center,
+    /// Top-left corner at the mouse pointer
+    Cursor,
+    /// Just below (or above, for bottom taskbars) the tray icon
+    Tray,
+    /// Wherever the window was last moved to; top-right until it has been moved
+    LastPosition,
+}
+
+impl Default for WindowAnchor {
+    fn default() -> Self {
+        defaults::window_anchor()
+    }
+}
+
+/// Offset from the anchor in logical pixels, pointing into the screen
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct WindowMargin {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Native translucency behind the window contents
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowEffect {
+    #[default]
+    None,
+    /// macOS NSVisualEffectView material
+    Vibrancy,
+    /// Windows 10/11 acrylic blur
+    Acrylic,
+    /// Windows 11 mica
+    Mica,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct WindowAppearance {
+    /// Opacity of the window background from 0.0 to 1.0; text stays opaque
+    pub opacity: f64,
+    pub effect: WindowEffect,
+}
+
+impl Default for WindowAppearance {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            effect: WindowEffect::None,
+        }
+    }
+}
+
+/// Light or dark, or whatever the OS is set to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub mode: ThemeMode,
+    /// Highlight color as "#rrggbb"; the UI's default when unset
+    pub accent: Option<String>,
+}
+
+impl ThemeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.accent {
+            Some(accent) if self.accent_rgb().is_none() => {
+                Err(format!("Accent color must look like #rrggbb: {}", accent))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The accent as RGB, for the tray icon
+    pub fn accent_rgb(&self) -> Option<Rgb> {
+        let hex = self.accent.as_deref()?.strip_prefix('#')?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+}
+
+/// Window geometry in logical pixels
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct WindowBounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Where the window was left on one screen, relative to the top-left of its
+/// usable area in logical pixels
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ScreenPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// How long the window has to sit still before its bounds are written to config
+const SAVE_BOUNDS_DELAY: Duration = Duration::from_millis(500);
+
+/// A toggle this soon after a focus-loss hide is treated as part of the same click
+const BLUR_TOGGLE_GRACE: Duration = Duration::from_millis(300);
+
+static BLUR_HIDDEN_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Bumped on every move/resize so only the last one in a burst gets saved
+static BOUNDS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Where `place` last put the window, so its own moves aren't remembered as the user's
+static PLACED_AT: Mutex<Option<(f64, f64)>> = Mutex::new(None);
+
+/// A screen's usable area in logical pixels, origin top-left
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    /// Scale factor of the screen the area belongs to
+    scale: f64,
+}
+
+/// A screen's usable area and the name it's remembered under
+struct Screen {
+    area: Area,
+    name: String,
+}
+
+/// The screen containing a point given in points from the top-left of the main screen
+#[cfg(target_os = "macos")]
+fn screen_at(mtm: MainThreadMarker, x: f64, y: f64) -> Option<Screen> {
+    // Cocoa screen coordinates have their origin at the bottom-left of the main screen
+    let main_height = NSScreen::mainScreen(mtm)?.frame().size.height;
+    let y = main_height - y;
+
+    let screen = NSScreen::screens(mtm).iter().find(|screen| {
+        let frame = screen.frame();
+        x >= frame.origin.x
+            && x < frame.origin.x + frame.size.width
+            && y >= frame.origin.y
+            && y < frame.origin.y + frame.size.height
+    })?;
+
+    // Excludes the menu bar and Dock
+    let visible = screen.visibleFrame();
+    let area = Area {
+        x: visible.origin.x,
+        y: main_height - (visible.origin.y + visible.size.height),
+        width: visible.size.width,
+        height: visible.size.height,
+        scale: screen.backingScaleFactor(),
+    };
+    Some(Screen {
+        area,
+        name: screen.localizedName().to_string(),
+    })
+}
+
+/// The screen under the mouse, plus the mouse position
+#[cfg(target_os = "macos")]
+fn cursor_screen(_window: &WebviewWindow) -> Option<(Screen, (f64, f64))> {
+    let mtm = MainThreadMarker::new()?;
+    let main_height = NSScreen::mainScreen(mtm)?.frame().size.height;
+    let mouse_pos = NSEvent::mouseLocation();
+    let cursor = (mouse_pos.x, main_height - mouse_pos.y);
+    Some((screen_at(mtm, cursor.0, cursor.1)?, cursor))
+}
+
+/// The screen the middle of the window is on
+#[cfg(target_os = "macos")]
+fn window_screen(window: &WebviewWindow) -> Option<Screen> {
+    let mtm = MainThreadMarker::new()?;
+    let bounds = current_bounds(window)?;
+    screen_at(mtm, bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn monitor_screen(monitor: &tauri::Monitor) -> Screen {
+    let scale = monitor.scale_factor();
+    let work_area = monitor.work_area();
+    let position = work_area.position.to_logical::<f64>(scale);
+    let size = work_area.size.to_logical::<f64>(scale);
+    let area = Area {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale,
+    };
+    Screen {
+        area,
+        name: monitor.name().cloned().unwrap_or_default(),
+    }
+}
+
+/// Windows and Linux: the monitor under the cursor, falling back to the primary one
+/// when the cursor position isn't available (e.g. on Wayland)
+#[cfg(not(target_os = "macos"))]
+fn cursor_screen(window: &WebviewWindow) -> Option<(Screen, (f64, f64))> {
+    let cursor = window.cursor_position().ok();
+    let monitor = cursor
+        .and_then(|p| window.monitor_from_point(p.x, p.y).ok().flatten())
+        .or_else(|| window.primary_monitor().ok().flatten())?;
+
+    let screen = monitor_screen(&monitor);
+    let cursor = cursor
+        .map(|p| p.to_logical::<f64>(screen.area.scale))
+        .map(|p| (p.x, p.y))
+        .unwrap_or((screen.area.x, screen.area.y));
+    Some((screen, cursor))
+}
+
+/// The monitor the window is mostly on
+#[cfg(not(target_os = "macos"))]
+fn window_screen(window: &WebviewWindow) -> Option<Screen> {
+    let monitor = window.current_monitor().ok()??;
+    Some(monitor_screen(&monitor))
+}
+
+/// The tray icon's bounds in logical pixels, where the platform reports them
+fn tray_rect(app: &AppHandle, scale: f64) -> Option<Area> {
+    let rect = app.tray_by_id(TRAY_ID)?.rect().ok()??;
+    let position = rect.position.to_logical::<f64>(scale);
+    let size = rect.size.to_logical::<f64>(scale);
+    Some(Area {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale,
+    })
+}
+
+/// Position the window according to the configured anchor
+pub fn place(app: &AppHandle, window: &WebviewWindow) {
+    let screen = cursor_screen(window);
+    let (anchor, bounds, margin, remembered) = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        let margin = config.window_margins.get(&config.window_anchor).copied().unwrap_or_default();
+        let remembered = screen.as_ref().and_then(|(screen, _)| config.screen_positions.get(&screen.name).copied());
+        (config.window_anchor, config.window_bounds, margin, remembered)
+    };
+
+    if remembered.is_none() && anchor == WindowAnchor::LastPosition {
+        if let Some(bounds) = bounds {
+            *PLACED_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some((bounds.x, bounds.y));
+            let _ = window.set_position(LogicalPosition::new(bounds.x, bounds.y));
+            return;
+        }
+    }
+
+    let Some((Screen { area, .. }, cursor)) = screen else {
+        return;
+    };
+    let Ok(scale) = window.scale_factor() else {
+        return;
+    };
+    let Ok(size) = window.outer_size().map(|s| s.to_logical::<f64>(scale)) else {
+        return;
+    };
+    let (width, height) = (size.width, size.height);
+    let right = area.x + area.width - width;
+    let bottom = area.y + area.height - height;
+
+    // Where the user last left the window on this screen beats the anchor
+    let (x, y) = match remembered {
+        Some(p) => (area.x + p.x, area.y + p.y),
+        None => match anchor {
+            WindowAnchor::TopLeft => (area.x + margin.x, area.y + margin.y),
+            WindowAnchor::BottomRight => (right - margin.x, bottom - margin.y),
+            WindowAnchor::BottomLeft => (area.x + margin.x, bottom - margin.y),
+            WindowAnchor::Center => (
+                area.x + (area.width - width) / 2.0 + margin.x,
+                area.y + (area.height - height) / 2.0 + margin.y,
+            ),
+            WindowAnchor::Cursor => (cursor.0 + margin.x, cursor.1 + margin.y),
+            WindowAnchor::Tray => match tray_rect(app, area.scale) {
+                Some(tray) => {
+                    let x = tray.x + (tray.width - width) / 2.0 + margin.x;
+                    // Menu bars sit at the top; Windows taskbars usually at the bottom
+                    if tray.y > area.y + area.height / 2.0 {
+                        (x, tray.y - height - margin.y)
+                    } else {
+                        (x, tray.y + tray.height + margin.y)
+                    }
+                }
+                None => (right - margin.x, area.y + margin.y),
+            },
+            WindowAnchor::TopRight | WindowAnchor::LastPosition => (right - margin.x, area.y + margin.y),
+        },
+    };
+
+    // Keep the whole window on the screen whatever the margins say
+    let x = x.min(right).max(area.x);
+    let y = y.min(bottom).max(area.y);
+    *PLACED_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some((x, y));
+    move_to(window, &area, x, y);
+}
+
+/// Center `window` on the screen under the cursor, a bit above the middle
+pub fn center_on_cursor_screen(window: &WebviewWindow) {
+    let Some((Screen { area, .. }, _)) = cursor_screen(window) else {
+        return;
+    };
+    let Ok(scale) = window.scale_factor() else {
+        return;
+    };
+    let Ok(size) = window.outer_size().map(|s| s.to_logical::<f64>(scale)) else {
+        return;
+    };
+    let x = area.x + (area.width - size.width) / 2.0;
+    let y = area.y + (area.height - size.height) / 3.0;
+    move_to(window, &area, x, y);
+}
+
+/// Put `window` at the top edge of the screen under the cursor, centered
+pub fn place_top_center(window: &WebviewWindow) {
+    let Some((Screen { area, .. }, _)) = cursor_screen(window) else {
+        return;
+    };
+    let Ok(scale) = window.scale_factor() else {
+        return;
+    };
+    let Ok(size) = window.outer_size().map(|s| s.to_logical::<f64>(scale)) else {
+        return;
+    };
+    move_to(window, &area, area.x + (area.width - size.width) / 2.0, area.y);
+}
+
+/// Move the window to a logical position on `area`'s screen
+#[cfg_attr(target_os = "macos", allow(unused_variables))]
+fn move_to(window: &WebviewWindow, area: &Area, x: f64, y: f64) {
+    // Cocoa positions windows in points across all screens
+    #[cfg(target_os = "macos")]
+    let position = LogicalPosition::new(x, y);
+    // Elsewhere logical coordinates depend on the window's current monitor, so
+    // convert with the target monitor's scale to land right on mixed-DPI setups
+    #[cfg(not(target_os = "macos"))]
+    let position = LogicalPosition::new(x, y).to_physical::<i32>(area.scale);
+
+    let _ = window.set_position(position);
+}
+
+/// Hide the window when it loses focus, if the user wants dropdown-style behavior
+pub fn on_focus_lost(app: &AppHandle) {
+    let hide = {
+        let state = app.state::<AppState>();
+        let config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+        config.hide_on_blur && !config.pinned
+    };
+    if hide {
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.hide();
+            *BLUR_HIDDEN_AT.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        }
+    }
+}
+
+/// Clicking the tray icon or pressing the hotkey steals focus first, so the window
+/// is already gone by the time the toggle runs; that toggle shouldn't reopen it
+pub fn just_hidden_by_blur() -> bool {
+    BLUR_HIDDEN_AT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .is_some_and(|at| at.elapsed() < BLUR_TOGGLE_GRACE)
+}
+
+/// Keep the window above other apps while pinned; also turns off hide-on-blur
+pub fn apply_pinned(app: &AppHandle) {
+    let pinned = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).pinned;
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_always_on_top(pinned);
+    }
+}
+
+/// Apply the configured native effect. The background opacity is drawn by the
+/// UI, which gets the new appearance through "window-appearance-changed".
+pub fn apply_appearance(app: &AppHandle) {
+    let appearance = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).window_appearance;
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    // Effects a platform doesn't support are ignored by Tauri
+    let effect = match appearance.effect {
+        WindowEffect::None => None,
+        WindowEffect::Vibrancy => Some(Effect::UnderWindowBackground),
+        WindowEffect::Acrylic => Some(Effect::Acrylic),
+        WindowEffect::Mica => Some(Effect::Mica),
+    };
+    let effects = effect.map(|effect| {
+        EffectsBuilder::new()
+            .effect(effect)
+            .state(EffectState::FollowsWindowActiveState)
+            .build()
+    });
+    if let Err(e) = window.set_effects(effects) {
+        eprintln!("Warning: Failed to apply window effect: {}", e);
+    }
+    let _ = app.emit("window-appearance-changed", appearance);
+}
+
+/// Put every window in the configured light or dark mode, redraw the tray icon to
+/// match and send the UI the theme through "theme-changed"
+pub fn apply_theme(app: &AppHandle) {
+    let theme = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).theme.clone();
+    let native = match theme.mode {
+        ThemeMode::System => None,
+        ThemeMode::Light => Some(Theme::Light),
+        ThemeMode::Dark => Some(Theme::Dark),
+    };
+    for window in app.webview_windows().values() {
+        if let Err(e) = window.set_theme(native) {
+            eprintln!("Warning: Failed to set the window theme: {}", e);
+        }
+    }
+    tray::refresh_icon(app);
+    let _ = app.emit("theme-changed", theme);
+}
+
+/// Apply the remembered size at startup
+pub fn restore_size(app: &AppHandle, window: &WebviewWindow) {
+    let bounds = app.state::<AppState>().config.lock().unwrap_or_else(|e| e.into_inner()).window_bounds;
+    if let Some(bounds) = bounds {
+        let _ = window.set_size(LogicalSize::new(bounds.width, bounds.height));
+    }
+}
+
+fn current_bounds(window: &WebviewWindow) -> Option<WindowBounds> {
+    let scale = window.scale_factor().ok()?;
+    let position = window.outer_position().ok()?.to_logical::<f64>(scale);
+    let size = window.inner_size().ok()?.to_logical::<f64>(scale);
+    Some(WindowBounds {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+/// Called on move/resize; saves the bounds once the window has settled
+pub fn remember_bounds(app: &AppHandle) {
+    let generation = BOUNDS_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(SAVE_BOUNDS_DELAY);
+        if BOUNDS_GENERATION.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        // Screens can only be looked up from the main thread on macOS
+        let handle = app.clone();
+        let _ = handle.run_on_main_thread(move || save_bounds(&app));
+    });
+}
+
+fn save_bounds(app: &AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let Some(bounds) = current_bounds(&window) else {
+        return;
+    };
+    // Only a spot the user dragged the window to is worth remembering for the screen
+    let placed_at = *PLACED_AT.lock().unwrap_or_else(|e| e.into_inner());
+    let dragged = window.is_visible().unwrap_or(false)
+        && placed_at.is_some_and(|(x, y)| (bounds.x - x).abs() > 1.0 || (bounds.y - y).abs() > 1.0);
+    let screen = if dragged { window_screen(&window) } else { None };
+
+    let state = app.state::<AppState>();
+    let mut config = state.config.lock().unwrap_or_else(|e| e.into_inner());
+    let mut changed = config.window_bounds != Some(bounds);
+    if let Some(screen) = screen {
+        let position = ScreenPosition {
+            x: bounds.x - screen.area.x,
+            y: bounds.y - screen.area.y,
+        };
+        changed |= config.screen_positions.insert(screen.name, position) != Some(position);
+    }
+    if !changed {
+        return;
+    }
+    config.window_bounds = Some(bounds);
+    if let Err(e) = save_config(&config) {
+        eprintln!("Warning: Failed to save window position: {}", e);
+    }
+}
@@ -1,19 +1,28 @@
 import { useEffect, useState, useRef, useCallback, useMemo } from 'react';
-import { getTasks, saveTasks, completeTask, hideWindow, getHotkey, setHotkey, TaskState, Task, Note } from './store';
+import { getTasks, saveTasks, mergeTasks, SaveError, completeTask, undo as undoChange, redo as redoChange, hideWindow, getHotkey, setHotkey, onTasksChanged, onOpenSettings, onOpenPlanning, onOpenSummary, getDaySummary, DaySummary, getWindowAppearance, onWindowAppearanceChanged, TaskState, WindowAppearance, Task, Note, ActiveTimer, startTimer, stopTimer, getActiveTimer, onTimerChanged, onTimerTick, formatDuration } from './store';
 import './App.css';
 
-// Debounce helper
+// Debounce helper; `cancel` drops a call that hasn't run yet
 function debounce<T extends (...args: Parameters<T>) => void>(
   fn: T,
   delay: number
-): (...args: Parameters<T>) => void {
+): ((...args: Parameters<T>) => void) & { cancel: () => void } {
   let timeoutId: ReturnType<typeof setTimeout> | null = null;
-  return (...args: Parameters<T>) => {
+  const debounced = (...args: Parameters<T>) => {
     if (timeoutId) clearTimeout(timeoutId);
     timeoutId = setTimeout(() => fn(...args), delay);
   };
+  debounced.cancel = () => {
+    if (timeoutId) clearTimeout(timeoutId);
+    timeoutId = null;
+  };
+  return debounced;
 }
 
+// "Mar 2, 17:00" for the due marker next to a task
+const formatDue = (due: string) =>
+  new Date(due).toLocaleString(undefined, { month: 'short', day: 'numeric', hour: '2-digit', minute: '2-digit' });
+
 // Helper to render text with clickable links
 const renderTextWithLinks = (text: string) => {
   const urlRegex = /(https?:\/\/[^\s]+)/g;
@@ -42,7 +51,6 @@ const renderTextWithLinks = (text: string) => {
 type Pane = 'current' | 'shelf';
 
 const MAX_CURRENT = 10;
-const MAX_HISTORY = 50;
 
 // Convert keyboard event to hotkey string
 function eventToHotkeyString(e: KeyboardEvent): string | null {
@@ -80,7 +88,6 @@ function eventToHotkeyString(e: KeyboardEvent): string | null {
 
 function App() {
   const [tasks, setTasks] = useState<TaskState>({ current: [], shelf: [] });
-  const [history, setHistory] = useState<TaskState[]>([]);
   const [activePane, setActivePane] = useState<Pane>('current');
   const [selectedIndex, setSelectedIndex] = useState(0);
   const [editingIndex, setEditingIndex] = useState<number | null>(null);
@@ -94,37 +101,62 @@ function App() {
   const [isCreatingNote, setIsCreatingNote] = useState(false);
   const [showHelp, setShowHelp] = useState(false);
   const [showSettings, setShowSettings] = useState(false);
+  const [showPlanning, setShowPlanning] = useState(false);
+  const [summary, setSummary] = useState<DaySummary | null>(null);
   const [currentHotkey, setCurrentHotkey] = useState('');
   const [pendingHotkey, setPendingHotkey] = useState<string | null>(null);
   const [hotkeyError, setHotkeyError] = useState<string | null>(null);
   const [saveIndicator, setSaveIndicator] = useState(false);
+  const [timer, setTimer] = useState<ActiveTimer | null>(null);
   const inputRef = useRef<HTMLInputElement>(null);
   const noteInputRef = useRef<HTMLInputElement>(null);
   const tasksRef = useRef<TaskState>(tasks);
+  // The lists as last loaded from or saved to the backend, to merge with on a conflict
+  const baseRef = useRef<TaskState>(tasks);
 
   // Keep ref in sync with state
   useEffect(() => {
     tasksRef.current = tasks;
   }, [tasks]);
 
+  const loadTasks = useCallback((state: TaskState) => {
+    baseRef.current = state;
+    setTasks(state);
+  }, []);
+
+  // Save `state`, made from `base` by this window. When the lists changed elsewhere since
+  // (tray, scheduler), this window's changes are merged into them and that is saved instead
+  const saveLists = useCallback(async (state: TaskState, base: TaskState) => {
+    try {
+      const { revision } = await saveTasks(state);
+      baseRef.current = { ...state, revision };
+      setTasks(prev => ({ ...prev, revision }));
+    } catch (err) {
+      if ((err as SaveError)?.kind !== 'conflict') throw err;
+      const merged = await mergeTasks(base, state);
+      const { revision } = await saveTasks(merged);
+      loadTasks({ ...merged, revision });
+    }
+  }, [loadTasks]);
+
   // Debounced save to reduce disk writes with visual feedback
   const debouncedSave = useMemo(
-    () => debounce((state: TaskState) => {
-      saveTasks(state).then(() => {
+    () => debounce((state: TaskState, base: TaskState) => {
+      saveLists(state, base).then(() => {
         setSaveIndicator(true);
         setTimeout(() => setSaveIndicator(false), 600);
       });
     }, 300),
-    []
+    [saveLists]
   );
 
   const currentList = activePane === 'current' ? tasks.current : tasks.shelf;
   const otherList = activePane === 'current' ? tasks.shelf : tasks.current;
 
   useEffect(() => {
-    getTasks().then(setTasks);
+    getTasks().then(loadTasks);
     getHotkey().then(setCurrentHotkey);
-  }, []);
+  }, [loadTasks]);
 
   useEffect(() => {
     if (editingIndex !== null || isCreating) {
@@ -148,11 +180,7 @@ function App() {
   // Reload tasks when window regains focus to ensure fresh state
   useEffect(() => {
     const handleWindowFocus = () => {
-      getTasks().then((loadedTasks) => {
-        setTasks(loadedTasks);
-        // Clear history since we're loading fresh state from disk
-        setHistory([]);
-      });
+      getTasks().then(loadTasks);
     };
 
     window.addEventListener('focus', handleWindowFocus);
@@ -160,6 +188,74 @@ function App() {
     return () => {
       window.removeEventListener('focus', handleWindowFocus);
     };
+  }, [loadTasks]);
+
+  // Pick up changes made by the backend while the window is open
+  useEffect(() => {
+    const unlisten = onTasksChanged((state) => {
+      loadTasks(state);
+      setExpandedIndex(null);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [loadTasks]);
+
+  // The timer runs in the backend; the clock here just follows it
+  useEffect(() => {
+    getActiveTimer().then(setTimer);
+    const unlistenChanged = onTimerChanged(setTimer);
+    const unlistenTick = onTimerTick(setTimer);
+    return () => {
+      unlistenChanged.then((fn) => fn());
+      unlistenTick.then((fn) => fn());
+    };
+  }, []);
+
+  // Background opacity comes from config; native vibrancy/acrylic shows through it
+  useEffect(() => {
+    const applyAppearance = (appearance: WindowAppearance) => {
+      document.documentElement.style.setProperty('--bg-primary', `rgba(10, 10, 12, ${appearance.opacity})`);
+    };
+    getWindowAppearance().then(applyAppearance);
+    const unlisten = onWindowAppearanceChanged(applyAppearance);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Tray "Settings…" opens the settings panel
+  useEffect(() => {
+    const unlisten = onOpenSettings(() => {
+      setShowSettings(true);
+      setPendingHotkey(null);
+      setHotkeyError(null);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // The scheduler opens the window on the planning view at the start of the day
+  useEffect(() => {
+    const unlisten = onOpenPlanning(() => {
+      setShowHelp(false);
+      setShowPlanning(true);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Tray "Today's Summary…" opens the day's report
+  useEffect(() => {
+    const unlisten = onOpenSummary(async () => {
+      setShowHelp(false);
+      setSummary(await getDaySummary());
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
   }, []);
 
   // Handle hotkey capture in settings mode
@@ -201,35 +297,24 @@ function App() {
     }
   };
 
-  const pushHistory = useCallback((state: TaskState) => {
-    setHistory(prev => {
-      const newHistory = [...prev, state];
-      if (newHistory.length > MAX_HISTORY) {
-        return newHistory.slice(-MAX_HISTORY);
-      }
-      return newHistory;
-    });
-  }, []);
-
   const persist = useCallback((newState: TaskState) => {
-    // Use ref to get current state, avoiding stale closure
-    pushHistory(tasksRef.current);
     setTasks(newState);
-    debouncedSave(newState);
-  }, [pushHistory, debouncedSave]);
-
-  const undo = useCallback(async () => {
-    if (history.length > 0) {
-      const previousState = history[history.length - 1];
-      setHistory(prev => prev.slice(0, -1));
-      setTasks(previousState);
-      await saveTasks(previousState);
-      // Adjust selected index if needed
-      const list = activePane === 'current' ? previousState.current : previousState.shelf;
-      setSelectedIndex(prev => Math.min(prev, Math.max(0, list.length - 1)));
-      setExpandedIndex(null);
+    debouncedSave(newState, baseRef.current);
+  }, [debouncedSave]);
+
+  // Undo and redo live in the backend, which also records changes from the tray, capture
+  // prompts and scheduler. A save still waiting on the debounce goes first so it's part of
+  // the history; the result comes back through onTasksChanged
+  const stepHistory = useCallback(async (back: boolean) => {
+    debouncedSave.cancel();
+    await saveLists(tasksRef.current, baseRef.current);
+    try {
+      await (back ? undoChange() : redoChange());
+    } catch {
+      // Nothing to undo or redo
     }
-  }, [history, activePane]);
+    setExpandedIndex(null);
+  }, [debouncedSave, saveLists]);
 
   const clampIndex = useCallback((idx: number, list: Task[]) => {
     if (list.length === 0) return 0;
@@ -380,7 +465,35 @@ function App() {
       case 'u':
         // Undo last action
         e.preventDefault();
-        await undo();
+        await stepHistory(true);
+        break;
+      case 'U':
+        // Redo what undo reverted
+        e.preventDefault();
+        await stepHistory(false);
+        break;
+      case 't': {
+        // Start or stop timing the selected task; a task just added has no id until it's saved
+        const task = currentList[clampIndex(selectedIndex, currentList)];
+        if (expandedIndex !== null || task?.id === undefined) break;
+        try {
+          if (timer?.task_id === task.id) {
+            await stopTimer();
+          } else {
+            await startTimer(task.id);
+          }
+        } catch (err) {
+          console.error('Timer failed:', err);
+        }
+        break;
+      }
+      case 'z':
+      case 'Z':
+        if (e.metaKey || e.ctrlKey) {
+          // Cmd+Z / Ctrl+Z undoes, with Shift it redoes
+          e.preventDefault();
+          await stepHistory(!e.shiftKey);
+        }
         break;
       case 'Enter':
         if (e.metaKey || e.ctrlKey) {
@@ -490,7 +603,11 @@ function App() {
         }
         break;
       case 'Escape':
-        if (showHelp) {
+        if (summary) {
+          setSummary(null);
+        } else if (showPlanning) {
+          setShowPlanning(false);
+        } else if (showHelp) {
           setShowHelp(false);
         } else if (expandedIndex !== null) {
           setExpandedIndex(null);
@@ -509,7 +626,7 @@ function App() {
         }
         break;
     }
-  }, [activePane, currentList, otherList, selectedIndex, tasks, editingIndex, isCreating, persist, clampIndex, clampNoteIndex, expandedIndex, selectedNoteIndex, editingNoteIndex, isCreatingNote, undo, showHelp, showSettings]);
+  }, [activePane, currentList, otherList, selectedIndex, tasks, editingIndex, isCreating, persist, clampIndex, clampNoteIndex, expandedIndex, selectedNoteIndex, editingNoteIndex, isCreatingNote, stepHistory, showHelp, showSettings, showPlanning, summary, timer]);
 
   useEffect(() => {
     window.addEventListener('keydown', handleKeyDown);
@@ -640,7 +757,7 @@ function App() {
     
     return (
       <div className={`pane ${isActive ? 'active' : ''} ${shouldShake ? 'shake' : ''}`}>
-        <div className={`pane-header ${isCurrent ? 'current' : 'shelf'}`}>
+        <div className={`pane-header ${isCurrent ? 'current' : 'shelf'}`} data-tauri-drag-region>
           {pane.toUpperCase()}
         </div>
         <div className="pane-content">
@@ -681,6 +798,14 @@ function App() {
                       {task.notes.length > 0 && (
                         <span className="note-indicator"> ({task.notes.length})</span>
                       )}
+                      {task.due && (
+                        <span className="due-indicator"> {formatDue(task.due)}</span>
+                      )}
+                      {timer && timer.task_id === task.id ? (
+                        <span className="timer-indicator running"> {formatDuration(timer.total)}</span>
+                      ) : task.time_spent ? (
+                        <span className="timer-indicator"> {formatDuration(task.time_spent)}</span>
+                      ) : null}
                     </span>
                   )}
                 </div>
@@ -794,13 +919,55 @@ function App() {
               <div className="help-category">Actions</div>
               <div className="help-row"><span className="help-key">⌘↵</span><span>Complete task / toggle note</span></div>
               <div className="help-row"><span className="help-key">⌘⌫</span><span>Delete</span></div>
+              <div className="help-row"><span className="help-key">t</span><span>Start / stop timer</span></div>
               <div className="help-row"><span className="help-key">u</span><span>Undo</span></div>
+              <div className="help-row"><span className="help-key">U</span><span>Redo</span></div>
               <div className="help-row"><span className="help-key">Esc</span><span>Close / Hide</span></div>
             </div>
             <div className="help-footer">Press ? or Esc to close</div>
           </div>
         </div>
       )}
+      {showPlanning && (
+        <div className="help-overlay" onClick={() => setShowPlanning(false)}>
+          <div className="help-content planning-content" onClick={(e) => e.stopPropagation()}>
+            <div className="help-title">Plan Today</div>
+            <div className="help-section">
+              <div className="help-category">Current ({tasks.current.length})</div>
+              {tasks.current.length === 0 && <div className="help-row">Nothing planned yet</div>}
+              {tasks.current.map((task, idx) => (
+                <div key={idx} className="help-row"><span>{idx + 1}. {task.text}</span></div>
+              ))}
+            </div>
+            <div className="help-section">
+              <div className="help-category">Shelf ({tasks.shelf.length})</div>
+              {tasks.shelf.map((task, idx) => (
+                <div key={idx} className="help-row"><span>• {task.text}</span></div>
+              ))}
+            </div>
+            <div className="help-footer">Press Esc to start the day</div>
+          </div>
+        </div>
+      )}
+      {summary && (
+        <div className="help-overlay" onClick={() => setSummary(null)}>
+          <div className="help-content planning-content" onClick={(e) => e.stopPropagation()}>
+            <div className="help-title">Today</div>
+            <div className="help-section">
+              <div className="help-category">Completed ({summary.completed.length})</div>
+              {summary.completed.length === 0 && <div className="help-row">Nothing yet</div>}
+              {summary.completed.map((text, idx) => (
+                <div key={idx} className="help-row"><span>✓ {text}</span></div>
+              ))}
+            </div>
+            <div className="help-section">
+              <div className="help-category">Still open</div>
+              <div className="help-row"><span>{summary.open} in current</span></div>
+            </div>
+            <div className="help-footer">Press Esc to close</div>
+          </div>
+        </div>
+      )}
       {showSettings && (
         <div className="help-overlay" onClick={() => { setShowSettings(false); setPendingHotkey(null); setHotkeyError(null); }}>
           <div className="help-content settings-content" onClick={(e) => e.stopPropagation()}>
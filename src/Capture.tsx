@@ -0,0 +1,52 @@
+import { useEffect, useRef, useState } from 'react';
+import { submitCapture, hideCapture, onCaptureOpened } from './store';
+import './App.css';
+
+// Contents of the small "capture" window: one input, Enter adds, Esc dismisses
+function Capture() {
+  const [value, setValue] = useState('');
+  const inputRef = useRef<HTMLInputElement>(null);
+
+  useEffect(() => {
+    const unlisten = onCaptureOpened(() => {
+      setValue('');
+      inputRef.current?.focus();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const handleKeyDown = async (e: React.KeyboardEvent) => {
+    if (e.key === 'Enter') {
+      e.preventDefault();
+      const text = value;
+      setValue('');
+      await submitCapture(text);
+    } else if (e.key === 'Escape') {
+      e.preventDefault();
+      setValue('');
+      await hideCapture();
+    }
+  };
+
+  return (
+    <div className="capture">
+      <div className="task selected creating">
+        <span className="cursor">›</span>
+        <input
+          ref={inputRef}
+          type="text"
+          value={value}
+          onChange={(e) => setValue(e.target.value)}
+          onKeyDown={handleKeyDown}
+          className="task-input"
+          placeholder="New task..."
+          autoFocus
+        />
+      </div>
+    </div>
+  );
+}
+
+export default Capture;
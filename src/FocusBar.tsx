@@ -0,0 +1,43 @@
+import { useEffect, useState } from 'react';
+import { ActiveTimer, formatDuration, getActiveTimer, getFocusTask, onFocusTaskChanged, onTimerChanged, onTimerTick } from './store';
+import './App.css';
+
+// Contents of the "focus-bar" window: just the task being worked on, and the timer's clock while it runs
+function FocusBar() {
+  const [task, setTask] = useState<string | null>(null);
+  const [timer, setTimer] = useState<ActiveTimer | null>(null);
+
+  useEffect(() => {
+    getFocusTask().then(setTask);
+    const unlisten = onFocusTaskChanged(setTask);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    getActiveTimer().then(setTimer);
+    const unlistenChanged = onTimerChanged(setTimer);
+    const unlistenTick = onTimerTick(setTimer);
+    return () => {
+      unlistenChanged.then((fn) => fn());
+      unlistenTick.then((fn) => fn());
+    };
+  }, []);
+
+  return (
+    <div className="focus-bar" data-tauri-drag-region>
+      <span className="cursor" data-tauri-drag-region>›</span>
+      <span className={task ? 'focus-bar-text' : 'focus-bar-text empty'} data-tauri-drag-region>
+        {task ?? 'No current task'}
+      </span>
+      {timer && (
+        <span className="focus-bar-timer" title={timer.text} data-tauri-drag-region>
+          {formatDuration(timer.elapsed)}
+        </span>
+      )}
+    </div>
+  );
+}
+
+export default FocusBar;
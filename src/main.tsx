@@ -1,9 +1,15 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import App from "./App";
+import Capture from "./Capture";
+import FocusBar from "./FocusBar";
+
+// Every window loads this page; the quick-capture and focus-bar windows only need their own view
+const label = getCurrentWindow().label;
 
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
-    <App />
+    {label === "capture" ? <Capture /> : label === "focus-bar" ? <FocusBar /> : <App />}
   </React.StrictMode>,
 );
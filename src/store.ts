@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 
 export interface Note {
   text: string;
@@ -6,21 +7,660 @@ export interface Note {
 }
 
 export interface Task {
+  // Stable id given by the backend when the task is added; keep it when editing
+  id?: number;
   text: string;
   notes: Note[];
+  // Local time without offset, e.g. "2026-03-02T17:00:00"; reminders fire from the backend
+  due?: string;
+  // A shelved task returns to current at this local time
+  snoozed_until?: string;
+  // Client or project; see project_logs in config
+  project?: string;
+  priority?: Priority;
+  // Local time the task was added; filled in by the backend
+  created?: string;
+  // Seconds timed on the task; added to by the backend when its timer stops
+  time_spent?: number;
+}
+
+export type Priority = 'low' | 'medium' | 'high';
+
+export type Weekday = 'Mon' | 'Tue' | 'Wed' | 'Thu' | 'Fri' | 'Sat' | 'Sun';
+
+export type Recurrence =
+  | 'daily'
+  | 'weekdays'
+  | { weekly: Weekday[] }
+  | { monthly: number }
+  | { every_days: number };
+
+export interface RecurringTask {
+  task: Task;
+  rule: Recurrence;
+  at: string;
+  next?: string | null;
 }
 
 export interface TaskState {
   current: Task[];
   shelf: Task[];
+  // Templates the backend copies into current on schedule; keep them when saving
+  recurring?: RecurringTask[];
+  // Goes up with every save; a save has to carry the revision it last saw
+  revision?: number;
+}
+
+// Tasks completed today (oldest first) and how many are still in current
+export interface DaySummary {
+  completed: string[];
+  open: number;
+}
+
+// Today's completions against the daily_goal setting; goal is 0 when none is set
+export interface GoalProgress {
+  date: string | null;
+  goal: number;
+  completed: number;
+  reached: boolean;
+}
+
+// A line of done.jsonl
+export interface DoneRecord {
+  text: string;
+  notes?: Note[];
+  completed_at: string;
+  due?: string;
+  tags?: string[];
+  project?: string;
+  priority?: Priority;
+  // When the task was added, if known
+  created?: string;
+}
+
+// A completed task parsed from done.md or an archive
+export interface DoneEntry {
+  line: number;
+  date: string;
+  time: string | null;
+  task: Task;
+}
+
+// A done_*.md archive; first/last are the oldest and newest entry dates
+export interface ArchiveInfo {
+  name: string;
+  first: string | null;
+  last: string | null;
+  entries: number;
+  bytes: number;
+  compressed: boolean;
+}
+
+export interface DedupeReport {
+  merged: number;
+  removed: number;
+  total: number;
+}
+
+// One archive pruneArchives compressed or deleted (or would, on a dry run)
+export interface PruneAction {
+  name: string;
+  action: 'compress' | 'delete';
+  bytes: number;
+}
+
+// Inclusive YYYY-MM-DD bounds; leave one out for an open end
+export interface DateRange {
+  from?: string;
+  to?: string;
+}
+
+// A search hit in the done history; source is "done.md", an archive name or "done.jsonl"
+export interface DoneMatch {
+  source: string;
+  date: string;
+  time: string | null;
+  task: Task;
+}
+
+export type DoneFormat = 'markdown' | 'org' | 'csv';
+
+// Where completed tasks are logged; path may start with ~/ and defaults to ~/.tasks/done.<ext>
+export interface DoneLogConfig {
+  path?: string | null;
+  format: DoneFormat;
+}
+
+// A week of completions; per_day runs from the week_start setting for seven days as [date, count]
+export interface WeeklyRollup {
+  week_start: string;
+  total: number;
+  per_day: [string, number][];
+  per_tag: [string, number][];
+  file: string;
+}
+
+// A search hit; index is the position in current/shelf, or the done entry's line id
+export interface SearchResult {
+  list: 'current' | 'shelf' | 'done';
+  index: number;
+  date: string | null;
+  text: string;
+  // Set when a note matched rather than the text
+  note: number | null;
+  snippet: string;
+  // Fuzzy match score; higher is better and results come sorted by it
+  score: number;
+}
+
+export interface QueryMatch {
+  list: 'current' | 'shelf';
+  index: number;
+  task: Task;
+}
+
+export interface SavedFilter {
+  name: string;
+  query: string;
+}
+
+export interface SmartList {
+  name: string;
+  query: string;
+  matches: QueryMatch[];
+  // Set when the saved query no longer parses
+  error: string | null;
+}
+
+export interface Suggestion {
+  kind: 'task' | 'tag' | 'project';
+  text: string;
+  count: number;
+}
+
+export interface Duplicate {
+  list: 'current' | 'shelf';
+  index: number;
+  text: string;
+}
+
+export interface DuplicateGroup {
+  text: string;
+  tasks: Duplicate[];
+}
+
+export interface NoteMatch {
+  note: number;
+  snippet: string;
+  score: number;
+}
+
+export type TaskStatus = 'overdue' | 'due_today' | 'scheduled' | 'unscheduled' | 'snoozed';
+
+export interface ViewOptions {
+  list?: 'current' | 'shelf';
+  tag?: string;
+  status?: TaskStatus;
+  // Same syntax as queryTasks
+  query?: string;
+  sort?: 'manual' | 'due' | 'priority' | 'age';
+  reverse?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export type Granularity = 'day' | 'week' | 'month';
+
+export interface Stats {
+  granularity: Granularity;
+  total: number;
+  // Every day, week (from the week_start setting) or month in the range, empty ones included
+  buckets: { start: string; completed: number }[];
+}
+
+export interface Heatmap {
+  year: number;
+  // 0 when Jan 1 falls on the week_start setting
+  first_weekday: number;
+  // One count per day from Jan 1
+  counts: number[];
+  max: number;
+}
+
+export interface FlowMetrics {
+  // Creation to completion, over completions that know both
+  lead_time: { count: number; average_hours: number; median_hours: number };
+  // Open tasks per age band, youngest first
+  wip_age: { label: string; open: number }[];
+  wip_unknown_age: number;
+  weekly_throughput: { start: string; completed: number }[];
+  average_weekly_throughput: number;
+}
+
+export interface CompletionPatterns {
+  total: number;
+  // 24 counts, midnight first; completions logged without a time are left out
+  by_hour: number[];
+  // 7 counts, starting with the week_start setting
+  by_weekday: number[];
+  // grid[weekday][hour]
+  grid: number[][];
+  without_time: number;
+}
+
+export interface Streak {
+  current: number;
+  longest: number;
+  today_done: boolean;
+}
+
+export interface WeeklyReview {
+  week_start: string;
+  completed: number;
+  // Busiest project first; null groups tasks without one
+  by_project: { project: string | null; tasks: string[] }[];
+  added_open: QueryMatch[];
+  stale_shelf: QueryMatch[];
+  streak: Streak;
+  file: string | null;
+}
+
+export interface Forecast {
+  target: string;
+  remaining: number;
+  // Completions per working day lately; the project's own when project_pace
+  per_day: number;
+  project_pace: boolean;
+  // null when nothing was completed lately to go by
+  working_days: number | null;
+  finish: string | null;
+  message: string;
+}
+
+export interface YearInReview {
+  year: number;
+  total: number;
+  active_days: number;
+  // January to December
+  per_month: number[];
+  busiest_weeks: { week_start: string; completed: number }[];
+  longest_streak: number;
+  top_projects: [string, number][];
+  top_tags: [string, number][];
+  markdown: string;
+  file: string | null;
+}
+
+export interface Breakdown {
+  dimension: 'tag' | 'project';
+  completed_total: number;
+  open_total: number;
+  // Percentages are 0–100; with several tags a task counts toward each
+  rows: { key: string; completed: number; completed_percent: number; open: number; open_percent: number }[];
+}
+
+export interface BurndownDay {
+  date: string;
+  open: number;
+  done: number;
+}
+
+export type StatsMetric = 'daily' | 'weekly' | 'monthly' | 'patterns' | 'tags' | 'projects' | 'flow';
+
+export type HotkeyAction = 'toggle' | 'quick_add' | 'complete_top_task' | 'start_pomodoro';
+
+export type HotkeyStatus = { state: 'active' } | { state: 'inactive'; reason: string };
+
+// 'peek' shows the window only while the toggle shortcut is held
+export type ToggleMode = 'toggle' | 'peek';
+
+export type ModifierKey =
+  | 'left_cmd'
+  | 'right_cmd'
+  | 'left_shift'
+  | 'right_shift'
+  | 'left_option'
+  | 'right_option'
+  | 'left_control'
+  | 'right_control';
+
+// 'last_position' reopens the window wherever it was last dragged to
+export type WindowAnchor =
+  | 'top_right'
+  | 'top_left'
+  | 'bottom_right'
+  | 'bottom_left'
+  | 'center'
+  | 'cursor'
+  | 'tray'
+  | 'last_position';
+
+export type WindowEffect = 'none' | 'vibrancy' | 'acrylic' | 'mica';
+
+// opacity applies to the background only; effect is the native blur behind it
+export interface WindowAppearance {
+  opacity: number;
+  effect: WindowEffect;
+}
+
+export type ThemeMode = 'system' | 'light' | 'dark';
+
+// accent is "#rrggbb", or null for the default
+export interface ThemeConfig {
+  mode: ThemeMode;
+  accent: string | null;
+}
+
+// Offset from the anchor in logical pixels, pointing into the screen
+export interface WindowMargin {
+  x: number;
+  y: number;
+}
+
+export type TrayIconStyle = 'template' | 'colored' | 'badge' | 'progress';
+
+export type TrayMenuEntry =
+  | 'tasks'
+  | 'recent'
+  | 'summary'
+  | 'quick_add'
+  | 'dnd'
+  | 'pause_hotkeys'
+  | 'focus_bar'
+  | 'pomodoro'
+  | 'settings'
+  | 'archive'
+  | 'separator'
+  | 'quit';
+
+export type TrayClickAction =
+  | 'toggle_window'
+  | 'show_menu'
+  | 'quick_add'
+  | 'quick_add_clipboard'
+  | 'open_settings'
+  | 'archive_completed'
+  | 'nothing';
+
+export interface TrayClickConfig {
+  left: TrayClickAction;
+  right: TrayClickAction;
+  middle: TrayClickAction;
+  double: TrayClickAction;
+}
+
+// Everything in config.json; see the README's config table for what each does
+export interface AppConfig {
+  // Layout of config.json, set by the app; updateConfig rejects it
+  version: number;
+  hotkeys: Partial<Record<HotkeyAction, string>>;
+  toggle_mode: ToggleMode;
+  tray_task_limit: number;
+  tray_recent_limit: number;
+  tray_icon_style: TrayIconStyle;
+  tray_menu: TrayMenuEntry[];
+  tray_click: TrayClickConfig;
+  double_tap: ModifierKey | null;
+  window_anchor: WindowAnchor;
+  hide_on_blur: boolean;
+  pinned: boolean;
+  panel_mode: boolean;
+  window_margins: Partial<Record<WindowAnchor, WindowMargin>>;
+  screen_positions: Record<string, { x: number; y: number }>;
+  focus_bar: boolean;
+  window_appearance: WindowAppearance;
+  theme: ThemeConfig;
+  work_week: { days: Weekday[]; holidays: string[] };
+  done_log: DoneLogConfig;
+  done_time_format: string;
+  date_format: string;
+  locale: string | null;
+  week_start: Weekday;
+  project_logs: 'off' | 'separate' | 'prefix';
+  archive_retention: { keep_months: number | null; max_total_mb: number | null; compress: boolean };
+  rotate_done_monthly: boolean;
+  auto_archive: 'off' | 'daily' | 'weekly' | 'monthly';
+  overdue: { enabled: boolean; renotify_minutes: number[]; badge: boolean };
+  snooze_again_minutes: number;
+  idle_minutes: number;
+  reminder_lead_minutes: number[];
+  planning_time: string | null;
+  summary_time: string | null;
+  summary_opens_window: boolean;
+  window_bounds: { x: number; y: number; width: number; height: number } | null;
+  saved_filters: SavedFilter[];
+  daily_goal: number;
+  launch_at_login: boolean;
+  pomodoro: PomodoroConfig;
+}
+
+export interface PomodoroConfig {
+  work_minutes: number;
+  short_break_minutes: number;
+  long_break_minutes: number;
+  // Work phases before each long break
+  long_break_every: number;
+  // Notification sound names by the phase starting; '' for the platform's usual one
+  sounds: { work: string; short_break: string; long_break: string };
+}
+
+// Whether the app is registered to start (hidden, in the menu bar) at login
+export async function getAutostart(): Promise<boolean> {
+  return await invoke<boolean>('get_autostart');
+}
+
+export async function setAutostart(enabled: boolean): Promise<void> {
+  await invoke('set_autostart', { enabled });
+}
+
+export async function getConfig(): Promise<AppConfig> {
+  return await invoke<AppConfig>('get_config');
+}
+
+// Changes only the settings in `patch` (null resets one to its default); rejects with
+// "<setting>: <reason>" and saves nothing if any of them is invalid
+export async function updateConfig(patch: { [K in keyof AppConfig]?: AppConfig[K] | null }): Promise<AppConfig> {
+  return await invoke<AppConfig>('update_config', { patch });
+}
+
+// A named data directory with its own tasks, done log and settings
+export interface Profile {
+  name: string;
+  data_dir: string;
+}
+
+// `profiles` leaves out "default", which is always there and lives in ~/.tasks
+export interface Profiles {
+  active: string;
+  profiles: Profile[];
+}
+
+export async function getProfiles(): Promise<Profiles> {
+  return await invoke<Profiles>('get_profiles');
+}
+
+// Adds a profile or changes the data directory of the one with this name (not the one in use)
+export async function saveProfile(name: string, dataDir: string): Promise<void> {
+  await invoke('save_profile', { name, dataDir });
+}
+
+// Forgets a profile without touching its files
+export async function deleteProfile(name: string): Promise<void> {
+  await invoke('delete_profile', { name });
+}
+
+// Loads the profile's tasks and settings in place of the current ones; tasks-changed,
+// config-changed and profile-changed follow
+export async function switchProfile(name: string): Promise<void> {
+  await invoke('switch_profile', { name });
+}
+
+export async function onProfileChanged(handler: (name: string) => void): Promise<UnlistenFn> {
+  return await listen<string>('profile-changed', (event) => handler(event.payload));
+}
+
+// Writes every setting except window positions to an absolute (or ~/) path; .toml paths get TOML
+export async function exportSettings(path: string): Promise<void> {
+  await invoke('export_settings', { path });
+}
+
+export interface ImportReport {
+  applied: string[];
+  // Unknown to this version, kept per machine, or macOS-only elsewhere
+  skipped: string[];
+}
+
+// Applies the settings in an exported file (or another machine's config); rejects and changes
+// nothing if any of them is invalid
+export async function importSettings(path: string): Promise<ImportReport> {
+  return await invoke<ImportReport>('import_settings', { path });
+}
+
+// Labels of the changes undo and redo would step over next, e.g. "Snooze"; null when there's none
+export interface UndoStatus {
+  undo: string | null;
+  redo: string | null;
+}
+
+// Reverts the latest change to the task lists, wherever it came from (window, tray, capture,
+// scheduler); resolves to its label and rejects when there's nothing to undo
+export async function undo(): Promise<string> {
+  return await invoke<string>('undo');
+}
+
+export async function redo(): Promise<string> {
+  return await invoke<string>('redo');
+}
+
+export async function getUndoStatus(): Promise<UndoStatus> {
+  return await invoke<UndoStatus>('get_undo_status');
+}
+
+export async function onUndoChanged(handler: (status: UndoStatus) => void): Promise<UnlistenFn> {
+  return await listen<UndoStatus>('undo-changed', (event) => handler(event.payload));
+}
+
+export type TaskChange =
+  | { kind: 'added'; list: 'current' | 'shelf'; text: string }
+  | { kind: 'edited'; from: string; to: string }
+  | { kind: 'note_added' | 'note_completed' | 'note_reopened' | 'note_removed'; text: string }
+  | { kind: 'moved'; from: 'current' | 'shelf'; to: 'current' | 'shelf' }
+  | { kind: 'completed' }
+  | { kind: 'removed' };
+
+export type TaskEvent = TaskChange & {
+  // Local time without offset
+  at: string;
+  task: number;
+};
+
+// Everything that happened to the task with this id, oldest first
+export async function getTaskHistory(taskId: number): Promise<TaskEvent[]> {
+  return await invoke<TaskEvent[]>('get_task_history', { taskId });
+}
+
+// The task lists as they were at a local time ("2026-03-02T17:00:00"), rebuilt from the last
+// snapshot before it and the task history; tasks added since the snapshot only have their
+// text, notes and list
+export async function getStateAt(at: string): Promise<TaskState> {
+  return await invoke<TaskState>('get_state_at', { at });
+}
+
+export type AuditSource = 'ui' | 'capture' | 'tray' | 'hotkey' | 'scheduler';
+
+export interface ListSizes {
+  current: number;
+  shelf: number;
+  recurring: number;
+}
+
+export interface AuditEntry {
+  // Local time without offset
+  at: string;
+  source: AuditSource;
+  // As the undo menu names it, e.g. "Snooze"
+  action: string;
+  before: ListSizes;
+  after: ListSizes;
+  // The tasks it touched, e.g. 'added "Call Sam" to current'
+  changes: string[];
+}
+
+// Every change to the task lists made on the days in range, oldest first
+export async function getAuditLog(range: DateRange): Promise<AuditEntry[]> {
+  return await invoke<AuditEntry[]>('get_audit_log', { range });
+}
+
+export interface SnapshotInfo {
+  id: string;
+  label: string;
+  // Local time without offset
+  created: string;
+  // Tasks in current and on the shelf
+  tasks: number;
+}
+
+// Saves the task lists and settings as they are now under a name
+export async function createSnapshot(label: string): Promise<SnapshotInfo> {
+  return await invoke<SnapshotInfo>('create_snapshot', { label });
+}
+
+// Newest first
+export async function listSnapshots(): Promise<SnapshotInfo[]> {
+  return await invoke<SnapshotInfo[]>('list_snapshots');
+}
+
+// Takes a snapshot of the current lists and settings first, so a restore can be reversed
+export async function restoreSnapshot(id: string): Promise<void> {
+  await invoke('restore_snapshot', { id });
+}
+
+export async function deleteSnapshot(id: string): Promise<void> {
+  await invoke('delete_snapshot', { id });
+}
+
+export interface DeletedTask {
+  task: Task;
+  // The list it was deleted from
+  list: 'current' | 'shelf';
+  // Local time without offset
+  deleted_at: string;
+}
+
+// Tasks deleted from the window in the last 30 days, newest first
+export async function listRecentlyDeleted(): Promise<DeletedTask[]> {
+  return await invoke<DeletedTask[]>('list_recently_deleted');
+}
+
+// Puts the task back in the list it was deleted from; resolves to it
+export async function recoverDeleted(id: number): Promise<Task> {
+  return await invoke<Task>('recover_deleted', { id });
 }
 
 export async function getTasks(): Promise<TaskState> {
   return await invoke<TaskState>('get_tasks');
 }
 
-export async function saveTasks(state: TaskState): Promise<void> {
-  await invoke('save_state', { newState: state });
+// Resolves to any tasks in `state` that duplicate each other (same words, ignoring case and punctuation)
+export interface Saved {
+  // Revision the lists are at now, for the next save
+  revision: number;
+  duplicates: DuplicateGroup[];
+}
+
+// Rejection of saveTasks: 'conflict' when the lists changed elsewhere since the revision
+// the save carried, with what they are now
+export type SaveError =
+  | { kind: 'conflict'; current: TaskState }
+  | { kind: 'failed'; message: string };
+
+export async function saveTasks(state: TaskState): Promise<Saved> {
+  return await invoke<Saved>('save_state', { newState: state });
+}
+
+// This window's changes since it loaded `base`, made to the lists as they are now
+export async function mergeTasks(base: TaskState, ours: TaskState): Promise<TaskState> {
+  return await invoke<TaskState>('merge_tasks', { base, ours });
 }
 
 export async function completeTask(task: Task): Promise<void> {
@@ -28,6 +668,43 @@ export async function completeTask(task: Task): Promise<void> {
   await invoke('complete_task', { task });
 }
 
+// Adds the task either way; resolves to the open tasks it duplicates
+export async function quickAdd(text: string): Promise<Duplicate[]> {
+  return await invoke<Duplicate[]>('quick_add', { text });
+}
+
+export async function findDuplicates(): Promise<DuplicateGroup[]> {
+  return await invoke<DuplicateGroup[]>('find_duplicates');
+}
+
+// `at` is a time of day like "09:00"; defaults to midnight
+export async function addRecurring(text: string, rule: Recurrence, at?: string): Promise<void> {
+  await invoke('add_recurring', { text, rule, at });
+}
+
+// Shelves current[index] until `until` (local time, e.g. "2026-03-02T09:00:00")
+export async function snoozeTask(index: number, until: string): Promise<void> {
+  await invoke('snooze_task', { index, until });
+}
+
+// Shelves the tasks that last came back from a snooze again; returns how many moved
+export async function snoozeAgain(): Promise<number> {
+  return await invoke<number>('snooze_again');
+}
+
+export async function showCapture(): Promise<void> {
+  await invoke('show_capture');
+}
+
+export async function hideCapture(): Promise<void> {
+  await invoke('hide_capture');
+}
+
+// Adds the text as a task (if not blank) and closes the capture window
+export async function submitCapture(text: string): Promise<void> {
+  await invoke('submit_capture', { text });
+}
+
 export async function hideWindow(): Promise<void> {
   await invoke('hide_window');
 }
@@ -43,3 +720,499 @@ export async function getHotkey(): Promise<string> {
 export async function setHotkey(hotkey: string): Promise<void> {
   await invoke('set_hotkey', { hotkey });
 }
+
+export async function getHotkeys(): Promise<Partial<Record<HotkeyAction, string>>> {
+  return await invoke<Partial<Record<HotkeyAction, string>>>('get_hotkeys');
+}
+
+export async function setActionHotkey(action: HotkeyAction, hotkey: string): Promise<void> {
+  await invoke('set_action_hotkey', { action, hotkey });
+}
+
+export async function clearActionHotkey(action: HotkeyAction): Promise<void> {
+  await invoke('clear_action_hotkey', { action });
+}
+
+export async function getHotkeyStatus(): Promise<Partial<Record<HotkeyAction, HotkeyStatus>>> {
+  return await invoke<Partial<Record<HotkeyAction, HotkeyStatus>>>('get_hotkey_status');
+}
+
+export async function getToggleMode(): Promise<ToggleMode> {
+  return await invoke<ToggleMode>('get_toggle_mode');
+}
+
+export async function setToggleMode(mode: ToggleMode): Promise<void> {
+  await invoke('set_toggle_mode', { mode });
+}
+
+export async function getDoubleTap(): Promise<ModifierKey | null> {
+  return await invoke<ModifierKey | null>('get_double_tap');
+}
+
+// macOS only; rejects if Accessibility access hasn't been granted yet
+export async function setDoubleTap(key: ModifierKey | null): Promise<void> {
+  await invoke('set_double_tap', { key });
+}
+
+// Modifier bits for setHotkeyRaw, matching the backend's keyboard-types Modifiers
+export const HOTKEY_MODIFIERS = {
+  alt: 0x1,
+  ctrl: 0x8,
+  shift: 0x200,
+  cmd: 0x2000,
+} as const;
+
+// Bind a recorded key press; `code` is KeyboardEvent.code. Resolves to the saved hotkey string.
+export async function setHotkeyRaw(modifiers: number, code: string, action?: HotkeyAction): Promise<string> {
+  return await invoke<string>('set_hotkey_raw', { modifiers, code, action: action ?? null });
+}
+
+// Rejects with the reason (e.g. already in use by another app) if the hotkey can't be bound
+export async function validateHotkey(hotkey: string, action?: HotkeyAction): Promise<void> {
+  await invoke('validate_hotkey', { hotkey, action: action ?? null });
+}
+
+export async function getTrayIconStyle(): Promise<TrayIconStyle> {
+  return await invoke<TrayIconStyle>('get_tray_icon_style');
+}
+
+export async function setTrayIconStyle(style: TrayIconStyle): Promise<void> {
+  await invoke('set_tray_icon_style', { style });
+}
+
+export async function getTrayMenu(): Promise<TrayMenuEntry[]> {
+  return await invoke<TrayMenuEntry[]>('get_tray_menu');
+}
+
+export async function setTrayMenu(entries: TrayMenuEntry[]): Promise<void> {
+  await invoke('set_tray_menu', { entries });
+}
+
+export async function getTrayClick(): Promise<TrayClickConfig> {
+  return await invoke<TrayClickConfig>('get_tray_click');
+}
+
+export async function setTrayClick(actions: TrayClickConfig): Promise<void> {
+  await invoke('set_tray_click', { actions });
+}
+
+export async function getWindowAnchor(): Promise<WindowAnchor> {
+  return await invoke<WindowAnchor>('get_window_anchor');
+}
+
+// Pass a margin to also change the offset stored for this anchor
+export async function setWindowAnchor(anchor: WindowAnchor, margin?: WindowMargin): Promise<void> {
+  await invoke('set_window_anchor', { anchor, margin: margin ?? null });
+}
+
+export async function getHideOnBlur(): Promise<boolean> {
+  return await invoke<boolean>('get_hide_on_blur');
+}
+
+export async function setHideOnBlur(enabled: boolean): Promise<void> {
+  await invoke('set_hide_on_blur', { enabled });
+}
+
+export async function getPinned(): Promise<boolean> {
+  return await invoke<boolean>('get_pinned');
+}
+
+// Pinned keeps the window above other apps and turns off hide-on-blur
+export async function setPinned(pinned: boolean): Promise<void> {
+  await invoke('set_pinned', { pinned });
+}
+
+export async function getPanelMode(): Promise<boolean> {
+  return await invoke<boolean>('get_panel_mode');
+}
+
+// macOS only: show the window without taking focus from the app in front
+export async function setPanelMode(enabled: boolean): Promise<void> {
+  await invoke('set_panel_mode', { enabled });
+}
+
+export async function getFocusBar(): Promise<boolean> {
+  return await invoke<boolean>('get_focus_bar');
+}
+
+// Shows or hides the slim always-on-top strip with the current focus task
+export async function setFocusBar(enabled: boolean): Promise<void> {
+  await invoke('set_focus_bar', { enabled });
+}
+
+export async function getFocusTask(): Promise<string | null> {
+  return await invoke<string | null>('get_focus_task');
+}
+
+export interface ActiveTimer {
+  task_id: number;
+  text: string;
+  // Local time without offset
+  started: string;
+  // Seconds since started
+  elapsed: number;
+  // Seconds timed on the task in all, this run included
+  total: number;
+}
+
+// Times the task with this id, stopping the timer on any other task first
+export async function startTimer(taskId: number): Promise<ActiveTimer> {
+  return await invoke<ActiveTimer>('start_timer', { taskId });
+}
+
+// Adds the time since the timer started to the task's time_spent
+export async function stopTimer(): Promise<void> {
+  await invoke('stop_timer');
+}
+
+export async function getActiveTimer(): Promise<ActiveTimer | null> {
+  return await invoke<ActiveTimer | null>('get_active_timer');
+}
+
+export type PomodoroPhase = 'work' | 'short_break' | 'long_break';
+
+export interface PomodoroStatus {
+  phase: PomodoroPhase;
+  // Seconds left in the phase
+  remaining: number;
+  paused: boolean;
+  // Work phases finished since it started
+  sessions: number;
+}
+
+// Starts a pomodoro with a work phase, or resumes the paused one
+export async function startPomodoro(): Promise<PomodoroStatus> {
+  return await invoke<PomodoroStatus>('start_pomodoro');
+}
+
+export async function pausePomodoro(): Promise<PomodoroStatus> {
+  return await invoke<PomodoroStatus>('pause_pomodoro');
+}
+
+// Ends the phase now and starts the next one
+export async function skipPomodoro(): Promise<PomodoroStatus> {
+  return await invoke<PomodoroStatus>('skip_pomodoro');
+}
+
+export async function stopPomodoro(): Promise<void> {
+  await invoke('stop_pomodoro');
+}
+
+export async function getPomodoro(): Promise<PomodoroStatus | null> {
+  return await invoke<PomodoroStatus | null>('get_pomodoro');
+}
+
+// "25:03", or "1:02:03" past an hour
+export const formatDuration = (seconds: number) => {
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = String(seconds % 60).padStart(2, '0');
+  return h > 0 ? `${h}:${String(m).padStart(2, '0')}:${s}` : `${m}:${s}`;
+};
+
+export async function getWindowAppearance(): Promise<WindowAppearance> {
+  return await invoke<WindowAppearance>('get_window_appearance');
+}
+
+export async function setWindowAppearance(appearance: WindowAppearance): Promise<void> {
+  await invoke('set_window_appearance', { appearance });
+}
+
+export async function getWindowMargins(): Promise<Partial<Record<WindowAnchor, WindowMargin>>> {
+  return await invoke<Partial<Record<WindowAnchor, WindowMargin>>>('get_window_margins');
+}
+
+// Forget where the window was dragged on each screen so the anchor applies again
+export async function clearScreenPositions(): Promise<void> {
+  await invoke('clear_screen_positions');
+}
+
+export async function getDnd(): Promise<boolean> {
+  return await invoke<boolean>('get_dnd');
+}
+
+export async function setDnd(enabled: boolean): Promise<void> {
+  await invoke('set_dnd', { enabled });
+}
+
+export async function getHotkeysSuspended(): Promise<boolean> {
+  return await invoke<boolean>('get_hotkeys_suspended');
+}
+
+export async function suspendHotkeys(): Promise<void> {
+  await invoke('suspend_hotkeys');
+}
+
+// Rejects listing any shortcut that couldn't be registered again
+export async function resumeHotkeys(): Promise<void> {
+  await invoke('resume_hotkeys');
+}
+
+// Fired when the backend changes tasks on its own (e.g. completing from the tray)
+export async function onTasksChanged(handler: (state: TaskState) => void): Promise<UnlistenFn> {
+  return await listen<TaskState>('tasks-changed', (event) => handler(event.payload));
+}
+
+// Fired in the focus-bar window when the top current task changes
+export async function onFocusTaskChanged(handler: (task: string | null) => void): Promise<UnlistenFn> {
+  return await listen<string | null>('focus-task-changed', (event) => handler(event.payload));
+}
+
+// Fired when the timer starts, stops or moves to another task; null when it's stopped
+export async function onTimerChanged(handler: (timer: ActiveTimer | null) => void): Promise<UnlistenFn> {
+  return await listen<ActiveTimer | null>('timer-changed', (event) => handler(event.payload));
+}
+
+// Fired every second while the timer runs, whether or not the window is showing
+export async function onTimerTick(handler: (timer: ActiveTimer) => void): Promise<UnlistenFn> {
+  return await listen<ActiveTimer>('timer-tick', (event) => handler(event.payload));
+}
+
+// Fired on every pomodoro phase change, pause, resume and stop; null once it's stopped
+export async function onPomodoroChanged(handler: (status: PomodoroStatus | null) => void): Promise<UnlistenFn> {
+  return await listen<PomodoroStatus | null>('pomodoro-changed', (event) => handler(event.payload));
+}
+
+// Fired in the capture window each time it's shown
+export async function onCaptureOpened(handler: () => void): Promise<UnlistenFn> {
+  return await listen('capture-opened', () => handler());
+}
+
+// Fired when do-not-disturb is toggled (from the tray or the UI)
+export async function onDndChanged(handler: (enabled: boolean) => void): Promise<UnlistenFn> {
+  return await listen<boolean>('dnd-changed', (event) => handler(event.payload));
+}
+
+// Fired when global shortcuts are suspended or resumed (from the tray or the UI)
+export async function onHotkeysSuspendedChanged(handler: (suspended: boolean) => void): Promise<UnlistenFn> {
+  return await listen<boolean>('hotkeys-suspended-changed', (event) => handler(event.payload));
+}
+
+// Fired when a shortcut registers or fails to (e.g. the key is taken by another app)
+export async function onHotkeyStatusChanged(
+  handler: (status: Partial<Record<HotkeyAction, HotkeyStatus>>) => void,
+): Promise<UnlistenFn> {
+  return await listen<Partial<Record<HotkeyAction, HotkeyStatus>>>('hotkey-status-changed', (event) =>
+    handler(event.payload),
+  );
+}
+
+// Fired when the window is pinned or unpinned
+export async function onPinnedChanged(handler: (pinned: boolean) => void): Promise<UnlistenFn> {
+  return await listen<boolean>('pinned-changed', (event) => handler(event.payload));
+}
+
+// Fired when the window opacity or effect changes
+export async function getTheme(): Promise<ThemeConfig> {
+  return await invoke<ThemeConfig>('get_theme');
+}
+
+// Also sets the native window theme and the tray icon's colors
+export async function setTheme(theme: ThemeConfig): Promise<void> {
+  await invoke('set_theme', { theme });
+}
+
+export async function onThemeChanged(handler: (theme: ThemeConfig) => void): Promise<UnlistenFn> {
+  return await listen<ThemeConfig>('theme-changed', (event) => handler(event.payload));
+}
+
+export async function onWindowAppearanceChanged(handler: (appearance: WindowAppearance) => void): Promise<UnlistenFn> {
+  return await listen<WindowAppearance>('window-appearance-changed', (event) => handler(event.payload));
+}
+
+// Tasks past their due time, longest overdue first
+export async function getOverdueTasks(): Promise<Task[]> {
+  return await invoke<Task[]>('get_overdue_tasks');
+}
+
+export async function getDaySummary(): Promise<DaySummary> {
+  return await invoke<DaySummary>('get_day_summary');
+}
+
+// Recounts today's completions against the daily goal
+export async function getDailyGoalProgress(): Promise<GoalProgress> {
+  return await invoke<GoalProgress>('get_daily_goal_progress');
+}
+
+// Every completed task from the structured log, oldest first
+export async function getDoneRecords(): Promise<DoneRecord[]> {
+  return await invoke<DoneRecord[]>('get_done_records');
+}
+
+// Latest done.md entries, newest first, for uncompleteTask
+export async function getRecentDone(limit: number): Promise<DoneEntry[]> {
+  return await invoke<DoneEntry[]>('get_recent_done', { limit });
+}
+
+// Take a completed task out of the done log and back into current
+export async function uncompleteTask(entry: DoneEntry): Promise<Task> {
+  return await invoke<Task>('uncomplete_task', { entryId: entry.line, date: entry.date, text: entry.task.text });
+}
+
+export async function getDoneLog(): Promise<DoneLogConfig> {
+  return await invoke<DoneLogConfig>('get_done_log');
+}
+
+// Rejects with a message when the path can't hold a done log
+export async function setDoneLog(doneLog: DoneLogConfig): Promise<void> {
+  await invoke('set_done_log', { doneLog });
+}
+
+// Summarize the week containing `week` (YYYY-MM-DD, default this week) into rollup_<year>-W<nn>.md
+export async function generateWeeklyRollup(week?: string): Promise<WeeklyRollup> {
+  return await invoke<WeeklyRollup>('generate_weekly_rollup', { week });
+}
+
+// Drop same-day duplicates from the done log, optionally merging another done file in first
+export async function dedupeDone(mergeFrom?: string): Promise<DedupeReport> {
+  return await invoke<DedupeReport>('dedupe_done', { mergeFrom });
+}
+
+// Apply archive_retention from config; with dryRun nothing is touched
+export async function pruneArchives(dryRun: boolean): Promise<PruneAction[]> {
+  return await invoke<PruneAction[]>('prune_archives', { dryRun });
+}
+
+// Stitch everything completed in `range` into one file; .org and .csv paths get those formats.
+// Resolves to the number of entries written
+export async function exportHistory(range: DateRange, path: string): Promise<number> {
+  return await invoke<number>('export_history', { range, path });
+}
+
+export async function listArchives(): Promise<ArchiveInfo[]> {
+  return await invoke<ArchiveInfo[]>('list_archives');
+}
+
+export async function readArchive(name: string): Promise<DoneEntry[]> {
+  return await invoke<DoneEntry[]>('read_archive', { name });
+}
+
+// Tasks and done.md entries whose text or notes fuzzily match `query` ("dply web" finds
+// "Deploy website"), best match first. With `regex`, `query` is a case-insensitive
+// pattern (e.g. `PROJ-\d+`) and throws if it doesn't compile
+export async function search(query: string, regex = false): Promise<SearchResult[]> {
+  return await invoke<SearchResult[]>('search', { query, regex });
+}
+
+// Tasks in current and on the shelf matching a filter such as
+// `tag:work due:<friday priority:high -shelved`; throws on a malformed expression
+export async function queryTasks(expr: string): Promise<QueryMatch[]> {
+  return await invoke<QueryMatch[]>('query_tasks', { expr });
+}
+
+// Past task texts, #tags and projects starting with what's typed ("stand" → "Standup notes"),
+// most used first; a "#" prefix only suggests tags
+export async function suggest(prefix: string, limit?: number): Promise<Suggestion[]> {
+  return await invoke<Suggestion[]>('suggest', { prefix, limit });
+}
+
+// Open tasks filtered and sorted in the backend; `index` in each result still points into current/shelf
+export async function getTasksView(options: ViewOptions): Promise<QueryMatch[]> {
+  return await invoke<QueryMatch[]>('get_tasks_view', { options });
+}
+
+export async function getSavedFilters(): Promise<SavedFilter[]> {
+  return await invoke<SavedFilter[]>('get_saved_filters');
+}
+
+// Adds a filter, or replaces the query of the one with the same name; throws if the query doesn't parse
+export async function saveFilter(name: string, query: string): Promise<void> {
+  await invoke('save_filter', { name, query });
+}
+
+export async function deleteFilter(name: string): Promise<void> {
+  await invoke('delete_filter', { name });
+}
+
+// Every saved filter with the tasks it matches right now
+export async function getSmartLists(): Promise<SmartList[]> {
+  return await invoke<SmartList[]>('get_smart_lists');
+}
+
+// Completion counts for charts; an open range starts at the oldest completion and ends today
+export async function getStats(range?: DateRange, granularity: Granularity = 'day'): Promise<Stats> {
+  return await invoke<Stats>('get_stats', { range, granularity });
+}
+
+export async function getHeatmap(year: number): Promise<Heatmap> {
+  return await invoke<Heatmap>('get_heatmap', { year });
+}
+
+export async function getFlowMetrics(range?: DateRange): Promise<FlowMetrics> {
+  return await invoke<FlowMetrics>('get_flow_metrics', { range });
+}
+
+export async function getCompletionPatterns(range?: DateRange): Promise<CompletionPatterns> {
+  return await invoke<CompletionPatterns>('get_completion_patterns', { range });
+}
+
+// This week so far, for the Friday review; `write` also saves it as review_<year>-W<week>.md
+export async function weeklyReview(write = false): Promise<WeeklyReview> {
+  return await invoke<WeeklyReview>('weekly_review', { write });
+}
+
+// "At your current pace, the 14 remaining tasks in X will take ~9 working days" for 'current', 'shelf' or a project
+export async function estimateCompletion(listOrProject: string): Promise<Forecast> {
+  return await invoke<Forecast>('estimate_completion', { listOrProject });
+}
+
+// A year's totals and highlights with a markdown write-up; write also saves review_<year>.md
+export async function yearInReview(year: number, write = false): Promise<YearInReview> {
+  return await invoke<YearInReview>('year_in_review', { year, write });
+}
+
+export async function getBreakdown(dimension: 'tag' | 'project', range?: DateRange): Promise<Breakdown> {
+  return await invoke<Breakdown>('get_breakdown', { dimension, range });
+}
+
+// Open vs done per day for one project's tasks; an open range runs from its first task to today
+export async function getBurndown(project: string, range?: DateRange): Promise<BurndownDay[]> {
+  return await invoke<BurndownDay[]>('get_burndown', { project, range });
+}
+
+// Writes a metric to an absolute (or ~/) path for spreadsheets or notebooks; resolves to the row count
+export async function exportStats(
+  metric: StatsMetric,
+  format: 'csv' | 'json',
+  path: string,
+  range?: DateRange,
+): Promise<number> {
+  return await invoke<number>('export_stats', { metric, range, format, path });
+}
+
+// Notes of one task matching `query`, best first, to jump to among many. `index` is the
+// position in current/shelf, or for 'done' the line id `search` returned
+export async function searchNotes(
+  list: SearchResult['list'],
+  index: number,
+  query: string,
+  regex = false,
+): Promise<NoteMatch[]> {
+  return await invoke<NoteMatch[]>('search_notes', { list, index, query, regex });
+}
+
+// Completed tasks mentioning `query` (or matching it as a pattern, with `regex`) across
+// done.md and every archive, newest first
+export async function searchDone(query: string, range?: DateRange, regex = false): Promise<DoneMatch[]> {
+  return await invoke<DoneMatch[]>('search_done', { query, range, regex });
+}
+
+// Fired when the tray asks for today's summary
+export async function onOpenSummary(handler: () => void): Promise<UnlistenFn> {
+  return await listen('open-summary', () => handler());
+}
+
+// Fired at the configured planning time, after the window has been shown
+export async function onOpenPlanning(handler: () => void): Promise<UnlistenFn> {
+  return await listen('open-planning', () => handler());
+}
+
+// Fired when the tray asks for the settings panel
+export async function onOpenSettings(handler: () => void): Promise<UnlistenFn> {
+  return await listen('open-settings', () => handler());
+}
+
+// Fired with the new settings whenever they change, including hand edits to config.json
+export async function onConfigChanged(handler: (config: AppConfig) => void): Promise<UnlistenFn> {
+  return await listen<AppConfig>('config-changed', (event) => handler(event.payload));
+}